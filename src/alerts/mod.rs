@@ -0,0 +1,5 @@
+// Push notifications that tell craftsmen about new tasks, either because the task matches one of
+// their saved searches or because it is in their service area.
+
+mod published;
+pub use published::{notify_user, task_published};
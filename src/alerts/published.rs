@@ -0,0 +1,197 @@
+use crate::geo::{craftsmen_serving, sql_area_covers};
+use crate::models::{AlertFrequency, Craftsman, PublishStatus, SavedSearch, Task, User};
+use crate::push::send_custom_pn;
+use crate::tax;
+use crate::util::log;
+use crate::{
+    CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, SAVED_SEARCH_COLLECTION, TASK_COLLECTION,
+    USER_COLLECTION,
+};
+use cosmos_utils::{get, maybe_modify, modify, query, ModifyReturn};
+use std::collections::HashMap;
+
+// MAGIC NUMBER: More alerts than this a day from one search, or about the service area of a
+// craftsman without searches, and the craftsman stops reading them, the rest wait for the digest
+const MAX_ALERTS_PER_DAY: u32 = 5;
+
+// Sends a PN to a user, only logging if it fails
+pub async fn notify_user(user_id: &str, text: &str) {
+    let user: Result<(User, _), _> = get(USER_COLLECTION, [user_id], user_id).await;
+    match user {
+        Ok((user, _)) => {
+            if let Err(e) = send_custom_pn(&user, text, None, &NOTIFICATION_HUB_ACCOUNT).await {
+                log(format!("Could not send task alert PN due to {}", e));
+            }
+        }
+        Err(e) => log(format!(
+            "Could not send task alert PN due to get failing with {}",
+            e
+        )),
+    }
+}
+
+// Alerts the craftsmen that should hear about a task that has been published. Craftsmen are only
+// alerted the first time a task is published.
+pub async fn task_published(office_id: &str, task_id: &str) -> Result<(), warp::Rejection> {
+    // NOTE: The flag is set first so that a task that is published again, or by two requests at
+    // once, only alerts once
+    let marked = maybe_modify(TASK_COLLECTION, [office_id], task_id, |mut task: Task| {
+        if task.search_alerts_sent
+            || task.deleted
+            || !matches!(task.publish_status, PublishStatus::Published)
+        {
+            return Ok(ModifyReturn::DontReplace(task));
+        }
+        task.search_alerts_sent = true;
+        Ok(ModifyReturn::Replace(task))
+    })
+    .await?;
+    let task = match marked {
+        ModifyReturn::Replace(task) => task,
+        ModifyReturn::DontReplace(_) => return Ok(()),
+    };
+
+    let area = match &task.location {
+        Some(location) => format!(
+            "(NOT IS_DEFINED(s.area) OR IS_NULL(s.area) OR {})",
+            sql_area_covers("s.area", location)
+        ),
+        None => String::from("(NOT IS_DEFINED(s.area) OR IS_NULL(s.area))"),
+    };
+    let q = format!(
+        r#"SELECT * FROM {} s WHERE (NOT IS_DEFINED(s.deleted) OR s.deleted = false) AND s.frequency != "Off" AND {}"#,
+        SAVED_SEARCH_COLLECTION, area
+    );
+    let q_searchers = format!(
+        r#"SELECT VALUE s.craftsmanId FROM {} s WHERE NOT IS_DEFINED(s.deleted) OR s.deleted = false"#,
+        SAVED_SEARCH_COLLECTION
+    );
+    let (s, sc, c) = tokio::join!(
+        query(SAVED_SEARCH_COLLECTION, [office_id], q, -1),
+        query(SAVED_SEARCH_COLLECTION, [office_id], q_searchers, -1),
+        craftsmen_serving(office_id, &task)
+    );
+    let searches: Vec<SavedSearch> = s?;
+    let searchers: Vec<String> = sc?;
+    let serving = c?;
+
+    let mut by_craftsman: HashMap<String, Vec<SavedSearch>> = HashMap::new();
+    for search in searches.into_iter().filter(|s| s.matches(&task)) {
+        by_craftsman
+            .entry(search.craftsman_id.clone())
+            .or_insert_with(Vec::new)
+            .push(search);
+    }
+    for (craftsman_id, searches) in by_craftsman.iter() {
+        if let Err(e) = alert(office_id, craftsman_id, searches, &task).await {
+            log(format!(
+                "Could not alert craftsman {} about task {} due to {:?}",
+                craftsman_id, task.id, e
+            ));
+        }
+    }
+
+    // NOTE: Craftsmen that have not saved any searches hear about every task in their service area
+    for craftsman in serving.iter().filter(|c| !searchers.contains(&c.id)) {
+        if let Err(e) = area_alert(office_id, &craftsman.id, &task).await {
+            log(format!(
+                "Could not alert craftsman {} about task {} in their area due to {:?}",
+                craftsman.id, task.id, e
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Alerts a craftsman about a task in their service area, or puts it on their digest once the daily
+// cap is reached
+async fn area_alert(
+    office_id: &str,
+    craftsman_id: &str,
+    task: &Task,
+) -> Result<(), warp::Rejection> {
+    let today = tax::today();
+    let craftsman = modify(
+        CRAFTSMAN_COLLECTION,
+        [office_id],
+        craftsman_id,
+        |mut craftsman: Craftsman| {
+            let sent = craftsman.area_alerts_sent_on(today);
+            if sent < MAX_ALERTS_PER_DAY {
+                craftsman.area_alerts_sent = sent + 1;
+                craftsman.area_alerts_sent_on = Some(today);
+            } else if !craftsman.pending_area_task_ids.contains(&task.id) {
+                craftsman.pending_area_task_ids.push(task.id.clone());
+            }
+            Ok(craftsman)
+        },
+    )
+    .await?;
+    if !craftsman.pending_area_task_ids.contains(&task.id) {
+        notify_user(
+            &craftsman.user_id,
+            &format!("Nytt jobb i ditt område: {}", task.title),
+        )
+        .await;
+    }
+    Ok(())
+}
+
+// A craftsman gets one alert per task however many of their searches match it
+async fn alert(
+    office_id: &str,
+    craftsman_id: &str,
+    searches: &[SavedSearch],
+    task: &Task,
+) -> Result<(), warp::Rejection> {
+    let (craftsman, _): (Craftsman, _) =
+        get(CRAFTSMAN_COLLECTION, [office_id], craftsman_id).await?;
+    if craftsman.deleted || craftsman.frozen || !craftsman.can_bid_on(task) {
+        return Ok(());
+    }
+
+    let today = tax::today();
+    let immediate = searches.iter().find(|s| {
+        s.frequency == AlertFrequency::Immediate && s.alerts_sent_on(today) < MAX_ALERTS_PER_DAY
+    });
+    match immediate {
+        Some(search) => {
+            modify(
+                SAVED_SEARCH_COLLECTION,
+                [office_id],
+                &search.id,
+                |mut search: SavedSearch| {
+                    search.alerts_sent = search.alerts_sent_on(today) + 1;
+                    search.alerts_sent_on = Some(today);
+                    Ok(search)
+                },
+            )
+            .await?;
+            notify_user(
+                &craftsman.user_id,
+                &format!("Nytt jobb som matchar \"{}\": {}", search.name, task.title),
+            )
+            .await;
+        }
+        None => {
+            // NOTE: The task is only put on one of the digests so it is not counted twice
+            let search = searches
+                .iter()
+                .find(|s| s.frequency == AlertFrequency::DailyDigest)
+                .unwrap_or(&searches[0]);
+            modify(
+                SAVED_SEARCH_COLLECTION,
+                [office_id],
+                &search.id,
+                |mut search: SavedSearch| {
+                    if !search.pending_task_ids.contains(&task.id) {
+                        search.pending_task_ids.push(task.id.clone());
+                    }
+                    Ok(search)
+                },
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
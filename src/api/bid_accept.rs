@@ -1,33 +1,49 @@
 use crate::fault::Fault;
-use crate::models::{Bid, Claims, Currency, Payment, PaymentMethod, PaymentState, Task, User};
-use crate::util::{DataResponse, Empty};
-use crate::{
-    BASE_CALLBACK_URL, BID_COLLECTION, PAYMENT_COLLECTION, SWISH_CERT_PASS, SWISH_CERT_PATH,
-    SWISH_INTERMEDIATE_ACCOUNT_NUMBER, TASK_COLLECTION, USER_COLLECTION,
+use crate::models::{
+    Bid, Claims, Payment, PaymentMethod, PaymentState, Task, TaskActor, TaskState, User,
 };
+use crate::payment::{self, Initiated};
+use crate::tax;
+use crate::util::{log, DataRequest, DataResponse, Empty};
+use crate::{BID_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION};
 use chrono::Utc;
-use cosmos_utils::{get, insert, query};
-use serde::Serialize;
-use swish::SwishClient;
+use cosmos_utils::{get, insert, maybe_modify, modify, query, ModifyReturn};
+use serde::{Deserialize, Serialize};
 use tokio::join;
-use uuid::Uuid;
 use warp::reject;
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct Response {
-    #[serde(skip_serializing_if = "crate::util::is_none")]
-    payment_request_token: Option<String>,
+    #[serde(flatten)]
+    initiated: Initiated,
     payment_id: String,
 }
 
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptBid {
+    #[serde(default)]
+    payment_method: PaymentMethod,
+    // A campaign or referral code
+    #[serde(default)]
+    promo_code: Option<String>,
+    // Whether to take the referral credit of the customer off the price
+    #[serde(default)]
+    use_credit: bool,
+}
+
 pub async fn bid_accept(
     office_id: String,
     task_id: String,
     bid_id: String,
+    r: DataRequest<AcceptBid, Empty>,
     claims: Claims,
     _v: u8,
 ) -> Result<impl warp::Reply, warp::Rejection> {
+    // NOTE: The customer pays with swish unless anything else is asked for
+    let accept = r.data.unwrap_or_default();
+
     let q = format!(
         r#"SELECT * FROM {} p WHERE p.bidId = "{}""#,
         PAYMENT_COLLECTION, bid_id
@@ -37,7 +53,7 @@ pub async fn bid_accept(
         get(BID_COLLECTION, [&office_id], &bid_id),
         query(PAYMENT_COLLECTION, [&office_id], q, -1)
     );
-    let (task, _): (Task, _) = t?;
+    let (mut task, _): (Task, _) = t?;
     let (bid, _): (Bid, _) = b?;
     let payments: Vec<Payment> = p?;
     let (task_owner, _): (User, _) = get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
@@ -58,91 +74,154 @@ pub async fn bid_accept(
             "Only the task poster may accept bids",
         ))));
     }
-    if let Some(bid_id) = task.accepted_bid {
+    if bid.is_withdrawn() {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The bid has been withdrawn by the craftsman",
+        ))));
+    }
+    if bid.is_expired(Utc::now()) {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The bid is no longer valid, it has to be renewed by the craftsman",
+        ))));
+    }
+    if let Some(bid_id) = &task.accepted_bid {
         return Err(reject::custom(Fault::Forbidden(format!(
             "Already accepted a bid from bid id {}",
             bid_id
         ))));
     }
 
-    // NOTE: We need to format the ID as a simple string without hyphens in order for swish to
-    // accept it. It also *MUST* be upper-case letters.
-    let payment_id = Uuid::new_v4();
+    // NOTE: Checked on our copy first so that no payment is started for a task that can not
+    // accept bids
+    accept_on(&mut task, &claims)?;
 
-    let payment = Payment {
-        id: payment_id.to_string(),
-        office_id: office_id.clone(),
-        task_id: task_id.clone(),
-        bid_id: bid_id.clone(),
-        craftsman_id: bid.craftsman_id,
-        swish_payment_id: None,
-        payment_date: None,
-        payment_state: PaymentState::Initialized,
-        payment_method: PaymentMethod::Swish,
-        amount: bid.final_bid,
-        currency: Currency::SEK,
-        modified: Utc::now(),
-        deleted: false,
-    };
+    // NOTE: The customer may have used up some of the yearly deduction since the bid was made. The
+    // payment is made today, so the deduction counts towards this year.
+    let today = tax::today();
+    let rules = tax::rules_at(today);
+    let (deduction_type, allowance) = tax::task_deduction(rules, &task, today).await?;
+    if let Some(allowance) = allowance {
+        if bid.root_deduction > allowance {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "The deduction of the bid {} is more than the {} the customer has left this year",
+                bid.root_deduction, allowance
+            ))));
+        }
+    }
 
-    let mut uuid_encode_buf = Uuid::encode_buffer();
-    let id = &payment_id.to_simple().encode_upper(&mut uuid_encode_buf);
-    // Make payment to intermediate account, will create a payment on success
-    // https://toolit-api-play.azurewebsites.net/offices/<id>/tasks/<id>/bids/<id>/payments/<id>
-    let callback_url = format!(
-        "{}/offices/{}/tasks/{}/bids/{}/payments/{}/escrow",
-        BASE_CALLBACK_URL.as_str(),
-        office_id,
-        task_id,
-        bid_id,
-        payment_id.to_string()
+    // NOTE: A bid with milestones starts out with the payment of the first milestone, the rest
+    // are paid as the work goes on
+    let mut payment = payment::new_payment(
+        &bid,
+        bid.first_milestone(),
+        accept.payment_method,
+        deduction_type,
     );
+    payment::apply_discount(
+        &mut payment,
+        &bid,
+        &task_owner,
+        accept.promo_code.as_deref(),
+        accept.use_credit,
+    )
+    .await?;
 
-    // FIXME(Jonathan): This is old code from when we sent a phone number
-    //// Swish requires a special format, like MSISDN but without a plus sign.
-    //let mut payer_alias = task_owner.phone.clone();
-    //if payer_alias.starts_with('+') {
-    //    payer_alias = payer_alias.chars().into_iter().skip(1).collect();
-    //}
-
-    // Initialize the swish payment
-    let swish_client = SwishClient::new(SWISH_CERT_PATH.as_str(), &SWISH_CERT_PASS, None, None)
+    let initiated = match payment::provider(&payment.payment_method)
+        .initiate(&mut payment, &task_owner)
         .await
-        .map_err(|e| Fault::from(e))?;
-    // NOTE: We need to format the ID as a simple string without hyphens in order for swish to
-    // accept it. It also *MUST* be upper-case letters.
-    let payment_req = swish::PaymentRequest::V2(swish::PaymentRequestV2 {
-        id,
-        callback_url: &callback_url,
-        payee_alias: &SWISH_INTERMEDIATE_ACCOUNT_NUMBER,
-        amount: bid.final_bid,
-        currency: swish::Currency::SEK,
-        payee_payment_reference: None,
-        // NOTE: We are setting payer-alias to None in order to use the m-commerce feature and get
-        // a token back
-        // TODO(Jonathan): Remove the commented line below, it's only useful if we don't want the
-        // autostarttoken
-        //payer_alias: Some(&task_owner.phone),
-        payer_alias: None,
-        payer_ssn: Some(&task_owner.nid),
-        //payer_ssn: &payer.nid,
-        payer_age_limit: None,
-        message: Some(format!("Betalning för avslutat toolit arbete")),
-    });
-
-    let resp = swish_client
-        .payment_request(payment_req)
-        .await
-        .map_err(|e| Fault::from(e))?;
+    {
+        Ok(initiated) => initiated,
+        Err(e) => {
+            payment::release_discount(&payment).await;
+            return Err(e);
+        }
+    };
 
     // Insert initialized payment
-    insert(PAYMENT_COLLECTION, [&payment.office_id], &payment, None).await?;
+    if let Err(e) = insert(PAYMENT_COLLECTION, [&payment.office_id], &payment, None).await {
+        withdraw(&payment, false).await;
+        return Err(e.into());
+    }
+
+    // NOTE: Another bid may have been accepted while the payment was initiated, the customer must
+    // then not be able to pay for this one
+    let accepted = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
+        accept_on(&mut task, &claims)?;
+        Ok(task)
+    })
+    .await;
+    if let Err(e) = accepted {
+        withdraw(&payment, true).await;
+        return Err(e.into());
+    }
 
     Ok(warp::reply::json(&DataResponse {
         data: Some(&Response {
-            payment_request_token: resp.payment_request_token,
-            payment_id: payment_id.to_string(),
+            initiated,
+            payment_id: payment.id.clone(),
         }),
         extra: None::<Empty>,
     }))
 }
+
+// Moves the task to having accepted a bid. Only one bid at a time can be paid for, so a task that
+// has already accepted one is refused.
+fn accept_on(task: &mut Task, claims: &Claims) -> Result<(), warp::Rejection> {
+    match task.transition(
+        TaskState::BidAccepted,
+        TaskActor::Customer,
+        Some(&claims.sub),
+        None,
+    ) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(reject::custom(Fault::Forbidden(format!(
+            "The task is already waiting for the payment of an accepted bid"
+        )))),
+        Err(e) => Err(reject::custom(Fault::Forbidden(format!(
+            "The bid can not be accepted: {}",
+            e
+        )))),
+    }
+}
+
+// Withdraws a payment that was initiated for an accept that did not go through, giving back the
+// discount it took. The task is left as it is since it may be waiting for the payment of another
+// bid.
+async fn withdraw(payment: &Payment, stored: bool) {
+    if let Err(e) = payment::provider(&payment.payment_method)
+        .cancel(payment)
+        .await
+    {
+        log(format!(
+            "Could not withdraw payment {} of a failed accept due to {:?}",
+            payment.id, e
+        ));
+    }
+    if !stored {
+        payment::release_discount(payment).await;
+        return;
+    }
+    // NOTE: The discount is only given back by whoever marks the payment as failed
+    let failed = maybe_modify(
+        PAYMENT_COLLECTION,
+        [&payment.office_id],
+        &payment.id,
+        |mut payment: Payment| {
+            if !matches!(payment.payment_state, PaymentState::Initialized) {
+                return Ok(ModifyReturn::DontReplace(payment));
+            }
+            payment.payment_state = PaymentState::Failed;
+            payment.modified = Utc::now();
+            Ok(ModifyReturn::Replace(payment))
+        },
+    )
+    .await;
+    match failed {
+        Ok(ModifyReturn::Replace(payment)) => payment::release_discount(&payment).await,
+        Ok(ModifyReturn::DontReplace(_)) => {}
+        Err(e) => log(format!(
+            "Could not mark payment {} of a failed accept as failed due to {}",
+            payment.id, e
+        )),
+    }
+}
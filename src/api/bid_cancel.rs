@@ -1,69 +1,49 @@
-use crate::fault::Fault;
-use crate::models::{Bid, Claims, Task};
-use crate::util::{DataResponse, Empty};
-use crate::{BID_COLLECTION, TASK_COLLECTION};
-use cosmos_utils::modify;
-use serde::Serialize;
-use warp::reject;
+use crate::cancel;
+use crate::models::{Bid, Cancellation, Claims, Task};
+use crate::util::{DataRequest, DataResponse, Empty};
+use serde::{Deserialize, Serialize};
+
+// NOTE: Clients from before cancellations were recorded do not give a reason
+const DEFAULT_REASON: &str = "Ingen anledning angiven";
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelBid {
+    #[serde(default)]
+    reason: String,
+}
 
 #[derive(Serialize)]
 struct Response {
     bid: Bid,
     task: Task,
+    cancellation: Cancellation,
 }
 
+// Cancels the accepted bid of a task, which opens the task for bids again
 pub async fn bid_cancel(
     office_id: String,
     task_id: String,
     bid_id: String,
+    r: DataRequest<CancelBid, Empty>,
     claims: Claims,
     _v: u8,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let task = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
-        if claims.sub != task.user_id {
-            return Err(reject::custom(Fault::Forbidden(format!(
-                "Only the task poster may cancel bids",
-            ))));
-        }
-        if task.finished {
-            return Err(reject::custom(Fault::Forbidden(format!(
-                "May not cancel bids for finished tasks",
-            ))));
-        }
-        if let Some(accepted_bid) = task.accepted_bid {
-            if accepted_bid != bid_id {
-                return Err(reject::custom(Fault::Forbidden(format!(
-                    "Task has not accepted a bid from {}",
-                    bid_id
-                ))));
-            }
-        } else {
-            return Err(reject::custom(Fault::Forbidden(format!(
-                "Task has not accepted a bid from {}",
-                bid_id
-            ))));
-        }
-
-        task.accepted_bid = None;
-        Ok(task)
-    })
-    .await?;
-
-    let bid = match modify(BID_COLLECTION, [&office_id], &bid_id, |mut bid: Bid| {
-        bid.is_cancelled = true;
-        Ok(bid)
-    })
-    .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            // TODO: we need to reverse the previous change in the task here
-            return Err(e.into());
-        }
+    let cancel_bid = r.data.unwrap_or_default();
+    let reason = match cancel_bid.reason.trim() {
+        "" => DEFAULT_REASON,
+        reason => reason,
     };
 
+    let (task, bid, cancellation) =
+        cancel::cancel_accepted_bid(&office_id, &task_id, &bid_id, &claims, reason).await?;
+
     Ok(warp::reply::json(&DataResponse {
-        data: Some(&Response { bid, task }),
+        data: Some(&Response {
+            bid,
+            task,
+            cancellation,
+        }),
         extra: None::<Empty>,
     }))
 }
@@ -0,0 +1,225 @@
+use crate::fault::Fault;
+use crate::models::{Bid, BidLine, Claims, Craftsman, RoleFlags, Task};
+use crate::util::{has_role, is_empty, is_none, DataResponse, Empty};
+use crate::{BID_COLLECTION, CRAFTSMAN_COLLECTION, TASK_COLLECTION};
+use chrono::{DateTime, NaiveDate, Utc};
+use cosmos_utils::{get, query};
+use rust_decimal::prelude::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use warp::reject;
+
+// MAGIC NUMBER: Keeps the queries for the bids and tasks of the craftsmen well below the size limit
+// of Cosmos
+const IDS_PER_QUERY: usize = 500;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Comparison {
+    // The customer's own estimate of the price of the task
+    estimate: Decimal,
+    // Cheapest first
+    bids: Vec<ComparedBid>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparedBid {
+    bid_id: String,
+    craftsman_id: String,
+    company_name: String,
+    bid_message: String,
+    total: Decimal,
+    labour_cost: Decimal,
+    material_cost: Decimal,
+    vat: Decimal,
+    root_deduction: Decimal,
+    #[serde(skip_serializing_if = "is_none")]
+    labour_hours: Option<Decimal>,
+    #[serde(skip_serializing_if = "is_empty")]
+    lines: Vec<BidLine>,
+    rating_count: usize,
+    // Out of the same scale as the ratings, missing when the craftsman has not been rated
+    #[serde(skip_serializing_if = "is_none")]
+    rating_average: Option<Decimal>,
+    completed_jobs: usize,
+    f_tax: bool,
+    #[serde(skip_serializing_if = "is_none")]
+    earliest_start: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "is_none")]
+    valid_until: Option<DateTime<Utc>>,
+    // The total minus the estimate, negative when the bid is cheaper than the estimate
+    difference_from_estimate: Decimal,
+    #[serde(skip_serializing_if = "is_none")]
+    difference_percentage: Option<Decimal>,
+}
+
+// The bids of a task that can still be accepted side by side, for the customer to compare
+pub async fn bid_comparison_get(
+    office_id: String,
+    task_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+    if task.user_id != claims.sub
+        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only the task poster may compare the bids of a task"
+        ))));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} b WHERE b.taskId = "{}" AND (NOT IS_DEFINED(b.deleted) OR b.deleted = false) AND b.isCancelled = false AND (NOT IS_DEFINED(b.withdrawal) OR IS_NULL(b.withdrawal))"#,
+        BID_COLLECTION, task_id
+    );
+    let bids: Vec<Bid> = query(BID_COLLECTION, [&office_id], q, -1).await?;
+    let now = Utc::now();
+    let bids: Vec<Bid> = bids.into_iter().filter(|b| !b.is_expired(now)).collect();
+
+    let mut craftsman_ids: Vec<String> = bids
+        .iter()
+        .map(|b| format!(r#""{}""#, b.craftsman_id))
+        .collect();
+    craftsman_ids.sort();
+    craftsman_ids.dedup();
+    let mut craftsmen = HashMap::new();
+    if !craftsman_ids.is_empty() {
+        let q = format!(
+            r#"SELECT * FROM {} c WHERE ARRAY_CONTAINS([{}], c.id)"#,
+            CRAFTSMAN_COLLECTION,
+            craftsman_ids.join(",")
+        );
+        let found: Vec<Craftsman> = query(CRAFTSMAN_COLLECTION, [&office_id], q, -1).await?;
+        for craftsman in found {
+            craftsmen.insert(craftsman.id.clone(), craftsman);
+        }
+    }
+    let completed = completed_jobs(&office_id, &craftsman_ids).await?;
+
+    let mut compared: Vec<ComparedBid> = bids
+        .into_iter()
+        .map(|bid| {
+            let bid_craftsman = bid.craftsman_id.clone();
+            let craftsman = craftsmen.get(&bid.craftsman_id);
+            let ratings: Vec<i32> = craftsman
+                .map(|c| {
+                    c.ratings
+                        .iter()
+                        .filter(|r| !r.deleted)
+                        .map(|r| r.amount)
+                        .collect()
+                })
+                .unwrap_or_default();
+            let rating_average = if ratings.is_empty() {
+                None
+            } else {
+                let sum: i32 = ratings.iter().sum();
+                Some(
+                    (Decimal::from(sum) / Decimal::from(ratings.len()))
+                        .round_dp_with_strategy(1, RoundingStrategy::BankersRounding),
+                )
+            };
+            let difference = bid.final_bid - task.price;
+            ComparedBid {
+                bid_id: bid.id,
+                company_name: craftsman
+                    .map(|c| c.company_name.clone())
+                    .unwrap_or_default(),
+                craftsman_id: bid.craftsman_id,
+                bid_message: bid.bid_message,
+                total: bid.final_bid,
+                labour_cost: bid.labour_cost,
+                material_cost: bid.material_cost,
+                vat: bid.vat,
+                root_deduction: bid.root_deduction,
+                labour_hours: bid.labour_hours,
+                lines: bid.lines,
+                rating_count: ratings.len(),
+                rating_average,
+                completed_jobs: completed.get(&bid_craftsman).copied().unwrap_or(0),
+                f_tax: craftsman.is_some_and(|c| c.f_tax),
+                earliest_start: bid.earliest_start,
+                valid_until: bid.valid_until,
+                difference_from_estimate: difference,
+                difference_percentage: if task.price.is_zero() {
+                    None
+                } else {
+                    Some(
+                        (difference * Decimal::from(100) / task.price)
+                            .round_dp_with_strategy(1, RoundingStrategy::BankersRounding),
+                    )
+                },
+            }
+        })
+        .collect();
+    compared.sort_by(|a, b| a.total.cmp(&b.total));
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Comparison {
+            estimate: task.price,
+            bids: compared,
+        }),
+        extra: None::<Empty>,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BidRef {
+    id: String,
+    craftsman_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AcceptedRef {
+    accepted_bid: String,
+}
+
+// The number of finished tasks each of the craftsmen did. NOTE: Counted from the tasks since the
+// completed jobs stored on the craftsman are not kept up to date.
+async fn completed_jobs(
+    office_id: &str,
+    craftsman_ids: &[String],
+) -> Result<HashMap<String, usize>, warp::Rejection> {
+    let mut completed = HashMap::new();
+    if craftsman_ids.is_empty() {
+        return Ok(completed);
+    }
+    let mut bids: Vec<BidRef> = Vec::new();
+    for chunk in craftsman_ids.chunks(IDS_PER_QUERY) {
+        let q = format!(
+            r#"SELECT b.id, b.craftsmanId FROM {} b WHERE ARRAY_CONTAINS([{}], b.craftsmanId) AND b.isCancelled = false"#,
+            BID_COLLECTION,
+            chunk.join(",")
+        );
+        let found: Vec<BidRef> = query(BID_COLLECTION, [office_id], q, -1).await?;
+        bids.extend(found);
+    }
+    if bids.is_empty() {
+        return Ok(completed);
+    }
+    let bid_ids: Vec<String> = bids.iter().map(|b| format!(r#""{}""#, b.id)).collect();
+    let mut finished: Vec<AcceptedRef> = Vec::new();
+    for chunk in bid_ids.chunks(IDS_PER_QUERY) {
+        let q = format!(
+            r#"SELECT t.acceptedBid FROM {} t WHERE t.finished = true AND ARRAY_CONTAINS([{}], t.acceptedBid)"#,
+            TASK_COLLECTION,
+            chunk.join(",")
+        );
+        let found: Vec<AcceptedRef> = query(TASK_COLLECTION, [office_id], q, -1).await?;
+        finished.extend(found);
+    }
+    let craftsman_of: HashMap<&str, &str> = bids
+        .iter()
+        .map(|b| (b.id.as_str(), b.craftsman_id.as_str()))
+        .collect();
+    for task in finished.iter() {
+        if let Some(craftsman_id) = craftsman_of.get(task.accepted_bid.as_str()) {
+            *completed.entry(craftsman_id.to_string()).or_insert(0) += 1;
+        }
+    }
+    Ok(completed)
+}
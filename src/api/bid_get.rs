@@ -34,8 +34,11 @@ pub async fn bid_get(
     }
 
     //TODO (Jonathan): Make sure the has_role works for craftsmen
-    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN)
-        && &bid.craftsman_id != &claims.sub
+    if !has_role(
+        Some(&office_id),
+        &claims,
+        RoleFlags::OFFICE_CONTENT_ADMIN | RoleFlags::OFFICE_BILLING_ADMIN,
+    ) && &bid.craftsman_id != &claims.sub
     {
         return Err(reject::custom(Fault::Forbidden(format!(
             "User does not have sufficient roles."
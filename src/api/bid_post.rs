@@ -1,10 +1,14 @@
+use crate::chat;
 use crate::fault::Fault;
-use crate::models::{Bid, Chat, Claims, CraftStatus, Craftsman, RoleFlags, Task, User};
+use crate::models::{
+    Bid, Brokerage, Chat, Claims, Craftsman, Office, RoleFlags, Task, TaskState, User,
+};
 use crate::push::send_custom_pn;
+use crate::tax;
 use crate::util::{has_role, log, DataRequest, DataResponse, Empty};
 use crate::{
     BID_COLLECTION, CHAT_COLLECTION, CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT,
-    TASK_COLLECTION, USER_COLLECTION,
+    OFFICE_COLLECTION, TASK_COLLECTION, USER_COLLECTION,
 };
 use cosmos_utils::{get, CosmosSaga};
 use uuid::Uuid;
@@ -51,12 +55,14 @@ pub async fn bid_post(
         ))));
     }
 
-    let (craftsman_r, task_r) = tokio::join!(
+    let (craftsman_r, task_r, office_r) = tokio::join!(
         get(CRAFTSMAN_COLLECTION, [&office_id], &bid.craftsman_id),
-        get(TASK_COLLECTION, [&office_id], &task_id)
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(OFFICE_COLLECTION, [&office_id], &office_id)
     );
     let (craftsman, _): (Craftsman, _) = craftsman_r?;
     let (task, _): (Task, _) = task_r?;
+    let (office, _): (Office, _) = office_r?;
 
     if craftsman.frozen {
         return Err(reject::custom(Fault::Forbidden(format!(
@@ -65,18 +71,7 @@ pub async fn bid_post(
     }
 
     // NOTE: Make sure that the craftsman has at least one approved craft that is in the task before he is allowed to post bids
-    let mut found = false;
-    'outer: for task_craft in &task.crafts {
-        for craftsman_craft in &craftsman.crafts {
-            if &craftsman_craft.craft_type == task_craft
-                && craftsman_craft.status == CraftStatus::Approved
-            {
-                found = true;
-                break 'outer;
-            }
-        }
-    }
-    if !found {
+    if !craftsman.can_bid_on(&task) {
         return Err(reject::custom(Fault::Forbidden(format!(
             "Craftsman needs to have at least one approved overlapping craft before he can post bids."
         ))));
@@ -88,15 +83,63 @@ pub async fn bid_post(
         ))));
     }
 
-    // TODO: We need to set a flag to either use rot och rut or not here
-    if !bid.cost_is_correct(task.use_rot_rut) {
+    if let Some(valid_until) = bid.valid_until {
+        if valid_until <= chrono::Utc::now() {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "A bid has to be valid until a time in the future, not {}",
+                valid_until
+            ))));
+        }
+    }
+
+    if task.state() == TaskState::Expired {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The task has expired and does not take any more bids."
+        ))));
+    }
+
+    if let Err(e) = bid.apply_lines() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The lines of the bid are not correct: {}",
+            e
+        ))));
+    }
+
+    let today = tax::today();
+    let rules = tax::rules_at(today);
+    let (deduction, allowance) = tax::task_deduction(rules, &task, today).await?;
+    if !tax::has_labour_hours(deduction, bid.labour_hours) {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The hours of labour are needed when the customer gets a ROT or RUT deduction"
+        ))));
+    }
+    bid.reverse_charge = rules.reverse_charge(&task);
+    if let Err(e) = bid.verify_cost(rules, deduction, allowance, bid.reverse_charge) {
         return Err(reject::custom(Fault::Forbidden(format!(
-            "The cost of the bid is not correct"
+            "The cost of the bid is not correct: {}",
+            e
+        ))));
+    }
+
+    if let Err(e) = bid.verify_milestones() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The milestones of the bid are not correct: {}",
+            e
         ))));
     }
 
     bid.id = Uuid::new_v4().to_string();
+    for milestone in bid.milestones.iter_mut() {
+        milestone.id = Uuid::new_v4().to_string();
+    }
+    bid.brokerage = Some(Brokerage::new(
+        office.brokerage_percentage_for(&task.crafts, chrono::Utc::now()),
+        &bid.costs(),
+    ));
     bid.is_cancelled = false;
+    bid.revision = 0;
+    bid.revisions = Vec::new();
+    bid.withdrawal = None;
     bid.modified = chrono::Utc::now();
 
     //let (bid_insert_r, task_get_r) = tokio::join!(
@@ -113,6 +156,7 @@ pub async fn bid_post(
         bid_id: bid.id.clone(),
         modified: chrono::Utc::now(),
         deleted: false,
+        closed: false,
     };
     let (bid_r, chat_r, to_r, bi_r) = tokio::join!(
         bid_saga.insert(BID_COLLECTION, [&office_id], &bid, &bid.id, None),
@@ -137,6 +181,11 @@ pub async fn bid_post(
         return Err(e.into());
     }
 
+    // Show the customer what the bid is made up of where they talk about it
+    if !bid.lines.is_empty() {
+        chat::post_notice(&bid, chat::describe_lines(&bid)).await;
+    }
+
     // Attempt to send out a PN, do this in a new thread since failure takes a long time and we
     // only log errors, we don't return them.
     tokio::task::spawn(async {
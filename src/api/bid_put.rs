@@ -1,9 +1,13 @@
+use crate::chat;
 use crate::fault::Fault;
-use crate::models::{Bid, Claims, Task};
+use crate::models::{Bid, BidRevision, Brokerage, Claims, Office, Task};
+use crate::receipt::kronor;
+use crate::tax;
 use crate::util::{DataRequest, DataResponse, Empty};
-use crate::{BID_COLLECTION, TASK_COLLECTION};
+use crate::{BID_COLLECTION, OFFICE_COLLECTION, TASK_COLLECTION};
 use chrono::Utc;
 use cosmos_utils::{get, modify_async};
+use uuid::Uuid;
 use warp::reject;
 
 pub async fn bid_put(
@@ -14,7 +18,7 @@ pub async fn bid_put(
     claims: Claims,
     _v: u8,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let new_bid;
+    let mut new_bid: Bid;
     if let Some(q) = r.data {
         new_bid = q;
     } else {
@@ -49,43 +53,140 @@ pub async fn bid_put(
         ))));
     }
 
+    let now = Utc::now();
+    if let Some(valid_until) = new_bid.valid_until {
+        if valid_until <= now {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "A bid has to be valid until a time in the future, not {}",
+                valid_until
+            ))));
+        }
+    }
+
+    if let Err(e) = new_bid.apply_lines() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The lines of the new bid are not correct: {}",
+            e
+        ))));
+    }
+
+    // Milestones that are new in this version of the bid get ids
+    for milestone in new_bid.milestones.iter_mut() {
+        if milestone.id.is_empty() {
+            milestone.id = Uuid::new_v4().to_string();
+        }
+    }
+
+    let (office, _): (Office, _) = get(OFFICE_COLLECTION, [&office_id], &office_id).await?;
     let bid = modify_async(
         BID_COLLECTION,
         [&office_id],
         &bid_id,
         |mut bid: Bid| async {
+            if bid.is_withdrawn() || bid.is_cancelled {
+                return Err(warp::reject::custom(Fault::IllegalState(String::from(
+                    "Can not change a bid that has been withdrawn or cancelled",
+                ))));
+            }
+            // NOTE: The version that is replaced is kept as a revision, if anything changed
+            let revised = bid.bid_message != new_bid.bid_message
+                || bid.labour_hours != new_bid.labour_hours
+                || bid.valid_until != new_bid.valid_until
+                || bid.earliest_start != new_bid.earliest_start
+                || bid.final_bid != new_bid.final_bid
+                || bid.labour_cost != new_bid.labour_cost
+                || bid.material_cost != new_bid.material_cost
+                || bid.vat != new_bid.vat
+                || bid.root_deduction != new_bid.root_deduction
+                || bid.lines != new_bid.lines
+                || bid.milestones != new_bid.milestones;
+            if revised {
+                bid.revisions.push(BidRevision::of(&bid, now));
+                bid.revision += 1;
+            }
             bid.bid_message = new_bid.bid_message.clone();
+            bid.valid_until = new_bid.valid_until;
+            bid.earliest_start = new_bid.earliest_start;
             let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &bid.task_id).await?;
-            // If we want to change the cost of the bid make sure it's correct
-            if bid.final_bid != new_bid.final_bid
+            // If we want to change the cost of the bid make sure it's correct. NOTE: The hours go
+            // with the cost since they are what a ROT or RUT claim is made from.
+            if bid.labour_hours != new_bid.labour_hours
+                || bid.final_bid != new_bid.final_bid
                 || bid.labour_cost != new_bid.labour_cost
                 || bid.material_cost != new_bid.material_cost
+                || bid.vat != new_bid.vat
                 || bid.root_deduction != new_bid.root_deduction
+                || bid.lines != new_bid.lines
+                || bid.milestones != new_bid.milestones
             {
-                if let Some(accepted_id) = task.accepted_bid {
-                    if accepted_id == bid.id {
+                if let Some(accepted_id) = &task.accepted_bid {
+                    if *accepted_id == bid.id {
                         return Err(warp::reject::custom(Fault::Forbidden(String::from(
-                            "Can not change cost of a bid that is already accepted",
+                            "Can not change cost or hours of a bid that is already accepted",
                         ))));
                     }
                 }
-                if new_bid.cost_is_correct(task.use_rot_rut) {
-                    bid.final_bid = new_bid.final_bid;
-                    bid.labour_cost = new_bid.labour_cost;
-                    bid.material_cost = new_bid.material_cost;
-                    bid.root_deduction = new_bid.root_deduction;
-                } else {
-                    return Err(warp::reject::custom(Fault::Forbidden(String::from(
-                        "The cost of the new bid is not correct",
+                let today = tax::today();
+                let rules = tax::rules_at(today);
+                let (deduction, allowance) = tax::task_deduction(rules, &task, today).await?;
+                if !tax::has_labour_hours(deduction, new_bid.labour_hours) {
+                    return Err(warp::reject::custom(Fault::IllegalArgument(format!(
+                        "The hours of labour are needed when the customer gets a ROT or RUT deduction"
+                    ))));
+                }
+                let reverse_charge = rules.reverse_charge(&task);
+                if let Err(e) = new_bid.verify_cost(rules, deduction, allowance, reverse_charge) {
+                    return Err(warp::reject::custom(Fault::Forbidden(format!(
+                        "The cost of the new bid is not correct: {}",
+                        e
                     ))));
                 }
+                if let Err(e) = new_bid.verify_milestones() {
+                    return Err(warp::reject::custom(Fault::IllegalArgument(format!(
+                        "The milestones of the new bid are not correct: {}",
+                        e
+                    ))));
+                }
+                bid.labour_hours = new_bid.labour_hours;
+                bid.final_bid = new_bid.final_bid;
+                bid.labour_cost = new_bid.labour_cost;
+                bid.material_cost = new_bid.material_cost;
+                bid.vat = new_bid.vat;
+                bid.root_deduction = new_bid.root_deduction;
+                bid.lines = new_bid.lines.clone();
+                bid.reverse_charge = reverse_charge;
+                bid.milestones = new_bid.milestones.clone();
+                // NOTE: The bid keeps the brokerage it was posted with, e.g. during a promotion
+                let percentage = match &bid.brokerage {
+                    Some(brokerage) => brokerage.percentage,
+                    None => office.brokerage_percentage_for(&task.crafts, Utc::now()),
+                };
+                bid.brokerage = Some(Brokerage::new(percentage, &bid.costs()));
             }
-            bid.modified = Utc::now();
+            bid.modified = now;
             Ok(bid)
         },
     )
     .await?;
 
+    // Let the customer see in the chat that the bid changed
+    if let Some(previous) = bid.revisions.last().filter(|r| r.replaced == now) {
+        let mut text = if previous.final_bid != bid.final_bid {
+            format!(
+                "Budet har uppdaterats från {} till {}.",
+                kronor(previous.final_bid),
+                kronor(bid.final_bid)
+            )
+        } else {
+            String::from("Budet har uppdaterats.")
+        };
+        if previous.lines != bid.lines && !bid.lines.is_empty() {
+            text.push('\n');
+            text.push_str(&chat::describe_lines(&bid));
+        }
+        chat::post_notice(&bid, text).await;
+    }
+
     Ok(warp::reply::json(&DataResponse {
         data: Some(bid),
         extra: None::<Empty>,
@@ -0,0 +1,102 @@
+use crate::chat;
+use crate::fault::Fault;
+use crate::models::{Bid, BidWithdrawal, Claims, Task};
+use crate::payment;
+use crate::push::send_custom_pn;
+use crate::util::{log, DataRequest, DataResponse, Empty};
+use crate::{BID_COLLECTION, NOTIFICATION_HUB_ACCOUNT, TASK_COLLECTION, USER_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, modify};
+use serde::Deserialize;
+use warp::reject;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawBid {
+    reason: String,
+}
+
+// Lets the craftsman take back a bid the customer has not accepted. An accepted bid has to be
+// cancelled instead, so that the payment is taken care of.
+pub async fn bid_withdraw(
+    office_id: String,
+    task_id: String,
+    bid_id: String,
+    r: DataRequest<WithdrawBid, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let withdraw = r.data.unwrap_or_default();
+    let reason = withdraw.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "A reason is needed to withdraw a bid."
+        ))));
+    }
+
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+    if task.accepted_bid.as_deref() == Some(bid_id.as_str())
+        || !payment::active_payments(&office_id, &bid_id)
+            .await?
+            .is_empty()
+    {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The bid has been accepted and has to be cancelled instead"
+        ))));
+    }
+
+    let bid = modify(BID_COLLECTION, [&office_id], &bid_id, |mut bid: Bid| {
+        if bid.task_id != task_id {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "task_id does not match url ({} != {}).",
+                bid.task_id, task_id
+            ))));
+        }
+        // NOTE: Craftsman id is the same as the user id
+        if bid.craftsman_id != claims.sub {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only the craftsman may withdraw the bid"
+            ))));
+        }
+        if bid.is_withdrawn() {
+            return Err(reject::custom(Fault::IllegalState(format!(
+                "The bid has already been withdrawn"
+            ))));
+        }
+        bid.withdrawal = Some(BidWithdrawal {
+            reason: reason.clone(),
+            date: Utc::now(),
+        });
+        bid.modified = Utc::now();
+        Ok(bid)
+    })
+    .await?;
+
+    chat::post_notice(&bid, format!("Budet har dragits tillbaka: {}", reason)).await;
+
+    // Send a PN to the task owner, this should not fail the request
+    let text = format!(
+        "Ett bud på ditt jobb \"{}\" har dragits tillbaka.",
+        task.title
+    );
+    tokio::task::spawn(async move {
+        match get(USER_COLLECTION, [&task.user_id], &task.user_id).await {
+            Ok((task_owner, _)) => {
+                if let Err(e) =
+                    send_custom_pn(&task_owner, &text, None, &NOTIFICATION_HUB_ACCOUNT).await
+                {
+                    log(format!("Could not send PN in bid_withdraw due to {}", e));
+                }
+            }
+            Err(e) => log(format!(
+                "Could not send PN in bid_withdraw due to not being able to get TO user. Err: {}",
+                e
+            )),
+        }
+    });
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&bid),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,38 @@
+use crate::fault::Fault;
+use crate::models::{Campaign, Claims, RoleFlags};
+use crate::util::{has_role, DataResponse, Empty};
+use crate::CAMPAIGN_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use warp::reject;
+
+// Ends a campaign, payments that already used it keep their discount
+pub async fn campaign_delete(
+    office_id: String,
+    campaign_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to end a campaign",
+        ))));
+    }
+
+    let campaign = modify(
+        CAMPAIGN_COLLECTION,
+        [&office_id],
+        &campaign_id,
+        |mut campaign: Campaign| {
+            campaign.deleted = true;
+            campaign.modified = Utc::now();
+            Ok(campaign)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&campaign),
+        extra: None::<Empty>,
+    }))
+}
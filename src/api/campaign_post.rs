@@ -0,0 +1,107 @@
+use crate::fault::Fault;
+use crate::models::{Campaign, CampaignKind, Claims, RoleFlags};
+use crate::payment;
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::CAMPAIGN_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::{insert, query};
+use rust_decimal::prelude::{Decimal, One, Zero};
+use uuid::Uuid;
+use warp::reject;
+
+// Starts a marketing campaign in an office. This endpoint is callable only by the billing admin
+// of the office since Toolit pays for the campaign.
+pub async fn campaign_post(
+    office_id: String,
+    r: DataRequest<Campaign, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut campaign;
+    if let Some(q) = r.data {
+        campaign = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if campaign.office_id != office_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "office_id does not match url ({} != {}).",
+            campaign.office_id, office_id
+        ))));
+    }
+
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to start a campaign",
+        ))));
+    }
+
+    if campaign.starts >= campaign.ends {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The campaign ends before it starts"
+        ))));
+    }
+    let is_fraction = |p: Decimal| p > Decimal::zero() && p < Decimal::one();
+    let valid = match campaign.kind {
+        CampaignKind::CustomerDiscount => {
+            campaign
+                .discount_amount
+                .is_some_and(|a| a > Decimal::zero())
+                || campaign.discount_percentage.is_some_and(is_fraction)
+        }
+        CampaignKind::BrokerageDiscount => campaign
+            .brokerage_percentage
+            .is_some_and(|p| p >= Decimal::zero() && p < Decimal::one()),
+        CampaignKind::Referral => {
+            campaign
+                .referrer_credit
+                .is_some_and(|a| a > Decimal::zero())
+                && (campaign
+                    .discount_amount
+                    .is_some_and(|a| a > Decimal::zero())
+                    || campaign.discount_percentage.is_some_and(is_fraction))
+        }
+    };
+    if !valid {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The discount of the campaign does not fit a {:?} campaign",
+            campaign.kind
+        ))));
+    }
+
+    // NOTE: Referral campaigns use the codes of the users
+    campaign.code = match (campaign.kind, &campaign.code) {
+        (CampaignKind::Referral, _) => None,
+        (_, Some(code)) => Some(payment::normalize_code(code)?),
+        (_, None) => {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The campaign needs a code"
+            ))));
+        }
+    };
+    if let Some(code) = &campaign.code {
+        let q = format!(
+            r#"SELECT * FROM {} c WHERE c.code = "{}""#,
+            CAMPAIGN_COLLECTION, code
+        );
+        let existing: Vec<Campaign> = query(CAMPAIGN_COLLECTION, [&office_id], q, -1).await?;
+        if existing.iter().any(|c| !c.deleted) {
+            return Err(reject::custom(Fault::Duplicate(format!(
+                "Another campaign already uses the code {}",
+                code
+            ))));
+        }
+    }
+
+    campaign.id = Uuid::new_v4().to_string();
+    campaign.deleted = false;
+    campaign.uses = 0;
+    campaign.modified = Utc::now();
+    insert(CAMPAIGN_COLLECTION, [&office_id], &campaign, None).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&campaign),
+        extra: None::<Empty>,
+    }))
+}
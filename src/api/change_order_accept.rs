@@ -0,0 +1,171 @@
+use crate::fault::Fault;
+use crate::models::{
+    Bid, ChangeOrder, ChangeOrderStatus, Claims, Payment, PaymentMethod, PaymentState, Task, User,
+};
+use crate::payment::{self, Initiated};
+use crate::tax;
+use crate::util::{log, DataRequest, DataResponse, Empty};
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, insert, modify};
+use serde::{Deserialize, Serialize};
+use tokio::join;
+use warp::reject;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    #[serde(flatten)]
+    initiated: Initiated,
+    payment_id: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptChangeOrder {
+    #[serde(default)]
+    payment_method: PaymentMethod,
+}
+
+// Accepts a change order and starts the payment of it into escrow. A change order whose payment
+// failed can be accepted again.
+pub async fn change_order_accept(
+    office_id: String,
+    task_id: String,
+    change_order_id: String,
+    r: DataRequest<AcceptChangeOrder, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let accept = r.data.unwrap_or_default();
+
+    let (t, c) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(CHANGE_ORDER_COLLECTION, [&office_id], &change_order_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (change_order, _): (ChangeOrder, _) = c?;
+
+    if claims.sub != task.user_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only the task poster may accept change orders",
+        ))));
+    }
+    if change_order.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "task_id does not match url ({} != {}).",
+            change_order.task_id, task_id
+        ))));
+    }
+    if task.finished {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The task has already been finished",
+        ))));
+    }
+    // NOTE: A change order made on a bid that has since been cancelled, or whose payment failed,
+    // can not be paid for
+    if task.accepted_bid.as_ref() != Some(&change_order.bid_id) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The change order was made on bid {} which is no longer the accepted bid of the task",
+            change_order.bid_id
+        ))));
+    }
+
+    match (&change_order.status, &change_order.payment_id) {
+        (ChangeOrderStatus::Proposed, _) | (ChangeOrderStatus::Accepted, None) => (),
+        (ChangeOrderStatus::Accepted, Some(payment_id)) => {
+            let (previous, _): (Payment, _) =
+                get(PAYMENT_COLLECTION, [&office_id], payment_id).await?;
+            match previous.payment_state {
+                PaymentState::Failed | PaymentState::Error(_) => (),
+                _ => {
+                    return Err(reject::custom(Fault::Duplicate(format!(
+                        "A payment for this change order is either already in progress or has been completed",
+                    ))));
+                }
+            }
+        }
+        (ChangeOrderStatus::Rejected, _) => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "The change order has already been rejected",
+            ))));
+        }
+    }
+
+    let (b, u) = join!(
+        get(BID_COLLECTION, [&office_id], &change_order.bid_id),
+        get(USER_COLLECTION, [&task.user_id], &task.user_id)
+    );
+    let (bid, _): (Bid, _) = b?;
+    let (task_owner, _): (User, _) = u?;
+
+    // NOTE: The customer may have used up some of the yearly deduction since the change order was
+    // proposed
+    let today = tax::today();
+    let rules = tax::rules_at(today);
+    let (deduction_type, allowance) = tax::task_deduction(rules, &task, today).await?;
+    if let Some(allowance) = allowance {
+        if change_order.root_deduction > allowance {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "The deduction of the change order {} is more than the {} the customer has left this year",
+                change_order.root_deduction, allowance
+            ))));
+        }
+    }
+
+    let previous_payment_id = change_order.payment_id.clone();
+    let mut payment = payment::new_change_order_payment(
+        &bid,
+        &change_order,
+        accept.payment_method,
+        deduction_type,
+    );
+    let initiated = payment::provider(&payment.payment_method)
+        .initiate(&mut payment, &task_owner)
+        .await?;
+    insert(PAYMENT_COLLECTION, [&payment.office_id], &payment, None).await?;
+
+    let accepted = modify(
+        CHANGE_ORDER_COLLECTION,
+        [&office_id],
+        &change_order_id,
+        |mut change_order: ChangeOrder| {
+            if change_order.status == ChangeOrderStatus::Rejected {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "The change order has already been rejected",
+                ))));
+            }
+            // NOTE: Someone else accepted the change order while we set up the payment
+            if change_order.payment_id != previous_payment_id {
+                return Err(reject::custom(Fault::Duplicate(format!(
+                    "A payment for this change order is already in progress",
+                ))));
+            }
+            change_order.status = ChangeOrderStatus::Accepted;
+            change_order.payment_id = Some(payment.id.clone());
+            change_order.modified = Utc::now();
+            Ok(change_order)
+        },
+    )
+    .await;
+    if let Err(e) = accepted {
+        // NOTE: The payment must not be paid if the change order could not be accepted
+        if let Err(e) = payment::cancel(&payment).await {
+            log(format!(
+                "Could not cancel payment {} of change order {} due to {:?}",
+                payment.id, change_order_id, e
+            ));
+        }
+        return Err(e.into());
+    }
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response {
+            initiated,
+            payment_id: payment.id.clone(),
+        }),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,133 @@
+use crate::fault::Fault;
+use crate::models::{Bid, ChangeOrder, ChangeOrderStatus, Claims, Task, User};
+use crate::push::send_custom_pn;
+use crate::tax;
+use crate::util::{log, DataRequest, DataResponse, Empty};
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, NOTIFICATION_HUB_ACCOUNT, TASK_COLLECTION,
+    USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, insert};
+use uuid::Uuid;
+use warp::reject;
+
+// Proposes extra work on a task with an accepted bid. This endpoint is callable only by the
+// craftsman of the accepted bid.
+pub async fn change_order_post(
+    office_id: String,
+    task_id: String,
+    r: DataRequest<ChangeOrder, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut change_order;
+    if let Some(q) = r.data {
+        change_order = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if change_order.office_id != office_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "office_id does not match url ({} != {}).",
+            change_order.office_id, office_id
+        ))));
+    }
+
+    if change_order.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "task_id does not match url ({} != {}).",
+            change_order.task_id, task_id
+        ))));
+    }
+
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+    let bid_id = match &task.accepted_bid {
+        Some(bid_id) => bid_id,
+        None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Change orders can only be made on tasks with an accepted bid"
+            ))));
+        }
+    };
+    if task.finished {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Change orders can not be made on finished tasks"
+        ))));
+    }
+
+    let (bid, _): (Bid, _) = get(BID_COLLECTION, [&office_id], bid_id).await?;
+    // NOTE: Craftsman id is the same as the user id
+    if bid.craftsman_id != claims.sub {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only the craftsman of the accepted bid can propose change orders"
+        ))));
+    }
+
+    let today = tax::today();
+    let rules = tax::rules_at(today);
+    let (deduction, allowance) = tax::task_deduction(rules, &task, today).await?;
+    if !tax::has_labour_hours(deduction, change_order.labour_hours) {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The hours of labour are needed when the customer gets a ROT or RUT deduction"
+        ))));
+    }
+    change_order.reverse_charge = rules.reverse_charge(&task);
+    if let Err(e) =
+        change_order.verify_cost(rules, deduction, allowance, change_order.reverse_charge)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The cost of the change order is not correct: {}",
+            e
+        ))));
+    }
+
+    change_order.id = Uuid::new_v4().to_string();
+    change_order.bid_id = bid.id.clone();
+    change_order.craftsman_id = bid.craftsman_id.clone();
+    change_order.status = ChangeOrderStatus::Proposed;
+    change_order.payment_id = None;
+    change_order.deleted = false;
+    change_order.modified = Utc::now();
+
+    insert(CHANGE_ORDER_COLLECTION, [&office_id], &change_order, None).await?;
+
+    let description = change_order.description.clone();
+    tokio::task::spawn(async move {
+        let task_owner: Result<(User, _), _> =
+            get(USER_COLLECTION, [&task.user_id], &task.user_id).await;
+        match task_owner {
+            Ok((task_owner, _)) => {
+                // Send a PN to the task-owner
+                if let Err(e) = send_custom_pn(
+                    &task_owner,
+                    &format!(
+                        "Hantverkaren har föreslagit ett tilläggsarbete på \"{}\": {}",
+                        task.title, description
+                    ),
+                    None,
+                    &NOTIFICATION_HUB_ACCOUNT,
+                )
+                .await
+                {
+                    log(format!(
+                        "Could not send PN in change_order_post due to {}",
+                        e
+                    ));
+                }
+            }
+            Err(e) => {
+                log(format!(
+                    "Could not send PN in change_order_post due to get failing with {}",
+                    e
+                ));
+            }
+        }
+    });
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&change_order),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,95 @@
+use crate::fault::Fault;
+use crate::models::{ChangeOrder, ChangeOrderStatus, Claims, Craftsman, Task, User};
+use crate::push::send_custom_pn;
+use crate::util::{log, DataResponse, Empty};
+use crate::{
+    CHANGE_ORDER_COLLECTION, CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, TASK_COLLECTION,
+    USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, modify};
+use warp::reject;
+
+// This endpoint is callable only by the task-owner
+pub async fn change_order_reject(
+    office_id: String,
+    task_id: String,
+    change_order_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+    if claims.sub != task.user_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only the task poster may reject change orders",
+        ))));
+    }
+
+    let change_order = modify(
+        CHANGE_ORDER_COLLECTION,
+        [&office_id],
+        &change_order_id,
+        |mut change_order: ChangeOrder| {
+            if change_order.task_id != task_id {
+                return Err(reject::custom(Fault::IllegalArgument(format!(
+                    "task_id does not match url ({} != {}).",
+                    change_order.task_id, task_id
+                ))));
+            }
+            match change_order.status {
+                ChangeOrderStatus::Proposed => (),
+                ChangeOrderStatus::Rejected => return Ok(change_order),
+                ChangeOrderStatus::Accepted => {
+                    return Err(reject::custom(Fault::Forbidden(format!(
+                        "The change order has already been accepted",
+                    ))));
+                }
+            }
+            change_order.status = ChangeOrderStatus::Rejected;
+            change_order.modified = Utc::now();
+            Ok(change_order)
+        },
+    )
+    .await?;
+
+    let description = change_order.description.clone();
+    let craftsman_id = change_order.craftsman_id.clone();
+    tokio::task::spawn(async move {
+        let craftsman_user = async {
+            let (craftsman, _): (Craftsman, _) =
+                get(CRAFTSMAN_COLLECTION, [&office_id], &craftsman_id).await?;
+            let (user, _): (User, _) =
+                get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+            Result::<_, warp::Rejection>::Ok(user)
+        };
+        match craftsman_user.await {
+            Ok(craftsman_user) => {
+                // Send PN to the craftsman
+                if let Err(e) = send_custom_pn(
+                    &craftsman_user,
+                    &format!("Tilläggsarbetet \"{}\" blev inte godkänt.", description),
+                    None,
+                    &NOTIFICATION_HUB_ACCOUNT,
+                )
+                .await
+                {
+                    log(format!(
+                        "Could not send PN in change_order_reject due to {}",
+                        e
+                    ));
+                }
+            }
+            Err(e) => {
+                log(format!(
+                    "Could not send PN in change_order_reject due to get failing with {:?}",
+                    e
+                ));
+            }
+        }
+    });
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&change_order),
+        extra: None::<Empty>,
+    }))
+}
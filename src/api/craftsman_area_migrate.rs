@@ -0,0 +1,94 @@
+use crate::fault::Fault;
+use crate::geo;
+use crate::models::{Claims, Craftsman, RoleFlags, ServiceArea};
+use crate::util::{has_role, log, DataResponse, Empty};
+use crate::CRAFTSMAN_COLLECTION;
+use cosmos_utils::{maybe_modify, query, ModifyReturn};
+use serde::Serialize;
+use warp::reject;
+
+// MAGIC NUMBER: The distance around the old work area a migrated craftsman takes jobs in
+const MIGRATED_RADIUS_KM: f64 = 50.0;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationResult {
+    migrated: usize,
+    failed: usize,
+    // The craftsmen whose work area could not be located and has to be drawn by hand
+    unresolved: Vec<String>,
+}
+
+// Gives every craftsman that only has the old free text work area a service area around it
+pub async fn craftsman_area_migrate(
+    office_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_PERSONNEL_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "User does not have sufficient roles."
+        ))));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE IS_DEFINED(c.workArea) AND NOT IS_NULL(c.workArea) AND (NOT IS_DEFINED(c.serviceArea) OR IS_NULL(c.serviceArea))"#,
+        CRAFTSMAN_COLLECTION
+    );
+    let craftsmen: Vec<Craftsman> = query(CRAFTSMAN_COLLECTION, [&office_id], q, -1).await?;
+
+    let mut result = MigrationResult {
+        migrated: 0,
+        failed: 0,
+        unresolved: Vec::new(),
+    };
+    for craftsman in craftsmen {
+        // NOTE: The work area was free text, only the ones that are a postcode can be located
+        let center = match craftsman
+            .work_area
+            .as_deref()
+            .and_then(geo::postcode_location)
+        {
+            Some(center) => center,
+            None => {
+                result.unresolved.push(craftsman.id);
+                continue;
+            }
+        };
+
+        // NOTE: The craftsman may have drawn a service area since they were queried, then it is
+        // left alone
+        let r = maybe_modify(
+            CRAFTSMAN_COLLECTION,
+            [&office_id],
+            &craftsman.id,
+            |mut craftsman: Craftsman| {
+                if craftsman.service_area.is_some() {
+                    return Ok(ModifyReturn::DontReplace(craftsman));
+                }
+                craftsman.service_area = Some(ServiceArea::Radius {
+                    center: center.clone(),
+                    radius_km: MIGRATED_RADIUS_KM,
+                });
+                craftsman.work_area = None;
+                Ok(ModifyReturn::Replace(craftsman))
+            },
+        )
+        .await;
+        match r {
+            Ok(_) => result.migrated += 1,
+            Err(e) => {
+                log(format!(
+                    "Could not migrate the work area of craftsman {} due to {:?}",
+                    craftsman.id, e
+                ));
+                result.failed += 1;
+            }
+        }
+    }
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&result),
+        extra: None::<Empty>,
+    }))
+}
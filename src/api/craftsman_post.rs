@@ -30,6 +30,20 @@ pub async fn craftsman_post(
         ))));
     }
 
+    craftsman.work_area = None;
+    craftsman.area_alerts_sent_on = None;
+    craftsman.area_alerts_sent = 0;
+    craftsman.pending_area_task_ids = Vec::new();
+    craftsman.last_area_digest = None;
+
+    if let Some(service_area) = &craftsman.service_area {
+        if !service_area.is_valid() {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The service area has to be a radius of at most 500 km or a polygon."
+            ))));
+        }
+    }
+
     // If the sending user is not an office admin then the craftsman is only applying and has
     // to be approved
     craftsman.id = craftsman.user_id.clone();
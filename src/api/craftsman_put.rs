@@ -47,6 +47,14 @@ pub async fn craftsman_put(
         ))));
     }
 
+    if let Some(service_area) = &new_craftsman.service_area {
+        if !service_area.is_valid() {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The service area has to be a radius of at most 500 km or a polygon."
+            ))));
+        }
+    }
+
     let craftsman = modify(
         CRAFTSMAN_COLLECTION,
         [&office_id],
@@ -60,6 +68,11 @@ pub async fn craftsman_put(
             new_craftsman.user_id = craftsman.user_id;
             new_craftsman.ratings = craftsman.ratings;
             new_craftsman.frozen = craftsman.frozen;
+            new_craftsman.work_area = craftsman.work_area;
+            new_craftsman.area_alerts_sent_on = craftsman.area_alerts_sent_on;
+            new_craftsman.area_alerts_sent = craftsman.area_alerts_sent;
+            new_craftsman.pending_area_task_ids = craftsman.pending_area_task_ids;
+            new_craftsman.last_area_digest = craftsman.last_area_digest;
             new_craftsman.modified = Utc::now();
             Ok(new_craftsman)
         },
@@ -0,0 +1,55 @@
+use crate::fault::Fault;
+use crate::models::{Claims, RoleFlags};
+use crate::statement::{craftsman_statement, statement_csv, statement_pdf};
+use crate::util::has_role;
+use warp::{
+    http::{header, Response},
+    reject,
+};
+
+// Exports the yearly statement of a craftsman as a pdf or a csv file
+pub async fn craftsman_statement_export(
+    office_id: String,
+    craftsman_id: String,
+    year: i32,
+    format: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // NOTE: Craftsman id is the same as the user id
+    if claims.sub != craftsman_id
+        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be the craftsman or an office billing admin to export the statement",
+        ))));
+    }
+
+    let content_type = match format.as_str() {
+        "pdf" => "application/pdf",
+        "csv" => "text/csv; charset=utf-8",
+        _ => {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "Unknown format {}, expected pdf or csv",
+                format
+            ))));
+        }
+    };
+    let statement = craftsman_statement(&office_id, &craftsman_id, year).await?;
+    let file = if format == "pdf" {
+        statement_pdf(&statement)
+    } else {
+        statement_csv(&statement).into_bytes()
+    };
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                r#"attachment; filename="arsbesked-{}-{}.{}""#,
+                year, craftsman_id, format
+            ),
+        )
+        .body(file))
+}
@@ -0,0 +1,30 @@
+use crate::fault::Fault;
+use crate::models::{Claims, RoleFlags};
+use crate::statement::craftsman_statement;
+use crate::util::{has_role, DataResponse, Empty};
+use warp::reject;
+
+// Returns the yearly statement of what a craftsman got paid through Toolit
+pub async fn craftsman_statement_get(
+    office_id: String,
+    craftsman_id: String,
+    year: i32,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // NOTE: Craftsman id is the same as the user id
+    if claims.sub != craftsman_id
+        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be the craftsman or an office billing admin to get the statement",
+        ))));
+    }
+
+    let statement = craftsman_statement(&office_id, &craftsman_id, year).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&statement),
+        extra: None::<Empty>,
+    }))
+}
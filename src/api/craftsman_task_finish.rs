@@ -1,5 +1,6 @@
 use crate::fault::Fault;
-use crate::models::{Bid, Claims, Task, User};
+use crate::models::{Bid, Claims, PaymentState, Task, TaskActor, TaskState, User};
+use crate::payment;
 use crate::push::send_custom_pn;
 use crate::util::{log, DataResponse, Empty};
 use crate::{BID_COLLECTION, NOTIFICATION_HUB_ACCOUNT, TASK_COLLECTION, USER_COLLECTION};
@@ -25,6 +26,20 @@ pub async fn craftsman_task_finish(
                         "Only the craftsman of the accepted bid can call this"
                     ))));
                 }
+                // NOTE: With milestones the customer finishes the task by approving the last
+                // one, which they can not do before it has been paid
+                if let Some(last) = bid.last_milestone() {
+                    let payments = payment::active_payments(&office_id, &bid.id).await?;
+                    let paid = match payment::milestone_payment(&payments, &last.id) {
+                        Some(p) => !matches!(p.payment_state, PaymentState::Initialized),
+                        None => false,
+                    };
+                    if !paid {
+                        return Err(warp::reject::custom(Fault::Forbidden(format!(
+                            "The last milestone has to be paid before the work can be marked as finished"
+                        ))));
+                    }
+                }
             } else {
                 return Err(warp::reject::custom(Fault::Forbidden(format!(
                     "Can only call this endpoint if the task has an accepted bid"
@@ -33,9 +48,18 @@ pub async fn craftsman_task_finish(
 
             if task.craftsman_indicated_finished {
                 return Ok(ModifyReturn::DontReplace(task));
-            } else {
-                task.craftsman_indicated_finished = true;
-                return Ok(ModifyReturn::Replace(task));
+            }
+            match task.transition(
+                TaskState::CraftsmanDone,
+                TaskActor::Craftsman,
+                Some(&claims.sub),
+                None,
+            ) {
+                Ok(_) => Ok(ModifyReturn::Replace(task)),
+                Err(e) => Err(warp::reject::custom(Fault::Forbidden(format!(
+                    "The task can not be marked as finished: {}",
+                    e
+                )))),
             }
         },
     )
@@ -0,0 +1,61 @@
+use crate::fault::Fault;
+use crate::models::{Claims, Dispute};
+use crate::util::{DataResponse, Empty};
+use crate::DISPUTE_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::{get, upload_image, upsert};
+use warp::filters::multipart::FormData;
+use warp::reject;
+
+// Adds a photo as evidence to a statement in a dispute. This endpoint is callable only by the
+// author of the statement.
+pub async fn dispute_image_put(
+    office_id: String,
+    task_id: String,
+    dispute_id: String,
+    statement_id: String,
+    claims: Claims,
+    _v: u8,
+    f: FormData,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (mut dispute, etag): (Dispute, _) =
+        get(DISPUTE_COLLECTION, [&office_id], &dispute_id).await?;
+    if task_id != dispute.task_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "task id does not match the url {} != {}",
+            task_id, dispute.task_id
+        ))));
+    }
+
+    if !dispute.is_open() {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The dispute has already been resolved"
+        ))));
+    }
+
+    let statement = match dispute.statements.iter_mut().find(|s| s.id == statement_id) {
+        Some(statement) => statement,
+        None => {
+            return Err(reject::custom(Fault::NotFound(format!(
+                "No statement with id {} in the dispute",
+                statement_id
+            ))));
+        }
+    };
+
+    if statement.author_id != claims.sub {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Caller is not the author of the statement"
+        ))));
+    }
+
+    let image_id = upload_image(f).await?;
+    statement.images.push(image_id);
+    dispute.modified = Utc::now();
+    upsert(DISPUTE_COLLECTION, [&office_id], &dispute, Some(&etag)).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(dispute),
+        extra: None::<Empty>,
+    }))
+}
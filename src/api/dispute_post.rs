@@ -0,0 +1,120 @@
+use crate::dispute;
+use crate::fault::Fault;
+use crate::models::{Claims, Dispute, DisputeParty, DisputeStatement, Payment, PaymentState, Task};
+use crate::util::{DataRequest, DataResponse, Empty};
+use crate::{DISPUTE_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, insert, query};
+use serde::Deserialize;
+use tokio::join;
+use uuid::Uuid;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDispute {
+    payment_id: String,
+    text: String,
+}
+
+// Opens a dispute about the work paid for by a payment in escrow. This endpoint is callable only
+// by the task-owner and the craftsman.
+pub async fn dispute_post(
+    office_id: String,
+    task_id: String,
+    r: DataRequest<OpenDispute, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let open;
+    if let Some(q) = r.data {
+        open = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    let (t, p) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(PAYMENT_COLLECTION, [&office_id], &open.payment_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (payment, _): (Payment, _) = p?;
+
+    if payment.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "task_id does not match url ({} != {}).",
+            payment.task_id, task_id
+        ))));
+    }
+
+    let party = match dispute::party_of(&claims, &task, &payment.craftsman_id) {
+        Some(DisputeParty::Mediator) | None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only the task poster and the craftsman can open a dispute"
+            ))));
+        }
+        Some(party) => party,
+    };
+
+    match payment.payment_state {
+        PaymentState::PaidToEscrow => (),
+        s => {
+            return Err(reject::custom(Fault::IllegalState(format!(
+                "Only payments in escrow can be disputed, this payment is {:?}",
+                s
+            ))));
+        }
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} d WHERE d.paymentId = "{}""#,
+        DISPUTE_COLLECTION, payment.id
+    );
+    let disputes: Vec<Dispute> = query(DISPUTE_COLLECTION, [&office_id], q, -1).await?;
+    if disputes.iter().any(|d| d.is_open()) {
+        return Err(reject::custom(Fault::Duplicate(format!(
+            "The payment already has an open dispute"
+        ))));
+    }
+
+    let now = Utc::now();
+    let dispute = Dispute {
+        id: Uuid::new_v4().to_string(),
+        deleted: false,
+        office_id: office_id.clone(),
+        task_id: task_id.clone(),
+        payment_id: payment.id.clone(),
+        craftsman_id: payment.craftsman_id.clone(),
+        opened_by: party,
+        statements: vec![DisputeStatement {
+            id: Uuid::new_v4().to_string(),
+            author_id: claims.sub.clone(),
+            party,
+            text: open.text,
+            images: Vec::new(),
+            created: now,
+        }],
+        mediator_id: None,
+        outcome: None,
+        refund_amount: None,
+        resolved: None,
+        created: now,
+        modified: now,
+    };
+    insert(DISPUTE_COLLECTION, [&office_id], &dispute, None).await?;
+
+    dispute::notify_parties(
+        &dispute,
+        &task,
+        party,
+        format!(
+            "En reklamation har öppnats på \"{}\". Betalningen hålls kvar tills den är löst.",
+            task.title
+        ),
+    );
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&dispute),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,113 @@
+use crate::dispute;
+use crate::fault::Fault;
+use crate::models::{Claims, Dispute, DisputeOutcome, DisputeParty, RoleFlags, Task};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::{DISPUTE_COLLECTION, TASK_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, modify};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::join;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveDispute {
+    outcome: DisputeOutcome,
+    // What the customer gets back, only used for partial refunds
+    #[serde(default)]
+    refund_amount: Option<Decimal>,
+}
+
+// Ends a dispute and carries out the outcome on the payment. This endpoint is callable only by
+// the billing admin of the office.
+pub async fn dispute_resolve(
+    office_id: String,
+    task_id: String,
+    dispute_id: String,
+    r: DataRequest<ResolveDispute, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let resolve;
+    if let Some(q) = r.data {
+        resolve = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Caller is not a billing admin of the office"
+        ))));
+    }
+
+    let (t, d) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(DISPUTE_COLLECTION, [&office_id], &dispute_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (dispute, _): (Dispute, _) = d?;
+    if dispute.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "task_id does not match url ({} != {}).",
+            dispute.task_id, task_id
+        ))));
+    }
+    if !dispute.is_open() {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The dispute has already been resolved"
+        ))));
+    }
+
+    let refund_amount = match resolve.outcome {
+        DisputeOutcome::PartialRefund => resolve.refund_amount,
+        DisputeOutcome::FullRelease | DisputeOutcome::FullRefund => None,
+    };
+
+    // NOTE: The money is moved before the dispute is closed so that a failure can be retried
+    let payment = dispute::settle(&dispute, resolve.outcome, refund_amount).await?;
+
+    let dispute = modify(
+        DISPUTE_COLLECTION,
+        [&office_id],
+        &dispute_id,
+        |mut dispute: Dispute| {
+            dispute.outcome = Some(resolve.outcome);
+            dispute.refund_amount = match resolve.outcome {
+                DisputeOutcome::PartialRefund => refund_amount,
+                DisputeOutcome::FullRefund => Some(payment.amount),
+                DisputeOutcome::FullRelease => None,
+            };
+            if dispute.mediator_id.is_none() {
+                dispute.mediator_id = Some(claims.sub.clone());
+            }
+            dispute.resolved = Some(Utc::now());
+            dispute.modified = Utc::now();
+            Ok(dispute)
+        },
+    )
+    .await?;
+
+    let message = match resolve.outcome {
+        DisputeOutcome::FullRelease => format!(
+            "Reklamationen på \"{}\" är avslutad. Betalningen har släppts till hantverkaren.",
+            task.title
+        ),
+        DisputeOutcome::PartialRefund => format!(
+            "Reklamationen på \"{}\" är avslutad. {} kr återbetalas till kunden och resten släpps till hantverkaren.",
+            task.title,
+            refund_amount.unwrap_or_default()
+        ),
+        DisputeOutcome::FullRefund => format!(
+            "Reklamationen på \"{}\" är avslutad. Betalningen återbetalas till kunden.",
+            task.title
+        ),
+    };
+    dispute::notify_parties(&dispute, &task, DisputeParty::Mediator, message);
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&dispute),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,98 @@
+use crate::dispute;
+use crate::fault::Fault;
+use crate::models::{Claims, Dispute, DisputeParty, DisputeStatement, Task};
+use crate::util::{DataRequest, DataResponse, Empty};
+use crate::{DISPUTE_COLLECTION, TASK_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, modify};
+use serde::Deserialize;
+use tokio::join;
+use uuid::Uuid;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewStatement {
+    text: String,
+}
+
+// Adds a statement to the timeline of an open dispute. This endpoint is callable by both parties
+// and by office admins, the first admin to make a statement becomes the mediator.
+pub async fn dispute_statement_post(
+    office_id: String,
+    task_id: String,
+    dispute_id: String,
+    r: DataRequest<NewStatement, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let new_statement;
+    if let Some(q) = r.data {
+        new_statement = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    let (t, d) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(DISPUTE_COLLECTION, [&office_id], &dispute_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (dispute, _): (Dispute, _) = d?;
+    if dispute.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "task_id does not match url ({} != {}).",
+            dispute.task_id, task_id
+        ))));
+    }
+    let party = match dispute::party_of(&claims, &task, &dispute.craftsman_id) {
+        Some(party) => party,
+        None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Caller is not a party of the dispute"
+            ))));
+        }
+    };
+
+    let dispute = modify(
+        DISPUTE_COLLECTION,
+        [&office_id],
+        &dispute_id,
+        |mut dispute: Dispute| {
+            if !dispute.is_open() {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "The dispute has already been resolved"
+                ))));
+            }
+            if party == DisputeParty::Mediator && dispute.mediator_id.is_none() {
+                dispute.mediator_id = Some(claims.sub.clone());
+            }
+            dispute.statements.push(DisputeStatement {
+                id: Uuid::new_v4().to_string(),
+                author_id: claims.sub.clone(),
+                party,
+                text: new_statement.text.clone(),
+                images: Vec::new(),
+                created: Utc::now(),
+            });
+            dispute.modified = Utc::now();
+            Ok(dispute)
+        },
+    )
+    .await?;
+
+    dispute::notify_parties(
+        &dispute,
+        &task,
+        party,
+        format!(
+            "Det finns ett nytt inlägg i reklamationen på \"{}\".",
+            task.title
+        ),
+    );
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&dispute),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,38 @@
+use crate::fault::Fault;
+use crate::ledger::{balances, AccountBalance};
+use crate::models::{Claims, LedgerEntry, RoleFlags};
+use crate::util::{has_role, DataResponse, Empty};
+use crate::LEDGER_COLLECTION;
+use cosmos_utils::query;
+use serde::Serialize;
+use warp::reject;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    balances: Vec<AccountBalance>,
+}
+
+// Returns the balance of every ledger account for the whole office. The balance of the swish
+// account should match what is left for this office on the swish intermediate account.
+pub async fn ledger_get(
+    office_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to read the ledger",
+        ))));
+    }
+
+    let q = format!(r#"SELECT * FROM {} l"#, LEDGER_COLLECTION);
+    let entries: Vec<LedgerEntry> = query(LEDGER_COLLECTION, [&office_id], q, -1).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response {
+            balances: balances(&entries),
+        }),
+        extra: None::<Empty>,
+    }))
+}
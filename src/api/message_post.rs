@@ -1,10 +1,10 @@
 use crate::fault::Fault;
-use crate::models::{Bid, Claims, Craftsman, Message, Task, User, Chat};
+use crate::models::{Bid, Chat, Claims, Craftsman, Message, Task, User};
 use crate::push::send_custom_pn;
 use crate::util::{log, DataRequest, DataResponse, Empty};
 use crate::{
-    BID_COLLECTION, CRAFTSMAN_COLLECTION, MESSAGE_COLLECTION, NOTIFICATION_HUB_ACCOUNT,
-    TASK_COLLECTION, USER_COLLECTION, CHAT_COLLECTION
+    BID_COLLECTION, CHAT_COLLECTION, CRAFTSMAN_COLLECTION, MESSAGE_COLLECTION,
+    NOTIFICATION_HUB_ACCOUNT, TASK_COLLECTION, USER_COLLECTION,
 };
 use cosmos_utils::{get, insert};
 use uuid::Uuid;
@@ -72,8 +72,15 @@ pub async fn message_post(
         ))));
     }
 
+    if chat.closed {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The chat has been closed since the bid or the task was cancelled."
+        ))));
+    }
+
     message.id = Uuid::new_v4().to_string();
     message.is_read = false;
+    message.system = false;
     message.modified = chrono::Utc::now();
 
     insert(MESSAGE_COLLECTION, [&office_id], &message, None).await?;
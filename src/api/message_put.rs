@@ -69,6 +69,11 @@ pub async fn message_put(
         [&office_id],
         &message_id,
         |mut message: Message| {
+            if message.system {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "Messages written by the server can not be changed"
+                ))));
+            }
             message.text = new_message.text.clone();
             message.modified = Utc::now();
             Ok(message)
@@ -0,0 +1,162 @@
+use crate::dispute;
+use crate::fault::Fault;
+use crate::ledger;
+use crate::models::{Bid, Claims, Craftsman, Office, Payment, PaymentState, Task, User};
+use crate::payment;
+use crate::push::send_custom_pn;
+use crate::util::{log, DataResponse, Empty};
+use crate::{
+    BID_COLLECTION, CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, OFFICE_COLLECTION,
+    PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, maybe_modify, ModifyReturn};
+use tokio::join;
+use warp::reject;
+
+// Releases the escrowed payment of a milestone to the craftsman. This endpoint is callable only by
+// the task-owner, the last milestone is approved by finishing the task.
+pub async fn milestone_approve(
+    office_id: String,
+    task_id: String,
+    bid_id: String,
+    milestone_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (t, b, p) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(BID_COLLECTION, [&office_id], &bid_id),
+        payment::active_payments(&office_id, &bid_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (bid, _): (Bid, _) = b?;
+    let payments = p?;
+
+    if claims.sub != task.user_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "User is not the task owner"
+        ))));
+    }
+    if task.accepted_bid.as_ref() != Some(&bid_id) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only milestones of the accepted bid can be approved"
+        ))));
+    }
+
+    let milestone = match bid.milestone(&milestone_id) {
+        Some(m) => m,
+        None => {
+            return Err(reject::custom(Fault::NotFound(format!(
+                "The bid does not have a milestone with id {}",
+                milestone_id
+            ))));
+        }
+    };
+    if bid.last_milestone().map(|m| &m.id) == Some(&milestone.id) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The last milestone is approved by finishing the task"
+        ))));
+    }
+    payment::verify_earlier_milestones(&bid, milestone, &payments, true)?;
+    dispute::verify_no_open_dispute(&office_id, &task_id).await?;
+    let payment_id = match payment::milestone_payment(&payments, &milestone.id) {
+        Some(p) => p.id.clone(),
+        None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "The milestone has not been paid"
+            ))));
+        }
+    };
+
+    let payment = maybe_modify(
+        PAYMENT_COLLECTION,
+        [&office_id],
+        &payment_id,
+        |mut payment: Payment| match payment.payment_state {
+            PaymentState::PaidToEscrow => {
+                payment.payment_state = PaymentState::Finalized;
+                payment.modified = Utc::now();
+                Ok(ModifyReturn::Replace(payment))
+            }
+            // Idempotancy
+            PaymentState::Finalized | PaymentState::PaidToCraftsman => {
+                Ok(ModifyReturn::DontReplace(payment))
+            }
+            s => Err(reject::custom(Fault::Forbidden(format!(
+                "Could not approve milestone as its payment was not PaidToEscrow but {:?} instead",
+                s
+            )))),
+        },
+    )
+    .await?;
+    let payment = match payment {
+        ModifyReturn::Replace(payment) => payment,
+        ModifyReturn::DontReplace(payment) => {
+            return Ok(warp::reply::json(&DataResponse {
+                data: Some(&payment),
+                extra: None::<Empty>,
+            }));
+        }
+    };
+
+    let (office, _): (Office, _) = get(OFFICE_COLLECTION, [&office_id], &office_id).await?;
+    if let Err(e) = ledger::record_finalized(
+        &payment,
+        &payment.costs(&bid, None),
+        payment.brokerage_percentage(&office),
+    )
+    .await
+    {
+        log(format!(
+            "Could not book finalized payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+
+    let description = milestone.description.clone();
+    tokio::task::spawn(async move {
+        let users = async {
+            let (craftsman, _): (Craftsman, _) =
+                get(CRAFTSMAN_COLLECTION, [&office_id], &bid.craftsman_id).await?;
+            let (craftsman_user, _): (User, _) =
+                get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+            let (task_owner, _): (User, _) =
+                get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+            Result::<_, warp::Rejection>::Ok((craftsman_user, task_owner))
+        };
+        let (craftsman_user, task_owner) = match users.await {
+            Ok(users) => users,
+            Err(e) => {
+                log(format!(
+                    "Could not send PN in milestone_approve since cosmos get failed with {:?}",
+                    e
+                ));
+                return;
+            }
+        };
+        // Send PN to the craftsman
+        if let Err(e) = send_custom_pn(
+            &craftsman_user,
+            &format!(
+                "{} har godkänt delmomentet \"{}\"!",
+                task_owner.name(),
+                description
+            ),
+            None,
+            &NOTIFICATION_HUB_ACCOUNT,
+        )
+        .await
+        {
+            log(format!(
+                "Could not send PN in milestone_approve due to {}",
+                e
+            ));
+        }
+    });
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&payment),
+        extra: None::<Empty>,
+    }))
+}
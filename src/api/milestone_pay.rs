@@ -0,0 +1,118 @@
+use crate::fault::Fault;
+use crate::models::{Bid, Claims, PaymentMethod, Task, User};
+use crate::payment::{self, Initiated};
+use crate::tax;
+use crate::util::{DataRequest, DataResponse, Empty};
+use crate::{BID_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION};
+use cosmos_utils::{get, insert};
+use serde::{Deserialize, Serialize};
+use tokio::join;
+use warp::reject;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    #[serde(flatten)]
+    initiated: Initiated,
+    payment_id: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PayMilestone {
+    #[serde(default)]
+    payment_method: PaymentMethod,
+}
+
+// Starts the payment of the next milestone of the accepted bid. The first milestone is paid when
+// the bid is accepted.
+pub async fn milestone_pay(
+    office_id: String,
+    task_id: String,
+    bid_id: String,
+    milestone_id: String,
+    r: DataRequest<PayMilestone, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pay = r.data.unwrap_or_default();
+
+    let (t, b, p) = join!(
+        get(TASK_COLLECTION, [&office_id], &task_id),
+        get(BID_COLLECTION, [&office_id], &bid_id),
+        payment::active_payments(&office_id, &bid_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (bid, _): (Bid, _) = b?;
+    let payments = p?;
+
+    if claims.sub != task.user_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Only the task poster may pay milestones",
+        ))));
+    }
+    if bid.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "Task id does not match url ({} != {}).",
+            bid.task_id, task_id
+        ))));
+    }
+    if task.accepted_bid.as_ref() != Some(&bid_id) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The bid has to be accepted before its milestones can be paid",
+        ))));
+    }
+    if task.finished {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The task has already been finished",
+        ))));
+    }
+
+    let milestone = match bid.milestone(&milestone_id) {
+        Some(m) => m,
+        None => {
+            return Err(reject::custom(Fault::NotFound(format!(
+                "The bid does not have a milestone with id {}",
+                milestone_id
+            ))));
+        }
+    };
+    if payment::milestone_payment(&payments, &milestone.id).is_some() {
+        return Err(reject::custom(Fault::Duplicate(format!(
+            "A payment for this milestone is either already in progress or has been completed",
+        ))));
+    }
+    payment::verify_earlier_milestones(&bid, milestone, &payments, false)?;
+
+    let (task_owner, _): (User, _) = get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+
+    // NOTE: Only the share of the deduction given on this milestone is counted against what the
+    // customer has left this year
+    let today = tax::today();
+    let rules = tax::rules_at(today);
+    let (deduction_type, allowance) = tax::task_deduction(rules, &task, today).await?;
+    let mut payment =
+        payment::new_payment(&bid, Some(milestone), pay.payment_method, deduction_type);
+    if let Some(allowance) = allowance {
+        if payment.root_deduction > allowance {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "The deduction of the milestone {} is more than the {} the customer has left this year",
+                payment.root_deduction, allowance
+            ))));
+        }
+    }
+
+    let initiated = payment::provider(&payment.payment_method)
+        .initiate(&mut payment, &task_owner)
+        .await?;
+
+    insert(PAYMENT_COLLECTION, [&payment.office_id], &payment, None).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response {
+            initiated,
+            payment_id: payment.id.clone(),
+        }),
+        extra: None::<Empty>,
+    }))
+}
@@ -28,6 +28,8 @@ mod craftsman_note_delete;
 pub use craftsman_note_delete::craftsman_note_delete;
 mod craftsman_post;
 pub use craftsman_post::craftsman_post;
+mod craftsman_area_migrate;
+pub use craftsman_area_migrate::craftsman_area_migrate;
 mod craftsman_freeze;
 pub use craftsman_freeze::craftsman_freeze;
 mod craft_approve;
@@ -134,3 +136,77 @@ mod ad_image_put;
 pub use ad_image_put::ad_image_put;
 mod ad_video_put;
 pub use ad_video_put::ad_video_put;
+mod ledger_get;
+pub use ledger_get::ledger_get;
+mod craftsman_ledger_get;
+pub use craftsman_ledger_get::craftsman_ledger_get;
+mod sie_export;
+pub use sie_export::sie_export;
+mod office_chart_of_accounts_put;
+pub use office_chart_of_accounts_put::office_chart_of_accounts_put;
+mod rot_claim_get;
+pub use rot_claim_get::rot_claim_get;
+mod user_deductions_get;
+pub use user_deductions_get::user_deductions_get;
+mod payment_receipt_get;
+pub use payment_receipt_get::payment_receipt_get;
+mod payment_reconcile;
+pub use payment_reconcile::payment_reconcile;
+mod payment_cancel;
+pub use payment_cancel::payment_cancel;
+mod milestone_pay;
+pub use milestone_pay::milestone_pay;
+mod milestone_approve;
+pub use milestone_approve::milestone_approve;
+mod change_order_post;
+pub use change_order_post::change_order_post;
+mod change_order_accept;
+pub use change_order_accept::change_order_accept;
+mod change_order_reject;
+pub use change_order_reject::change_order_reject;
+mod dispute_post;
+pub use dispute_post::dispute_post;
+mod dispute_statement_post;
+pub use dispute_statement_post::dispute_statement_post;
+mod dispute_image_put;
+pub use dispute_image_put::dispute_image_put;
+mod dispute_resolve;
+pub use dispute_resolve::dispute_resolve;
+mod office_auto_finish_put;
+pub use office_auto_finish_put::office_auto_finish_put;
+mod office_brokerage_put;
+pub use office_brokerage_put::office_brokerage_put;
+mod campaign_post;
+pub use campaign_post::campaign_post;
+mod campaign_delete;
+pub use campaign_delete::campaign_delete;
+mod referral_code_post;
+pub use referral_code_post::referral_code_post;
+mod craftsman_statement_get;
+pub use craftsman_statement_get::craftsman_statement_get;
+mod craftsman_statement_export;
+pub use craftsman_statement_export::craftsman_statement_export;
+mod task_search;
+pub use task_search::task_search;
+mod saved_search_post;
+pub use saved_search_post::saved_search_post;
+mod saved_search_put;
+pub use saved_search_put::saved_search_put;
+mod saved_search_delete;
+pub use saved_search_delete::saved_search_delete;
+mod saved_searches_get;
+pub use saved_searches_get::saved_searches_get;
+mod task_state_migrate;
+pub use task_state_migrate::task_state_migrate;
+mod task_extend;
+pub use task_extend::task_extend;
+mod office_task_expiry_put;
+pub use office_task_expiry_put::office_task_expiry_put;
+mod task_cancel;
+pub use task_cancel::task_cancel;
+mod bid_withdraw;
+pub use bid_withdraw::bid_withdraw;
+mod bid_comparison_get;
+pub use bid_comparison_get::bid_comparison_get;
+mod price_estimate_post;
+pub use price_estimate_post::price_estimate_post;
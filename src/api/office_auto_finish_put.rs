@@ -0,0 +1,73 @@
+use crate::fault::Fault;
+use crate::models::{Claims, Office, RoleFlags};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::OFFICE_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use serde::Deserialize;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoFinish {
+    days: i64,
+}
+
+// Sets how many days the customer has to approve the work after the craftsman marked a task as
+// finished, before the task is finished automatically
+pub async fn office_auto_finish_put(
+    office_id: String,
+    r: DataRequest<AutoFinish, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let auto_finish;
+    if let Some(q) = r.data {
+        auto_finish = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(
+        Some(&office_id),
+        &claims,
+        RoleFlags::OFFICE_CONTENT_ADMIN
+            | RoleFlags::OFFICE_PERSONNEL_ADMIN
+            | RoleFlags::OFFICE_BILLING_ADMIN,
+    ) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office admin to change the grace period",
+        ))));
+    }
+
+    if auto_finish.days < 1 {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The grace period has to be at least one day, not {}",
+            auto_finish.days
+        ))));
+    }
+    if auto_finish.days > Office::MAX_AUTO_FINISH_DAYS {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The grace period can be at most {} days, not {}",
+            Office::MAX_AUTO_FINISH_DAYS,
+            auto_finish.days
+        ))));
+    }
+
+    let office = modify(
+        OFFICE_COLLECTION,
+        [&office_id],
+        &office_id,
+        |mut office: Office| {
+            office.auto_finish_days = auto_finish.days;
+            office.modified = Utc::now();
+            Ok(office)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&office),
+        extra: None::<Empty>,
+    }))
+}
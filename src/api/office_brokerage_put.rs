@@ -0,0 +1,95 @@
+use crate::fault::Fault;
+use crate::models::{BrokeragePromotion, Claims, CraftBrokerage, Office, RoleFlags};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::OFFICE_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use rust_decimal::prelude::{Decimal, One, Zero};
+use serde::Deserialize;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfficeBrokerage {
+    brokerage_percentage: Decimal,
+    #[serde(default)]
+    craft_brokerage: Vec<CraftBrokerage>,
+    #[serde(default)]
+    brokerage_promotions: Vec<BrokeragePromotion>,
+}
+
+fn verify_percentage(percentage: Decimal) -> Result<(), warp::Rejection> {
+    if percentage < Decimal::zero() || percentage >= Decimal::one() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The brokerage {} has to be a fraction between 0 and 1",
+            percentage
+        ))));
+    }
+    Ok(())
+}
+
+// Sets the brokerage of the office. Bids keep the brokerage they were posted with, so this only
+// changes the brokerage of new bids.
+pub async fn office_brokerage_put(
+    office_id: String,
+    r: DataRequest<OfficeBrokerage, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let brokerage;
+    if let Some(q) = r.data {
+        brokerage = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to change the brokerage",
+        ))));
+    }
+
+    verify_percentage(brokerage.brokerage_percentage)?;
+    for (i, craft) in brokerage.craft_brokerage.iter().enumerate() {
+        verify_percentage(craft.percentage)?;
+        if brokerage
+            .craft_brokerage
+            .iter()
+            .skip(i + 1)
+            .any(|c| c.craft_type == craft.craft_type)
+        {
+            return Err(reject::custom(Fault::Duplicate(format!(
+                "The craft {:?} has more than one brokerage",
+                craft.craft_type
+            ))));
+        }
+    }
+    for promotion in &brokerage.brokerage_promotions {
+        verify_percentage(promotion.percentage)?;
+        if promotion.starts >= promotion.ends {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The promotion '{}' ends before it starts",
+                promotion.description
+            ))));
+        }
+    }
+
+    let office = modify(
+        OFFICE_COLLECTION,
+        [&office_id],
+        &office_id,
+        |mut office: Office| {
+            office.brokerage_percentage = brokerage.brokerage_percentage;
+            office.craft_brokerage = brokerage.craft_brokerage.clone();
+            office.brokerage_promotions = brokerage.brokerage_promotions.clone();
+            office.modified = Utc::now();
+            Ok(office)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&office),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,90 @@
+use crate::fault::Fault;
+use crate::models::{ChartOfAccounts, Claims, LedgerAccount, Office, RoleFlags};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::OFFICE_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use warp::reject;
+
+// Sets which bookkeeping accounts the ledger accounts are exported to
+pub async fn office_chart_of_accounts_put(
+    office_id: String,
+    r: DataRequest<ChartOfAccounts, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let chart_of_accounts;
+    if let Some(q) = r.data {
+        chart_of_accounts = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to change the chart of accounts",
+        ))));
+    }
+
+    if chart_of_accounts.fiscal_year_start_month < 1
+        || chart_of_accounts.fiscal_year_start_month > 12
+    {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The fiscal year has to start in a month from 1 to 12, not {}",
+            chart_of_accounts.fiscal_year_start_month
+        ))));
+    }
+
+    for (i, account) in chart_of_accounts.accounts.iter().enumerate() {
+        // NOTE: SIE requires the account numbers to be four digits
+        if account.number < 1000 || account.number > 9999 {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "Account number {} is not a four digit number",
+                account.number
+            ))));
+        }
+        if chart_of_accounts
+            .accounts
+            .iter()
+            .skip(i + 1)
+            .any(|a| a.ledger_account == account.ledger_account)
+        {
+            return Err(reject::custom(Fault::Duplicate(format!(
+                "Ledger account {:?} is mapped more than once",
+                account.ledger_account
+            ))));
+        }
+    }
+
+    // NOTE: The ledger accounts that are not mapped fall back to their default account, so it is
+    // the numbers they all end up with that have to differ
+    let numbers: Vec<u32> = LedgerAccount::all()
+        .iter()
+        .map(|a| chart_of_accounts.get(*a).number)
+        .collect();
+    for (i, number) in numbers.iter().enumerate() {
+        if numbers.iter().skip(i + 1).any(|n| n == number) {
+            return Err(reject::custom(Fault::Duplicate(format!(
+                "Account number {} is used for more than one ledger account",
+                number
+            ))));
+        }
+    }
+
+    let office = modify(
+        OFFICE_COLLECTION,
+        [&office_id],
+        &office_id,
+        |mut office: Office| {
+            office.chart_of_accounts = chart_of_accounts.clone();
+            office.modified = Utc::now();
+            Ok(office)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&office),
+        extra: None::<Empty>,
+    }))
+}
@@ -1,10 +1,12 @@
 use crate::fault::Fault;
 use crate::models::{
-    Bid, Chat, Claims, Craftsman, CraftsmanNote, Message, Office, Payment, RoleFlags, Task, User,
+    Bid, Campaign, Cancellation, ChangeOrder, Chat, Claims, Craftsman, CraftsmanNote, Dispute,
+    Message, Office, Payment, RoleFlags, Task, User,
 };
 use crate::util::{self, has_role, DataResponse, Empty};
 use crate::{
-    BID_COLLECTION, CHAT_COLLECTION, CRAFTSMAN_COLLECTION, CRAFTSMAN_NOTE_COLLECTION,
+    BID_COLLECTION, CAMPAIGN_COLLECTION, CANCELLATION_COLLECTION, CHANGE_ORDER_COLLECTION,
+    CHAT_COLLECTION, CRAFTSMAN_COLLECTION, CRAFTSMAN_NOTE_COLLECTION, DISPUTE_COLLECTION,
     MESSAGE_COLLECTION, OFFICE_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION,
 };
 use chrono::{DateTime, Utc};
@@ -36,6 +38,14 @@ pub struct UserPollDataResponse {
     pub payments: Vec<Payment>,
     #[serde(skip_serializing_if = "util::is_empty")]
     pub bids: Vec<Bid>,
+    #[serde(skip_serializing_if = "util::is_empty")]
+    pub change_orders: Vec<ChangeOrder>,
+    #[serde(skip_serializing_if = "util::is_empty")]
+    pub disputes: Vec<Dispute>,
+    #[serde(skip_serializing_if = "util::is_empty")]
+    pub campaigns: Vec<Campaign>,
+    #[serde(skip_serializing_if = "util::is_empty")]
+    pub cancellations: Vec<Cancellation>,
 }
 
 /// Poll for admins, returns information about an office.
@@ -149,7 +159,59 @@ pub async fn office_poll(
         Result::<_, CosmosErrorStruct>::Ok(payments)
     };
 
-    let (office, users, tasks, bids, chats, messages, craftsmen, craftsman_notes, payments) = tokio::join!(
+    let q = format!(
+        "SELECT * FROM {} o WHERE o.officeId = \"{}\"{}",
+        CHANGE_ORDER_COLLECTION, &office_id, since
+    );
+    let change_orders = async {
+        let change_orders: Vec<ChangeOrder> =
+            query(CHANGE_ORDER_COLLECTION, [&office_id], q, -1).await?;
+        Result::<_, CosmosErrorStruct>::Ok(change_orders)
+    };
+
+    let q = format!(
+        "SELECT * FROM {} o WHERE o.officeId = \"{}\"{}",
+        DISPUTE_COLLECTION, &office_id, since
+    );
+    let disputes = async {
+        let disputes: Vec<Dispute> = query(DISPUTE_COLLECTION, [&office_id], q, -1).await?;
+        Result::<_, CosmosErrorStruct>::Ok(disputes)
+    };
+
+    let q = format!(
+        "SELECT * FROM {} o WHERE o.officeId = \"{}\"{}",
+        CAMPAIGN_COLLECTION, &office_id, since
+    );
+    let campaigns = async {
+        let campaigns: Vec<Campaign> = query(CAMPAIGN_COLLECTION, [&office_id], q, -1).await?;
+        Result::<_, CosmosErrorStruct>::Ok(campaigns)
+    };
+
+    let q = format!(
+        "SELECT * FROM {} o WHERE o.officeId = \"{}\"{}",
+        CANCELLATION_COLLECTION, &office_id, since
+    );
+    let cancellations = async {
+        let cancellations: Vec<Cancellation> =
+            query(CANCELLATION_COLLECTION, [&office_id], q, -1).await?;
+        Result::<_, CosmosErrorStruct>::Ok(cancellations)
+    };
+
+    let (
+        office,
+        users,
+        tasks,
+        bids,
+        chats,
+        messages,
+        craftsmen,
+        craftsman_notes,
+        payments,
+        change_orders,
+        disputes,
+        campaigns,
+        cancellations,
+    ) = tokio::join!(
         office,
         users,
         tasks,
@@ -158,7 +220,11 @@ pub async fn office_poll(
         messages,
         craftsmen,
         craftsman_notes,
-        payments
+        payments,
+        change_orders,
+        disputes,
+        campaigns,
+        cancellations
     );
     let office = office?;
     let users = users?;
@@ -169,6 +235,10 @@ pub async fn office_poll(
     let craftsmen = craftsmen?;
     let craftsman_notes = craftsman_notes?;
     let payments = payments?;
+    let change_orders = change_orders?;
+    let disputes = disputes?;
+    let campaigns = campaigns?;
+    let cancellations = cancellations?;
 
     let res = match serde_json::to_string(&DataResponse {
         data: Some(&UserPollDataResponse {
@@ -181,6 +251,10 @@ pub async fn office_poll(
             chats,
             payments,
             bids,
+            change_orders,
+            disputes,
+            campaigns,
+            cancellations,
         }),
         extra: None::<Empty>,
     }) {
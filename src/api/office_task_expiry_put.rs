@@ -0,0 +1,72 @@
+use crate::fault::Fault;
+use crate::models::{Claims, Office, RoleFlags};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::OFFICE_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use serde::Deserialize;
+use warp::reject;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskExpiry {
+    days: i64,
+}
+
+// Sets how many days a published task stays open without an accepted bid before it expires
+pub async fn office_task_expiry_put(
+    office_id: String,
+    r: DataRequest<TaskExpiry, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let task_expiry;
+    if let Some(q) = r.data {
+        task_expiry = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(
+        Some(&office_id),
+        &claims,
+        RoleFlags::OFFICE_CONTENT_ADMIN
+            | RoleFlags::OFFICE_PERSONNEL_ADMIN
+            | RoleFlags::OFFICE_BILLING_ADMIN,
+    ) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office admin to change the task expiry",
+        ))));
+    }
+
+    if task_expiry.days < 1 {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "A task has to stay open for at least one day, not {}",
+            task_expiry.days
+        ))));
+    }
+    if task_expiry.days > Office::MAX_TASK_EXPIRY_DAYS {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "A task can stay open for at most {} days, not {}",
+            Office::MAX_TASK_EXPIRY_DAYS,
+            task_expiry.days
+        ))));
+    }
+
+    let office = modify(
+        OFFICE_COLLECTION,
+        [&office_id],
+        &office_id,
+        |mut office: Office| {
+            office.task_expiry_days = task_expiry.days;
+            office.modified = Utc::now();
+            Ok(office)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&office),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,61 @@
+use crate::{
+    fault::Fault,
+    models::{Claims, Payment, PaymentState, RoleFlags, Task},
+    payment,
+    util::{has_role, DataResponse, Empty},
+    PAYMENT_COLLECTION, TASK_COLLECTION,
+};
+use cosmos_utils::get;
+use warp::reject;
+
+// Aborts a payment the customer has not paid yet, after which the customer can accept a bid again
+pub async fn payment_cancel(
+    office_id: String,
+    task_id: String,
+    bid_id: String,
+    payment_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (payment, _): (Payment, _) = get(PAYMENT_COLLECTION, [&office_id], &payment_id).await?;
+
+    if payment.task_id != task_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "Task id does not match url ({} != {}).",
+            payment.task_id, task_id
+        ))));
+    } else if payment.bid_id != bid_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "Bid id does not match url ({} != {}).",
+            payment.bid_id, bid_id
+        ))));
+    }
+
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+
+    // Make sure user is either admin or the paying user.
+    if task.user_id != claims.sub
+        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "User does not have sufficient roles."
+        ))));
+    }
+
+    match payment.payment_state {
+        PaymentState::Initialized => (),
+        s => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only payments that have not been paid can be cancelled, this payment is {:?}",
+                s
+            ))));
+        }
+    }
+
+    let payment = payment::cancel(&payment).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&payment),
+        extra: None::<Empty>,
+    }))
+}
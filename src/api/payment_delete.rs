@@ -1,6 +1,7 @@
 use crate::fault::Fault;
-use crate::models::{Claims, Payment, RoleFlags};
-use crate::util::{has_role, DataResponse, Empty};
+use crate::models::{Claims, Payment, PaymentState, RoleFlags};
+use crate::payment;
+use crate::util::{has_role, log, DataResponse, Empty};
 use crate::PAYMENT_COLLECTION;
 use chrono::Utc;
 use cosmos_utils::modify;
@@ -50,6 +51,20 @@ pub async fn payment_delete(
     )
     .await?;
 
+    // NOTE: A payment the customer has not paid yet is withdrawn so that it can not be paid after
+    // it has been deleted
+    if let PaymentState::Initialized = deleted_payment.payment_state {
+        if let Err(e) = payment::provider(&deleted_payment.payment_method)
+            .cancel(&deleted_payment)
+            .await
+        {
+            log(format!(
+                "Could not cancel deleted payment {} due to {:?}",
+                deleted_payment.id, e
+            ));
+        }
+    }
+
     Ok(warp::reply::json(&DataResponse {
         data: Some(deleted_payment),
         extra: None::<Empty>,
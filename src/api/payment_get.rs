@@ -15,7 +15,8 @@ pub async fn payment_get(
     claims: Claims,
     _v: u8,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let (payment, _etag): (Payment, _) = get(PAYMENT_COLLECTION, [&office_id], &payment_id).await?;
+    let (mut payment, _etag): (Payment, _) =
+        get(PAYMENT_COLLECTION, [&office_id], &payment_id).await?;
 
     if payment.task_id != task_id {
         return Err(reject::custom(Fault::IllegalArgument(format!(
@@ -35,15 +36,21 @@ pub async fn payment_get(
     // Get task.
     let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &bid.task_id).await?;
 
-    // Make sure user is either admin or the paying user.
-    if task.user_id != claims.sub
-        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN)
-    {
+    // Make sure user is either admin, the paying user or the paid craftsman.
+    let is_billing_admin = has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN);
+    // NOTE: Craftsman id is the same as the user id
+    let is_craftsman = payment.craftsman_id == claims.sub;
+    if task.user_id != claims.sub && !is_billing_admin && !is_craftsman {
         return Err(reject::custom(Fault::Forbidden(format!(
             "User does not have sufficient roles."
         ))));
     }
 
+    // NOTE: The brokerage is between Toolit and the craftsman
+    if !is_billing_admin && !is_craftsman {
+        payment.brokerage = None;
+    }
+
     Ok(warp::reply::json(&DataResponse {
         data: Some(payment),
         extra: None::<Empty>,
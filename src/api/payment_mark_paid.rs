@@ -1,12 +1,15 @@
 use crate::fault::Fault;
-use crate::models::{Claims, Payment, PaymentState, RoleFlags};
-use crate::util::{has_role, DataResponse, Empty};
+use crate::ledger;
+use crate::models::{Claims, Payment, PaymentMethod, PaymentState, RoleFlags};
+use crate::payment;
+use crate::util::{has_role, log, DataResponse, Empty};
 use crate::PAYMENT_COLLECTION;
 use chrono::Utc;
-use cosmos_utils::modify;
+use cosmos_utils::{get, modify};
 use warp::reject::custom;
 
-// This endpoint marks a payment as paid to the toolit craftsman
+// This endpoint marks a payment as paid to the toolit craftsman, or an invoice as paid by the
+// customer
 pub async fn payment_mark_paid(
     office_id: String,
     _task_id: String,
@@ -21,6 +24,20 @@ pub async fn payment_mark_paid(
         ))));
     }
 
+    let (payment, _): (Payment, _) = get(PAYMENT_COLLECTION, [&office_id], &payment_id).await?;
+    // NOTE: An invoice that has been paid into our bank account goes into escrow
+    if let (PaymentState::Initialized, PaymentMethod::Invoice) =
+        (&payment.payment_state, &payment.payment_method)
+    {
+        let payment =
+            payment::escrow(&office_id, &payment_id, payment.amount, None, Utc::now()).await?;
+        payment::paid_to_escrow(payment.clone()).await;
+        return Ok(warp::reply::json(&DataResponse {
+            data: Some(&payment),
+            extra: None::<Empty>,
+        }));
+    }
+
     let payment = modify(PAYMENT_COLLECTION, [&office_id], &payment_id, |mut payment: Payment| {
         match payment.payment_state {
             PaymentState::Finalized => {
@@ -37,6 +54,13 @@ pub async fn payment_mark_paid(
         };
     }).await?;
 
+    if let Err(e) = ledger::record_paid_to_craftsman(&payment).await {
+        log(format!(
+            "Could not book payout of payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+
     Ok(warp::reply::json(&DataResponse {
         data: Some(&payment),
         extra: None::<Empty>,
@@ -0,0 +1,123 @@
+use crate::fault::Fault;
+use crate::models::{BankTransaction, Claims, Payment, RoleFlags};
+use crate::payment;
+use crate::util::{has_role, log, DataRequest, DataResponse, Empty};
+use crate::PAYMENT_COLLECTION;
+use chrono::{TimeZone, Utc};
+use chrono_tz::Europe::Stockholm;
+use cosmos_utils::query;
+use serde::Serialize;
+use warp::reject;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+    // The invoice was paid and the payment is now in escrow
+    Matched,
+    // The reference is not a valid OCR reference
+    InvalidReference,
+    // No unpaid invoice has the reference
+    Unmatched,
+    // The customer did not pay the amount of the invoice
+    WrongAmount,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Reconciled {
+    #[serde(flatten)]
+    transaction: BankTransaction,
+    outcome: Outcome,
+    #[serde(skip_serializing_if = "crate::util::is_none")]
+    payment_id: Option<String>,
+}
+
+// Matches the incoming transactions of a bank statement against the unpaid invoices of the office
+// and moves every paid invoice into escrow. Transactions that could not be matched are returned
+// for manual handling.
+pub async fn payment_reconcile(
+    office_id: String,
+    r: DataRequest<Vec<BankTransaction>, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let transactions;
+    if let Some(q) = r.data {
+        transactions = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to reconcile payments",
+        ))));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.paymentMethod = "invoice" AND p.paymentState = "initialized""#,
+        PAYMENT_COLLECTION
+    );
+    let unpaid: Vec<Payment> = query(PAYMENT_COLLECTION, [&office_id], q, -1).await?;
+
+    let mut reconciled = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        let reference = transaction.reference.trim().to_string();
+        if !payment::is_valid_ocr(&reference) {
+            reconciled.push(Reconciled {
+                transaction,
+                outcome: Outcome::InvalidReference,
+                payment_id: None,
+            });
+            continue;
+        }
+        let found = unpaid.iter().find(|p| match &p.invoice {
+            Some(invoice) => invoice.ocr == reference,
+            None => false,
+        });
+        let (outcome, payment_id) = match found {
+            None => (Outcome::Unmatched, None),
+            Some(p) if p.amount != transaction.amount => (Outcome::WrongAmount, Some(p.id.clone())),
+            Some(p) => {
+                // NOTE: The bank only gives us the date, we count the payment as made at noon
+                let date = Stockholm
+                    .from_local_date(&transaction.date)
+                    .and_hms_opt(12, 0, 0)
+                    .single()
+                    .map(|d| d.with_timezone(&Utc))
+                    .unwrap_or_else(Utc::now);
+                match payment::escrow(
+                    &office_id,
+                    &p.id,
+                    transaction.amount,
+                    Some(reference.clone()),
+                    date,
+                )
+                .await
+                {
+                    Ok(payment) => {
+                        payment::paid_to_escrow(payment).await;
+                        (Outcome::Matched, Some(p.id.clone()))
+                    }
+                    Err(e) => {
+                        log(format!(
+                            "Could not move invoice payment {} into escrow due to {:?}",
+                            p.id, e
+                        ));
+                        (Outcome::Unmatched, Some(p.id.clone()))
+                    }
+                }
+            }
+        };
+        reconciled.push(Reconciled {
+            transaction,
+            outcome,
+            payment_id,
+        });
+    }
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&reconciled),
+        extra: None::<Empty>,
+    }))
+}
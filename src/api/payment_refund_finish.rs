@@ -1,5 +1,7 @@
 use crate::fault::Fault;
+use crate::ledger;
 use crate::models::{Payment, PaymentState};
+use crate::payment;
 use crate::util::{log, DataResponse, Empty};
 use crate::{PAYMENT_COLLECTION, SWISH_CERT_PASS, SWISH_CERT_PATH};
 use chrono::Utc;
@@ -71,43 +73,48 @@ pub async fn payment_refund_finish(
 
     match swish_refund_object.status {
         PaymentStatus::PAID => {
-            modify(
-                PAYMENT_COLLECTION,
-                [&office_id],
+            payment::refunded(
+                &office_id,
                 &payment_id,
-                |mut payment: Payment| {
-                    payment.payment_state = PaymentState::Refunded;
-                    payment.modified = Utc::now();
-                    Ok(payment)
-                },
+                &refund_id,
+                swish_refund_object.amount,
             )
             .await?;
         }
-        PaymentStatus::DECLINED => {
-            modify(
+        PaymentStatus::DECLINED | PaymentStatus::ERROR => {
+            let payment = modify(
                 PAYMENT_COLLECTION,
                 [&office_id],
                 &payment_id,
                 |mut payment: Payment| {
+                    // NOTE: The rest of a partially refunded payment has already been released
+                    // so the customer has to be paid back by hand
+                    if payment.is_partially_refunded() {
+                        log(format!(
+                            "The partial refund of payment {} failed and has to be made by hand",
+                            payment.id
+                        ));
+                        return Ok(payment);
+                    }
                     payment.payment_state = PaymentState::RefundFailed;
                     payment.modified = Utc::now();
                     Ok(payment)
                 },
             )
             .await?;
-        }
-        PaymentStatus::ERROR => {
-            modify(
-                PAYMENT_COLLECTION,
-                [&office_id],
-                &payment_id,
-                |mut payment: Payment| {
-                    payment.payment_state = PaymentState::RefundFailed;
-                    payment.modified = Utc::now();
-                    Ok(payment)
-                },
+            if let Err(e) = ledger::record_refund_finished(
+                &payment,
+                &refund_id,
+                swish_refund_object.amount,
+                false,
             )
-            .await?;
+            .await
+            {
+                log(format!(
+                    "Could not book failed refund of payment {} in the ledger due to {:?}",
+                    payment.id, e
+                ));
+            }
         }
         PaymentStatus::CREATED => {}
     };
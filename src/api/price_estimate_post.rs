@@ -0,0 +1,85 @@
+use crate::fault::Fault;
+use crate::models::{Claims, CraftType, PriceEstimate, RoleFlags, User};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::{PRICE_ESTIMATE_COLLECTION, USER_COLLECTION};
+use cosmos_utils::{get, query};
+use serde::Deserialize;
+use warp::reject;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftTask {
+    #[serde(default)]
+    crafts: Vec<CraftType>,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+// The going price of a task like the draft for each of its crafts, from the accepted bids on tasks
+// of that craft alone in the office. The estimate of the keyword of the draft with the most
+// accepted bids behind it is used, or the estimate of the whole craft if no keyword has one. Crafts
+// without enough accepted bids are left out.
+pub async fn price_estimate_post(
+    office_id: String,
+    r: DataRequest<DraftTask, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // NOTE: The estimates tell what the craftsmen of the office charge, so only the users of the
+    // office and its personnel get to see them
+    if !has_role(Some(&office_id), &claims, RoleFlags::all()) {
+        let (user, _): (User, _) = get(USER_COLLECTION, [&claims.sub], &claims.sub).await?;
+        if !user.office_ids.contains(&office_id) {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "User is not a member of the office."
+            ))));
+        }
+    }
+
+    let draft = r.data.unwrap_or_default();
+    if draft.crafts.is_empty() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "A price can only be estimated for a task with crafts."
+        ))));
+    }
+
+    let crafts = draft
+        .crafts
+        .iter()
+        .map(|c| serde_json::to_string(c).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    let keywords = PriceEstimate::keywords(&format!("{} {}", draft.title, draft.description))
+        .iter()
+        .map(|k| serde_json::to_string(k).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    let q = format!(
+        r#"SELECT * FROM {} e WHERE ARRAY_CONTAINS([{}], e.craft) AND (NOT IS_DEFINED(e.keyword) OR IS_NULL(e.keyword) OR ARRAY_CONTAINS([{}], e.keyword))"#,
+        PRICE_ESTIMATE_COLLECTION, crafts, keywords
+    );
+    let found: Vec<PriceEstimate> = query(PRICE_ESTIMATE_COLLECTION, [&office_id], q, -1).await?;
+
+    let mut estimates: Vec<&PriceEstimate> = Vec::new();
+    for craft in draft.crafts.iter() {
+        let by_keyword = found
+            .iter()
+            .filter(|e| e.craft == *craft && e.keyword.is_some())
+            .max_by_key(|e| e.sample_size);
+        let whole_craft = found
+            .iter()
+            .find(|e| e.craft == *craft && e.keyword.is_none());
+        if let Some(estimate) = by_keyword.or(whole_craft) {
+            if !estimates.iter().any(|e| e.id == estimate.id) {
+                estimates.push(estimate);
+            }
+        }
+    }
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&estimates),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,59 @@
+use crate::fault::Fault;
+use crate::models::{Campaign, CampaignKind, Claims, ReferralCode};
+use crate::util::{DataResponse, Empty};
+use crate::{CAMPAIGN_COLLECTION, REFERRAL_CODE_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, insert, query};
+use uuid::Uuid;
+use warp::reject;
+
+// Returns the referral code of the caller in a referral campaign, the code is made the first
+// time it is asked for
+pub async fn referral_code_post(
+    office_id: String,
+    campaign_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (campaign, _): (Campaign, _) = get(CAMPAIGN_COLLECTION, [&office_id], &campaign_id).await?;
+    if campaign.kind != CampaignKind::Referral {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "Only referral campaigns have codes of their own"
+        ))));
+    }
+    if !campaign.is_running(Utc::now()) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The campaign is not running"
+        ))));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} r WHERE r.campaignId = "{}" AND r.userId = "{}""#,
+        REFERRAL_CODE_COLLECTION, campaign.id, claims.sub
+    );
+    let existing: Vec<ReferralCode> = query(REFERRAL_CODE_COLLECTION, [&office_id], q, -1).await?;
+    if let Some(referral_code) = existing.into_iter().next() {
+        return Ok(warp::reply::json(&DataResponse {
+            data: Some(&referral_code),
+            extra: None::<Empty>,
+        }));
+    }
+
+    let id = Uuid::new_v4();
+    // MAGIC NUMBER: Eight characters is short enough to type and still unlikely to collide
+    let code = id.to_simple().to_string()[..8].to_uppercase();
+    let referral_code = ReferralCode {
+        id: id.to_string(),
+        office_id: office_id.clone(),
+        campaign_id: campaign.id.clone(),
+        user_id: claims.sub.clone(),
+        code,
+        modified: Utc::now(),
+    };
+    insert(REFERRAL_CODE_COLLECTION, [&office_id], &referral_code, None).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&referral_code),
+        extra: None::<Empty>,
+    }))
+}
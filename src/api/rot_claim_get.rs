@@ -0,0 +1,105 @@
+use crate::fault::Fault;
+use crate::models::{
+    Bid, ChangeOrder, Claims, Craftsman, DeductionType, Payment, RoleFlags, Task, User,
+};
+use crate::rot_rut::{rot_request_xml, validate_request, RotCase};
+use crate::tax;
+use crate::util::has_role;
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, CRAFTSMAN_COLLECTION, PAYMENT_COLLECTION,
+    TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::{Datelike, Utc};
+use chrono_tz::{Europe::Stockholm, Tz};
+use cosmos_utils::{get, query};
+use warp::{
+    http::{header, Response},
+    reject,
+};
+
+// Generates the ROT request for payment for all tasks a craftsman got paid for during a year
+pub async fn rot_claim_get(
+    office_id: String,
+    craftsman_id: String,
+    year: i32,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // NOTE: Craftsman id is the same as the user id
+    if claims.sub != craftsman_id
+        && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN)
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be the craftsman or an office billing admin to get the ROT request",
+        ))));
+    }
+
+    let (craftsman, _): (Craftsman, _) =
+        get(CRAFTSMAN_COLLECTION, [&office_id], &craftsman_id).await?;
+    if !craftsman.f_tax {
+        return Err(reject::custom(Fault::Ineligible(format!(
+            "Only craftsmen approved for F-tax can request ROT payments"
+        ))));
+    }
+
+    // NOTE: The deduction can only be requested once the money has been released to the craftsman
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.craftsmanId = "{}" AND (p.paymentState = "finalized" OR p.paymentState = "paidToCraftsman") AND p.paymentDate >= "{}" AND p.paymentDate < "{}""#,
+        PAYMENT_COLLECTION,
+        craftsman_id,
+        year - 1,
+        year + 2
+    );
+    let payments: Vec<Payment> = query(PAYMENT_COLLECTION, [&office_id], q, -1).await?;
+
+    let tz: Tz = Stockholm;
+    let mut cases = Vec::new();
+    let mut errors = Vec::new();
+    for payment in payments.iter().filter(|p| !p.deleted) {
+        let date = match payment.payment_date {
+            Some(d) => d.with_timezone(&tz),
+            None => continue,
+        };
+        if date.year() != year {
+            continue;
+        }
+        let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &payment.task_id).await?;
+        let rules = tax::rules_at(date.naive_local().date());
+        if !task.finished || rules.task_deduction(&task) != Some(DeductionType::Rot) {
+            continue;
+        }
+        let (bid, _): (Bid, _) = get(BID_COLLECTION, [&office_id], &payment.bid_id).await?;
+        let (customer, _): (User, _) = get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+        let change_order: Option<ChangeOrder> = match &payment.change_order_id {
+            Some(id) => Some(get(CHANGE_ORDER_COLLECTION, [&office_id], id).await?.0),
+            None => None,
+        };
+        let case = RotCase::new(&task, &bid, change_order.as_ref(), payment, &customer);
+        errors.append(&mut case.validate());
+        cases.push(case);
+    }
+    errors.append(&mut validate_request(
+        &cases,
+        year,
+        Utc::now().with_timezone(&tz).naive_local().date(),
+    ));
+    if !errors.is_empty() {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "The ROT request is not valid: {}",
+            errors.join("; ")
+        ))));
+    }
+
+    let xml = rot_request_xml(&format!("Toolit {}", year), &cases);
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                r#"attachment; filename="rot-{}-{}.xml""#,
+                year, craftsman_id
+            ),
+        )
+        .body(xml))
+}
@@ -0,0 +1,38 @@
+use crate::fault::Fault;
+use crate::models::{Claims, SavedSearch};
+use crate::util::{DataResponse, Empty};
+use crate::SAVED_SEARCH_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use warp::reject;
+
+// Deletes a saved search, which also drops the tasks waiting for its digest
+pub async fn saved_search_delete(
+    office_id: String,
+    search_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let search = modify(
+        SAVED_SEARCH_COLLECTION,
+        [&office_id],
+        &search_id,
+        |mut search: SavedSearch| {
+            if search.craftsman_id != claims.sub {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "Only the craftsman that saved the search can delete it"
+                ))));
+            }
+            search.deleted = true;
+            search.pending_task_ids.clear();
+            search.modified = Utc::now();
+            Ok(search)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&search),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,63 @@
+use crate::fault::Fault;
+use crate::models::{Claims, Craftsman, RoleFlags, SavedSearch};
+use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::{CRAFTSMAN_COLLECTION, SAVED_SEARCH_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, insert};
+use uuid::Uuid;
+use warp::reject;
+
+// Saves search criteria for the calling craftsman to be alerted about new tasks
+pub async fn saved_search_post(
+    office_id: String,
+    r: DataRequest<SavedSearch, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut search;
+    if let Some(q) = r.data {
+        search = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if search.office_id != office_id {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "office_id does not match url ({} != {}).",
+            search.office_id, office_id
+        ))));
+    }
+
+    if !has_role(None, &claims, RoleFlags::CRAFTSMAN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Need to be a craftsman to save searches."
+        ))));
+    }
+
+    if let Err(e) = search.verify() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The search is not correct: {}",
+            e
+        ))));
+    }
+
+    // NOTE: This works because we make sure the craftsman id is the userid
+    let (_craftsman, _): (Craftsman, _) =
+        get(CRAFTSMAN_COLLECTION, [&office_id], &claims.sub).await?;
+
+    search.id = Uuid::new_v4().to_string();
+    search.deleted = false;
+    search.craftsman_id = claims.sub.clone();
+    search.alerts_sent_on = None;
+    search.alerts_sent = 0;
+    search.pending_task_ids = Vec::new();
+    search.last_digest = None;
+    search.modified = Utc::now();
+
+    insert(SAVED_SEARCH_COLLECTION, [&office_id], &search, None).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&search),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,65 @@
+use crate::fault::Fault;
+use crate::models::{Claims, SavedSearch};
+use crate::util::{DataRequest, DataResponse, Empty};
+use crate::SAVED_SEARCH_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use warp::reject;
+
+// Changes the criteria or the alert frequency of a saved search
+pub async fn saved_search_put(
+    office_id: String,
+    search_id: String,
+    r: DataRequest<SavedSearch, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let new_search;
+    if let Some(q) = r.data {
+        new_search = q;
+    } else {
+        return Err(reject::custom(Fault::NoData));
+    }
+
+    if let Err(e) = new_search.verify() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The search is not correct: {}",
+            e
+        ))));
+    }
+
+    let search = modify(
+        SAVED_SEARCH_COLLECTION,
+        [&office_id],
+        &search_id,
+        |mut search: SavedSearch| {
+            if search.craftsman_id != claims.sub {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "Only the craftsman that saved the search can change it"
+                ))));
+            }
+            if search.deleted {
+                return Err(reject::custom(Fault::NotFound(format!(
+                    "The search {} has been deleted",
+                    search.id
+                ))));
+            }
+            search.name = new_search.name.clone();
+            search.crafts = new_search.crafts.clone();
+            search.area = new_search.area.clone();
+            search.city = new_search.city.clone();
+            search.postcode = new_search.postcode.clone();
+            search.min_price = new_search.min_price;
+            search.max_price = new_search.max_price;
+            search.frequency = new_search.frequency;
+            search.modified = Utc::now();
+            Ok(search)
+        },
+    )
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&search),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,22 @@
+use crate::models::{Claims, SavedSearch};
+use crate::util::{DataResponse, Empty};
+use crate::SAVED_SEARCH_COLLECTION;
+use cosmos_utils::query;
+
+// Returns the searches the calling craftsman has saved in the office
+pub async fn saved_searches_get(
+    office_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} s WHERE s.craftsmanId = "{}" AND (NOT IS_DEFINED(s.deleted) OR s.deleted = false)"#,
+        SAVED_SEARCH_COLLECTION, claims.sub
+    );
+    let searches: Vec<SavedSearch> = query(SAVED_SEARCH_COLLECTION, [&office_id], q, -1).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&searches),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,107 @@
+use crate::fault::Fault;
+use crate::ledger;
+use crate::models::{Bid, Claims, LedgerEntry, LedgerTransition, Office, RoleFlags};
+use crate::util::has_role;
+use crate::{BID_COLLECTION, LEDGER_COLLECTION, OFFICE_COLLECTION};
+use chrono::{NaiveDate, TimeZone, Utc};
+use cosmos_utils::{get, query};
+use std::collections::HashMap;
+use warp::{
+    http::{header, Response},
+    reject,
+};
+
+// Exports the ledger of an office between two dates, formatted as YYYY-MM-DD, as a SIE4 file
+pub async fn sie_export(
+    office_id: String,
+    from: String,
+    to: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_BILLING_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Needs to be an office billing admin to export the ledger",
+        ))));
+    }
+
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            reject::custom(Fault::IllegalArgument(format!(
+                "Could not parse {} as a date, expected YYYY-MM-DD",
+                date
+            )))
+        })
+    };
+    let from_date = parse(&from)?;
+    let to_date = parse(&to)?;
+    if from_date > to_date {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The start of the range {} is after the end {}",
+            from, to
+        ))));
+    }
+
+    // NOTE: The verifications are numbered in the order they were booked, an entry still in the
+    // queue would change the numbers once it is booked
+    let until = Utc.from_utc_date(&to_date.succ().succ()).and_hms(0, 0, 0);
+    if ledger::has_queued(&office_id, until).await? {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "Some ledger entries up to {} are still waiting to be booked, try again later",
+            to
+        ))));
+    }
+
+    // NOTE: Entries are booked in UTC while the export is in Stockholm time, so we fetch a day
+    // extra and let the export do the exact filtering.
+    let q = format!(
+        r#"SELECT * FROM {} l WHERE l.booked < "{}""#,
+        LEDGER_COLLECTION,
+        to_date.succ().succ().format("%Y-%m-%d")
+    );
+    let (o, e) = tokio::join!(
+        get(OFFICE_COLLECTION, [&office_id], &office_id),
+        query(LEDGER_COLLECTION, [&office_id], q, -1)
+    );
+    let (office, _): (Office, _) = o?;
+    let entries: Vec<LedgerEntry> = e?;
+
+    // NOTE: A SIE file describes a single fiscal year
+    let (year_start, _) = office.chart_of_accounts.fiscal_year(to_date);
+    if from_date < year_start {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The range {} to {} spans more than one fiscal year, which starts on {}",
+            from, to, year_start
+        ))));
+    }
+
+    let mut bid_ids: Vec<String> = entries
+        .iter()
+        .filter(|e| e.transition == LedgerTransition::Finalized)
+        .map(|e| format!(r#""{}""#, e.bid_id))
+        .collect();
+    bid_ids.sort();
+    bid_ids.dedup();
+    let mut bids = HashMap::new();
+    if !bid_ids.is_empty() {
+        let q = format!(
+            r#"SELECT * FROM {} b WHERE ARRAY_CONTAINS([{}], b.id)"#,
+            BID_COLLECTION,
+            bid_ids.join(",")
+        );
+        let found: Vec<Bid> = query(BID_COLLECTION, [&office_id], q, -1).await?;
+        for bid in found {
+            bids.insert(bid.id.clone(), bid);
+        }
+    }
+
+    let file = ledger::sie4(&office, from_date, to_date, &entries, &bids);
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain; charset=IBM437")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(r#"attachment; filename="toolit-{}-{}.se""#, from, to),
+        )
+        .body(file))
+}
@@ -0,0 +1,42 @@
+use crate::cancel;
+use crate::fault::Fault;
+use crate::models::{Cancellation, Claims, Task};
+use crate::util::{DataRequest, DataResponse, Empty};
+use serde::{Deserialize, Serialize};
+use warp::reject;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelTask {
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    task: Task,
+    cancellation: Cancellation,
+}
+
+// Cancels a task for good, together with the bid the customer has accepted
+pub async fn task_cancel(
+    office_id: String,
+    task_id: String,
+    r: DataRequest<CancelTask, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let cancel_task = r.data.unwrap_or_default();
+    let reason = cancel_task.reason.trim();
+    if reason.is_empty() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "A reason is needed to cancel a task."
+        ))));
+    }
+
+    let (task, cancellation) = cancel::cancel_task(&office_id, &task_id, &claims, reason).await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response { task, cancellation }),
+        extra: None::<Empty>,
+    }))
+}
@@ -1,17 +1,39 @@
+use crate::cancel;
 use crate::fault::Fault;
-use crate::models::{Claims, RoleFlags, Task};
+use crate::models::{Claims, RoleFlags, Task, TaskState};
 use crate::util::{has_role, DataResponse, Empty};
 use crate::TASK_COLLECTION;
 use chrono::Utc;
-use cosmos_utils::modify;
+use cosmos_utils::{get, modify};
 use warp::reject;
 
+// The reason given to the bidders when a task is deleted before it was completed
+const DELETED_REASON: &str = "Jobbet har tagits bort.";
+
 pub async fn task_delete(
     office_id: String,
     task_id: String,
     claims: Claims,
     _v: u8,
 ) -> Result<impl warp::Reply, warp::Rejection> {
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [&office_id], &task_id).await?;
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN)
+        && claims.sub != task.user_id
+    {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "User does not have sufficient roles."
+        ))));
+    }
+
+    // NOTE: A completed task is only hidden, anything else is cancelled first so that its payments
+    // are unwound, its chats closed and its bidders told
+    match task.state() {
+        TaskState::Completed | TaskState::Cancelled => {}
+        _ => {
+            cancel::cancel_task(&office_id, &task_id, &claims, DELETED_REASON).await?;
+        }
+    }
+
     let deleted_task = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
         if task.office_id != office_id {
             return Err(reject::custom(Fault::IllegalArgument(format!(
@@ -27,13 +49,6 @@ pub async fn task_delete(
             ))));
         }
 
-        if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN)
-            && claims.sub != task.user_id
-        {
-            return Err(reject::custom(Fault::Forbidden(format!(
-                "User does not have sufficient roles."
-            ))));
-        }
         task.deleted = true;
         task.modified = Utc::now();
         Ok(task)
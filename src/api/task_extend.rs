@@ -0,0 +1,61 @@
+use crate::fault::Fault;
+use crate::models::{Claims, RoleFlags, Task, TaskActor, TaskState};
+use crate::util::{has_role, DataResponse, Empty};
+use crate::TASK_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::modify;
+use warp::reject;
+
+// Keeps a task open for another expiry period, or publishes an expired task again
+pub async fn task_extend(
+    office_id: String,
+    task_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let task = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
+        let actor = if claims.sub == task.user_id {
+            TaskActor::Customer
+        } else if has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN) {
+            TaskActor::Admin
+        } else {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only the owner of the task can extend it."
+            ))));
+        };
+
+        match task.state() {
+            TaskState::Published => {
+                task.published_date = Some(Utc::now());
+                task.expiry_reminder_sent = false;
+                task.modified = Utc::now();
+            }
+            TaskState::Expired => {
+                if let Err(e) = task.transition(
+                    TaskState::Published,
+                    actor,
+                    Some(&claims.sub),
+                    Some("Extended"),
+                ) {
+                    return Err(reject::custom(Fault::Forbidden(format!(
+                        "The task can not be extended: {}",
+                        e
+                    ))));
+                }
+            }
+            state => {
+                return Err(reject::custom(Fault::IllegalState(format!(
+                    "Only published or expired tasks can be extended, the task is {:?}.",
+                    state
+                ))));
+            }
+        }
+        Ok(task)
+    })
+    .await?;
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&task),
+        extra: None::<Empty>,
+    }))
+}
@@ -1,6 +1,9 @@
+use crate::alerts;
 use crate::fault::Fault;
-use crate::models::{Claims, Task, User};
-use crate::util::{DataRequest, DataResponse, Empty};
+use crate::geo;
+use crate::models::{Claims, PublishStatus, Task, TaskActor, TaskState, User};
+use crate::rot_rut::is_valid_org_number;
+use crate::util::{log, DataRequest, DataResponse, Empty};
 use crate::{TASK_COLLECTION, USER_COLLECTION};
 use cosmos_utils::{insert, modify};
 use uuid::Uuid;
@@ -33,11 +36,73 @@ pub async fn task_post(
         ))));
     }
 
+    // NOTE: Tasks without a location never reach the craftsmen around them, so a postcode that can
+    // not be located is refused rather than stored
+    let location = match geo::postcode_location(&task.postcode) {
+        Some(location) => location,
+        None => {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The postcode {} could not be located.",
+                task.postcode
+            ))));
+        }
+    };
+
+    if task.customer_type.is_business() {
+        match &task.org_number {
+            Some(org_number) if is_valid_org_number(org_number) => {}
+            _ => {
+                return Err(reject::custom(Fault::IllegalArgument(format!(
+                    "A business customer needs a valid organization number."
+                ))));
+            }
+        }
+        // NOTE: Businesses can not get ROT or RUT deductions
+        if task.use_rot_rut {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "A business customer can not use ROT or RUT."
+            ))));
+        }
+    } else {
+        task.org_number = None;
+    }
+
+    // NOTE: The dates and flags kept by the server are cleared before the task is published, which
+    // sets the date it was published
+    task.payment_id = None;
+    task.date_done = None;
+    task.published_date = None;
+    task.expiry_reminder_sent = false;
+
+    // NOTE: Every task starts out as a draft and is published right away if the customer wants it
+    // to be
+    let publish = matches!(task.publish_status, PublishStatus::Published);
+    task.publish_status = PublishStatus::Unpublished;
+    task.state = Some(TaskState::Draft);
+    task.state_history = Vec::new();
+    if publish {
+        if let Err(e) = task.transition(
+            TaskState::Published,
+            TaskActor::Customer,
+            Some(&claims.sub),
+            None,
+        ) {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "The task can not be published: {}",
+                e
+            ))));
+        }
+    }
+
     task.id = Uuid::new_v4().to_string();
     task.accepted_bid = None;
     task.finished = false;
     task.craftsman_indicated_finished = false;
+    task.craftsman_finished_date = None;
+    task.finish_reminder_sent = false;
     task.rated = false;
+    task.search_alerts_sent = false;
+    task.location = Some(location);
     task.modified = chrono::Utc::now();
 
     insert(TASK_COLLECTION, [&office_id], &task, None).await?;
@@ -62,6 +127,18 @@ pub async fn task_post(
     )
     .await?;
 
+    // Let the craftsmen that are looking for a task like this know about it, in a new thread since
+    // we only log errors
+    let task_id = task.id.clone();
+    tokio::task::spawn(async move {
+        if let Err(e) = alerts::task_published(&office_id, &task_id).await {
+            log(format!(
+                "Could not send alerts for task {} due to {:?}",
+                task_id, e
+            ));
+        }
+    });
+
     Ok(warp::reply::json(&DataResponse {
         data: Some(&task),
         extra: None::<Empty>,
@@ -1,4 +1,5 @@
 use crate::fault::Fault;
+use crate::geo;
 use crate::models::{Claims, Task};
 use crate::util::{DataRequest, DataResponse, Empty};
 use crate::TASK_COLLECTION;
@@ -40,11 +41,22 @@ pub async fn task_put(
         ))));
     }
 
+    let location = match geo::postcode_location(&new_task.postcode) {
+        Some(location) => location,
+        None => {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The postcode {} could not be located.",
+                new_task.postcode
+            ))));
+        }
+    };
+
     let task = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
         task.crafts = new_task.crafts.clone();
         task.address = new_task.address.clone();
         task.city = new_task.city.clone();
         task.postcode = new_task.postcode.clone();
+        task.location = Some(location.clone());
         task.date_done = new_task.date_done.clone();
         task.description = new_task.description.clone();
         task.title = new_task.title.clone();
@@ -0,0 +1,237 @@
+use crate::fault::Fault;
+use crate::models::{Claims, CraftType, Craftsman, Location, PublishStatus, RoleFlags, Task};
+use crate::util::{has_role, is_none, DataRequest, DataResponse, Empty};
+use crate::{CRAFTSMAN_COLLECTION, TASK_COLLECTION};
+use cosmos_utils::{get, query};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::{min, Ordering};
+use warp::reject;
+
+// MAGIC NUMBER: Enough tasks to fill a screen or two on the phone
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub enum TaskSort {
+    Newest,
+    PriceAscending,
+    PriceDescending,
+    // Closest to the point given in the search first, tasks without a location come last
+    Distance,
+}
+
+impl Default for TaskSort {
+    fn default() -> Self {
+        TaskSort::Newest
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSearch {
+    // Only tasks with any of these crafts, all crafts the craftsman is approved for if empty
+    #[serde(default)]
+    crafts: Vec<CraftType>,
+    #[serde(default)]
+    city: Option<String>,
+    // Matches the start of the postcode so a whole area can be searched
+    #[serde(default)]
+    postcode: Option<String>,
+    #[serde(default)]
+    min_price: Option<Decimal>,
+    #[serde(default)]
+    max_price: Option<Decimal>,
+    #[serde(default)]
+    use_rot_rut: Option<bool>,
+    // Published tasks unless anything else is asked for
+    #[serde(default)]
+    publish_status: Option<PublishStatus>,
+    #[serde(default)]
+    without_accepted_bid: bool,
+    // Only tasks in the service area of the craftsman
+    #[serde(default)]
+    within_service_area: bool,
+    #[serde(default)]
+    sort: TaskSort,
+    // Where the craftsman is, needed to sort by distance
+    #[serde(default)]
+    near: Option<Location>,
+    #[serde(default)]
+    page: usize,
+    #[serde(default)]
+    page_size: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskHit {
+    #[serde(flatten)]
+    task: Task,
+    // Kilometers from the point given in the search
+    #[serde(skip_serializing_if = "is_none")]
+    distance: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    tasks: Vec<TaskHit>,
+    total: usize,
+    page: usize,
+    page_size: usize,
+}
+
+// Searches the tasks of an office that the calling craftsman can bid on
+pub async fn task_search(
+    office_id: String,
+    r: DataRequest<TaskSearch, Empty>,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let search = r.data.unwrap_or_default();
+
+    if !has_role(None, &claims, RoleFlags::CRAFTSMAN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Need to be a craftsman to search for tasks."
+        ))));
+    }
+    if let Some(near) = &search.near {
+        if !near.is_valid() {
+            return Err(reject::custom(Fault::IllegalArgument(format!(
+                "The point to search near is not a valid point."
+            ))));
+        }
+    }
+    if search.sort == TaskSort::Distance && search.near.is_none() {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "Need a point to search near to sort by distance."
+        ))));
+    }
+    let page_size = min(search.page_size.unwrap_or(DEFAULT_PAGE_SIZE), MAX_PAGE_SIZE);
+    if page_size == 0 {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "The page size has to be at least 1."
+        ))));
+    }
+
+    // NOTE: This works because we make sure the craftsman id is the userid
+    let (craftsman, _): (Craftsman, _) =
+        get(CRAFTSMAN_COLLECTION, [&office_id], &claims.sub).await?;
+
+    // NOTE: Only tasks the craftsman is allowed to bid on, see bid_post
+    let mut crafts = craftsman.approved_crafts();
+    if !search.crafts.is_empty() {
+        crafts.retain(|c| search.crafts.contains(c));
+    }
+    if crafts.is_empty() {
+        return Ok(warp::reply::json(&DataResponse {
+            data: Some(&Response {
+                tasks: Vec::new(),
+                total: 0,
+                page: search.page,
+                page_size,
+            }),
+            extra: None::<Empty>,
+        }));
+    }
+
+    // NOTE: The filters on crafts, flags and the publish status are done by Cosmos. The free text
+    // and the prices are matched here, decimals are stored as strings so Cosmos can not compare
+    // them.
+    let crafts = crafts
+        .iter()
+        .map(|c| serde_json::to_string(c).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    let publish_status = serde_json::to_string(
+        &search
+            .publish_status
+            .clone()
+            .unwrap_or(PublishStatus::Published),
+    )
+    .unwrap_or_default();
+    let mut q = format!(
+        r#"SELECT * FROM {} t WHERE (NOT IS_DEFINED(t.deleted) OR t.deleted = false) AND (NOT IS_DEFINED(t.finished) OR t.finished = false) AND t.publishStatus = {} AND (NOT IS_DEFINED(t.state) OR IS_NULL(t.state) OR t.state != "Expired") AND EXISTS(SELECT VALUE c FROM c IN t.crafts WHERE ARRAY_CONTAINS([{}], c))"#,
+        TASK_COLLECTION, publish_status, crafts
+    );
+    if let Some(use_rot_rut) = search.use_rot_rut {
+        q.push_str(&format!(" AND t.useRotRut = {}", use_rot_rut));
+    }
+    if search.without_accepted_bid {
+        q.push_str(" AND (NOT IS_DEFINED(t.acceptedBid) OR IS_NULL(t.acceptedBid))");
+    }
+    if search.within_service_area {
+        match &craftsman.service_area {
+            Some(service_area) => {
+                q.push_str(&format!(" AND {}", service_area.sql_contains("t.location")))
+            }
+            None => {
+                return Err(reject::custom(Fault::IllegalArgument(format!(
+                    "The craftsman has not set a service area to search in."
+                ))));
+            }
+        }
+    }
+    let tasks: Vec<Task> = query(TASK_COLLECTION, [&office_id], q, -1).await?;
+
+    let city = search.city.as_ref().map(|c| c.trim().to_lowercase());
+    let postcode = search.postcode.as_ref().map(|p| p.replace(' ', ""));
+    let mut hits: Vec<TaskHit> = tasks
+        .into_iter()
+        .filter(|t| craftsman.can_bid_on(t))
+        .filter(|t| match &city {
+            Some(city) => t.city.trim().to_lowercase() == *city,
+            None => true,
+        })
+        .filter(|t| match &postcode {
+            Some(postcode) => t.postcode.replace(' ', "").starts_with(postcode.as_str()),
+            None => true,
+        })
+        .filter(|t| search.min_price.is_none_or(|min| t.price >= min))
+        .filter(|t| search.max_price.is_none_or(|max| t.price <= max))
+        .map(|task| TaskHit {
+            distance: match (&search.near, &task.location) {
+                (Some(near), Some(location)) => Some(near.distance_km(location)),
+                _ => None,
+            },
+            task,
+        })
+        .collect();
+
+    match search.sort {
+        // NOTE: Editing a task does not make it new, tasks that were never published come last
+        TaskSort::Newest => hits.sort_by(|a, b| {
+            b.task
+                .published_date
+                .cmp(&a.task.published_date)
+                .then_with(|| b.task.modified.cmp(&a.task.modified))
+        }),
+        TaskSort::PriceAscending => hits.sort_by(|a, b| a.task.price.cmp(&b.task.price)),
+        TaskSort::PriceDescending => hits.sort_by(|a, b| b.task.price.cmp(&a.task.price)),
+        TaskSort::Distance => hits.sort_by(|a, b| match (a.distance, b.distance) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }),
+    }
+
+    let total = hits.len();
+    let tasks: Vec<TaskHit> = hits
+        .into_iter()
+        .skip(search.page.saturating_mul(page_size))
+        .take(page_size)
+        .collect();
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response {
+            tasks,
+            total,
+            page: search.page,
+            page_size,
+        }),
+        extra: None::<Empty>,
+    }))
+}
@@ -0,0 +1,64 @@
+use crate::fault::Fault;
+use crate::models::{Claims, RoleFlags, Task};
+use crate::util::{has_role, log, DataResponse, Empty};
+use crate::TASK_COLLECTION;
+use cosmos_utils::{maybe_modify, query, ModifyReturn};
+use serde::Serialize;
+use warp::reject;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationResult {
+    migrated: usize,
+    failed: usize,
+}
+
+// Gives every task that was created before the state machine a state derived from its old flags
+pub async fn task_state_migrate(
+    office_id: String,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN) {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "User does not have sufficient roles."
+        ))));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} t WHERE NOT IS_DEFINED(t.state) OR IS_NULL(t.state)"#,
+        TASK_COLLECTION
+    );
+    let tasks: Vec<Task> = query(TASK_COLLECTION, [&office_id], q, -1).await?;
+
+    let mut result = MigrationResult {
+        migrated: 0,
+        failed: 0,
+    };
+    for task in tasks {
+        // NOTE: The task may have been given a state since it was queried, then it is left alone
+        let r = maybe_modify(TASK_COLLECTION, [&office_id], &task.id, |mut task: Task| {
+            if task.state.is_some() {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            task.state = Some(task.legacy_state());
+            Ok(ModifyReturn::Replace(task))
+        })
+        .await;
+        match r {
+            Ok(_) => result.migrated += 1,
+            Err(e) => {
+                log(format!(
+                    "Could not migrate the state of task {} due to {:?}",
+                    task.id, e
+                ));
+                result.failed += 1;
+            }
+        }
+    }
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&result),
+        extra: None::<Empty>,
+    }))
+}
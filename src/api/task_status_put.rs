@@ -1,6 +1,7 @@
+use crate::alerts;
 use crate::fault::Fault;
-use crate::models::{Claims, PublishStatus, RoleFlags, Task};
-use crate::util::{has_role, DataRequest, DataResponse, Empty};
+use crate::models::{Claims, PublishStatus, RoleFlags, Task, TaskActor, TaskState};
+use crate::util::{has_role, log, DataRequest, DataResponse, Empty};
 use crate::TASK_COLLECTION;
 use chrono::Utc;
 use cosmos_utils::modify;
@@ -20,83 +21,70 @@ pub async fn task_status_put(
         }
     };
 
-    let task = modify(
-        TASK_COLLECTION,
-        [&office_id],
-        &task_id,
-        |mut task: Task| match publish_status {
+    let task = modify(TASK_COLLECTION, [&office_id], &task_id, |mut task: Task| {
+        let is_admin = has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN);
+        let actor = if is_admin {
+            TaskActor::Admin
+        } else if claims.sub == task.user_id {
+            TaskActor::Customer
+        } else {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "User does not have sufficient roles."
+            ))));
+        };
+
+        let r = match publish_status {
             PublishStatus::Published => {
-                match task.publish_status {
-                    PublishStatus::Published => {
-                        if claims.sub != task.user_id {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
-                    PublishStatus::Unpublished => {
-                        if claims.sub != task.user_id {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
-                    PublishStatus::Flagged => {
-                        if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN) {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
-                }
-                task.modified = Utc::now();
-                task.publish_status = publish_status.clone();
-                Ok(task)
+                task.transition(TaskState::Published, actor, Some(&claims.sub), None)
             }
             PublishStatus::Unpublished => {
-                match task.publish_status {
-                    PublishStatus::Published => {
-                        if claims.sub != task.user_id
-                            && !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN)
-                        {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
-                    PublishStatus::Unpublished => {
-                        if claims.sub != task.user_id {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
-                    PublishStatus::Flagged => {
-                        if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN) {
-                            return Err(reject::custom(Fault::Forbidden(format!(
-                                "User does not have sufficient roles."
-                            ))));
-                        }
-                    }
+                let r = task.transition(TaskState::Draft, actor, Some(&claims.sub), None);
+                // NOTE: Only a content admin can take back the flag of a task
+                if is_admin && r.is_ok() {
+                    task.publish_status = PublishStatus::Unpublished;
                 }
-                task.modified = Utc::now();
-                task.publish_status = publish_status.clone();
-                Ok(task)
+                r
             }
             PublishStatus::Flagged => {
-                if !has_role(Some(&office_id), &claims, RoleFlags::OFFICE_CONTENT_ADMIN) {
+                if !is_admin {
                     return Err(reject::custom(Fault::Forbidden(format!(
                         "User does not have sufficient roles."
                     ))));
                 }
+                // NOTE: A flagged task is taken down if it is published, work that has already
+                // started goes on
+                task.publish_status = PublishStatus::Flagged;
                 task.modified = Utc::now();
-                task.publish_status = publish_status.clone();
-                Ok(task)
+                if task.state() == TaskState::Published {
+                    task.transition(TaskState::Draft, actor, Some(&claims.sub), Some("Flagged"))
+                } else {
+                    Ok(true)
+                }
             }
-        },
-    )
+        };
+        if let Err(e) = r {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "The publish status can not be changed: {}",
+                e
+            ))));
+        }
+        Ok(task)
+    })
     .await?;
 
+    // NOTE: Craftsmen are alerted the first time the task is published
+    if let PublishStatus::Published = task.publish_status {
+        let task_id = task_id.clone();
+        tokio::task::spawn(async move {
+            if let Err(e) = alerts::task_published(&office_id, &task_id).await {
+                log(format!(
+                    "Could not send alerts for task {} due to {:?}",
+                    task_id, e
+                ));
+            }
+        });
+    }
+
     // TODO: Delete old image, if any.
     Ok(warp::reply::json(&DataResponse {
         data: Some(task),
@@ -0,0 +1,43 @@
+use crate::fault::Fault;
+use crate::models::{Claims, DeductionType, TaxDeductionUsage};
+use crate::tax;
+use crate::util::{DataResponse, Empty};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use warp::reject;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    usage: TaxDeductionUsage,
+    remaining_rot: Decimal,
+    remaining_rut: Decimal,
+}
+
+// Returns how much ROT and RUT deduction the user has used and has left during a year
+pub async fn user_deductions_get(
+    user_id: String,
+    year: i32,
+    claims: Claims,
+    _v: u8,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if claims.sub != user_id {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "Users can only see their own deductions"
+        ))));
+    }
+
+    let usage = tax::deduction_usage(&user_id, year).await?;
+    // NOTE: The ceilings are the ones in effect at the end of the year
+    let rules = tax::rules_at(NaiveDate::from_ymd(year, 12, 31));
+
+    Ok(warp::reply::json(&DataResponse {
+        data: Some(&Response {
+            remaining_rot: rules.remaining_allowance(&usage, DeductionType::Rot),
+            remaining_rut: rules.remaining_allowance(&usage, DeductionType::Rut),
+            usage,
+        }),
+        extra: None::<Empty>,
+    }))
+}
@@ -1,9 +1,13 @@
 use crate::fault::Fault;
-use crate::models::{Ad, Bid, Chat, Claims, Craftsman, Message, Office, Payment, Task, User};
+use crate::models::{
+    Ad, Bid, ChangeOrder, Chat, Claims, Craftsman, Dispute, Message, Office, Payment, Task,
+    TaskState, User,
+};
 use crate::util::{self, DataResponse, Empty};
 use crate::{
-    AD_COLLECTION, BID_COLLECTION, CHAT_COLLECTION, CRAFTSMAN_COLLECTION, MESSAGE_COLLECTION,
-    OFFICE_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION, USER_COLLECTION,
+    AD_COLLECTION, BID_COLLECTION, CHANGE_ORDER_COLLECTION, CHAT_COLLECTION, CRAFTSMAN_COLLECTION,
+    DISPUTE_COLLECTION, MESSAGE_COLLECTION, OFFICE_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION,
+    USER_COLLECTION,
 };
 use chrono::{DateTime, Utc};
 use cosmos_utils::{get, query, query_crosspartition};
@@ -34,6 +38,10 @@ pub struct UserPollDataResponse<'a> {
     #[serde(skip_serializing_if = "util::is_empty")]
     pub bids: Vec<Bid>,
     #[serde(skip_serializing_if = "util::is_empty")]
+    pub change_orders: Vec<ChangeOrder>,
+    #[serde(skip_serializing_if = "util::is_empty")]
+    pub disputes: Vec<Dispute>,
+    #[serde(skip_serializing_if = "util::is_empty")]
     pub ads: Vec<Ad>,
 }
 
@@ -184,7 +192,37 @@ pub async fn user_poll(
     });
     let payments_futs = join_all(payments_futs);
 
-    let (bids, payments) = tokio::join!(bids_futs, payments_futs,);
+    // NOTE: Get only the change orders where user was the task creator or user is craftsman
+    let change_orders_futs = user.office_ids.iter().map(|id| {
+        // Create futures for each cosmos call.
+        let q = format!(
+            r#"SELECT * FROM {} u WHERE u.craftsmanId IN {} OR u.taskId IN {}"#,
+            CHANGE_ORDER_COLLECTION, my_craftsmen_ids, my_task_ids
+        );
+        async move {
+            let change_orders: Vec<ChangeOrder> =
+                query(CHANGE_ORDER_COLLECTION, [&id], q, -1).await?;
+            Result::<Vec<ChangeOrder>, warp::Rejection>::Ok(change_orders)
+        }
+    });
+    let change_orders_futs = join_all(change_orders_futs);
+
+    // NOTE: Get only the disputes where user was the task creator or user is craftsman
+    let disputes_futs = user.office_ids.iter().map(|id| {
+        // Create futures for each cosmos call.
+        let q = format!(
+            r#"SELECT * FROM {} u WHERE u.craftsmanId IN {} OR u.taskId IN {}"#,
+            DISPUTE_COLLECTION, my_craftsmen_ids, my_task_ids
+        );
+        async move {
+            let disputes: Vec<Dispute> = query(DISPUTE_COLLECTION, [&id], q, -1).await?;
+            Result::<Vec<Dispute>, warp::Rejection>::Ok(disputes)
+        }
+    });
+    let disputes_futs = join_all(disputes_futs);
+
+    let (bids, payments, change_orders, disputes) =
+        tokio::join!(bids_futs, payments_futs, change_orders_futs, disputes_futs);
 
     let bids_iter = bids.into_iter().filter(|u| u.is_ok()).map(|u| u.unwrap());
     let mut bids = vec![];
@@ -201,6 +239,39 @@ pub async fn user_poll(
         payments.extend(b);
     }
 
+    let change_orders_iter = change_orders
+        .into_iter()
+        .filter(|u| u.is_ok())
+        .map(|u| u.unwrap());
+    let mut change_orders = vec![];
+    for b in change_orders_iter {
+        change_orders.extend(b);
+    }
+
+    // NOTE: The brokerage is between Toolit and the craftsman, the customer does not see it
+    for bid in bids.iter_mut().filter(|b| b.craftsman_id != user.id) {
+        bid.brokerage = None;
+    }
+    for payment in payments.iter_mut().filter(|p| p.craftsman_id != user.id) {
+        payment.brokerage = None;
+    }
+
+    // NOTE: Expired tasks are only kept for the customer and the craftsmen that bid on them
+    tasks.retain(|t| {
+        t.state() != TaskState::Expired
+            || t.user_id == user.id
+            || bids.iter().any(|b| b.task_id == t.id)
+    });
+
+    let disputes_iter = disputes
+        .into_iter()
+        .filter(|u| u.is_ok())
+        .map(|u| u.unwrap());
+    let mut disputes = vec![];
+    for b in disputes_iter {
+        disputes.extend(b);
+    }
+
     // NOTE: Only get chats from bids that we are part of
     let chats_futs: Vec<_> = bids
         .iter()
@@ -282,6 +353,14 @@ pub async fn user_poll(
             .filter(|u| u.modified >= since)
             .collect();
         bids = bids.into_iter().filter(|u| u.modified >= since).collect();
+        change_orders = change_orders
+            .into_iter()
+            .filter(|u| u.modified >= since)
+            .collect();
+        disputes = disputes
+            .into_iter()
+            .filter(|u| u.modified >= since)
+            .collect();
         ads = ads.into_iter().filter(|u| u.modified >= since).collect();
     }
     let user = new_user;
@@ -296,6 +375,8 @@ pub async fn user_poll(
             chats,
             payments,
             bids,
+            change_orders,
+            disputes,
             ads,
         }),
         extra: None::<Empty>,
@@ -43,6 +43,7 @@ pub async fn user_put(
         new_user.nid = user.nid;
         new_user.email = user.email;
         new_user.office_ids = user.office_ids;
+        new_user.referral_credit = user.referral_credit;
         new_user.modified = chrono::Utc::now();
         Ok(new_user)
     })
@@ -0,0 +1,215 @@
+use crate::alerts::notify_user;
+use crate::fault::Fault;
+use crate::models::{
+    Bid, Cancellation, ChangeOrder, ChangeOrderStatus, Chat, Claims, Payment, PaymentState,
+    RoleFlags, Task, TaskActor, TaskState,
+};
+use crate::payment;
+use crate::util::{has_role, log};
+use crate::{BID_COLLECTION, CHANGE_ORDER_COLLECTION, CHAT_COLLECTION, PAYMENT_COLLECTION};
+use cosmos_utils::{get, maybe_modify, query, ModifyReturn};
+use warp::reject;
+
+// Who the caller is when cancelling the task, or the given bid of it, if anyone is allowed to
+pub fn initiator_of(claims: &Claims, task: &Task, bid: Option<&Bid>) -> Option<TaskActor> {
+    if claims.sub == task.user_id {
+        Some(TaskActor::Customer)
+    // NOTE: Craftsman id is the same as the user id
+    } else if bid.is_some_and(|b| b.craftsman_id == claims.sub) {
+        Some(TaskActor::Craftsman)
+    } else if has_role(
+        Some(&task.office_id),
+        claims,
+        RoleFlags::OFFICE_CONTENT_ADMIN | RoleFlags::OFFICE_BILLING_ADMIN,
+    ) {
+        Some(TaskActor::Admin)
+    } else {
+        None
+    }
+}
+
+// The bid the customer has accepted along with its payments. While the customer is paying the bid
+// is only known from the payment, the task points to it once the money is in escrow.
+pub async fn accepted_bid(
+    office_id: &str,
+    task: &Task,
+) -> Result<Option<(Bid, Vec<Payment>)>, warp::Rejection> {
+    let bid_id = match &task.accepted_bid {
+        Some(bid_id) => bid_id.clone(),
+        None if task.state() == TaskState::BidAccepted => {
+            let q = format!(
+                r#"SELECT * FROM {} p WHERE p.taskId = "{}""#,
+                PAYMENT_COLLECTION, task.id
+            );
+            let payments: Vec<Payment> = query(PAYMENT_COLLECTION, [office_id], q, -1).await?;
+            match payments
+                .into_iter()
+                .find(|p| !p.deleted && matches!(p.payment_state, PaymentState::Initialized))
+            {
+                Some(payment) => payment.bid_id,
+                None => return Ok(None),
+            }
+        }
+        None => return Ok(None),
+    };
+    let (bid, _): (Bid, _) = get(BID_COLLECTION, [office_id], &bid_id).await?;
+    let payments = payment::active_payments(office_id, &bid_id).await?;
+    Ok(Some((bid, payments)))
+}
+
+// The change orders of the bid that are still proposed or accepted. They go with the bid when it
+// is cancelled, or they would hold up the next bid of the task from being finished.
+pub async fn open_change_orders(
+    office_id: &str,
+    bid_id: &str,
+) -> Result<Vec<ChangeOrder>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE c.bidId = "{}" AND (NOT IS_DEFINED(c.deleted) OR c.deleted = false)"#,
+        CHANGE_ORDER_COLLECTION, bid_id
+    );
+    let change_orders: Vec<ChangeOrder> =
+        query(CHANGE_ORDER_COLLECTION, [office_id], q, -1).await?;
+    Ok(change_orders
+        .into_iter()
+        .filter(|c| c.status != ChangeOrderStatus::Rejected)
+        .collect())
+}
+
+// Money that has been released to the craftsman can not be taken back by a cancellation, that is
+// what disputes are for
+pub fn verify_refundable(payments: &[Payment]) -> Result<(), warp::Rejection> {
+    for payment in payments {
+        match payment.payment_state {
+            PaymentState::Initialized
+            | PaymentState::PaidToEscrow
+            | PaymentState::Refunded
+            | PaymentState::Failed => {}
+            ref s => {
+                return Err(reject::custom(Fault::IllegalState(format!(
+                    "Payment {} is {:?} and can not be undone by a cancellation, open a dispute instead",
+                    payment.id, s
+                ))));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The customer can not take back money in escrow on their own, the craftsman may already have done
+// work for it. That is for a dispute, or for the craftsman or an admin to cancel.
+pub fn verify_unwindable_by(
+    initiator: TaskActor,
+    payments: &[Payment],
+) -> Result<(), warp::Rejection> {
+    if initiator != TaskActor::Customer {
+        return Ok(());
+    }
+    match payments
+        .iter()
+        .find(|p| matches!(p.payment_state, PaymentState::PaidToEscrow))
+    {
+        Some(payment) => Err(reject::custom(Fault::Forbidden(format!(
+            "Payment {} is in escrow and can only be refunded by the craftsman or an admin, open a dispute instead",
+            payment.id
+        )))),
+        None => Ok(()),
+    }
+}
+
+// Withdraws the payments that have not been paid and refunds the ones in escrow. Failures are
+// recorded on the cancellation rather than failing it since the task or bid is already cancelled.
+pub async fn unwind_payments(payments: &[Payment], cancellation: &mut Cancellation) {
+    for payment in payments {
+        match payment.payment_state {
+            PaymentState::Initialized => match payment::cancel(payment).await {
+                Ok(_) => cancellation.cancelled_payments.push(payment.id.clone()),
+                Err(e) => {
+                    log(format!(
+                        "Could not cancel payment {} of a cancellation due to {:?}",
+                        payment.id, e
+                    ));
+                    cancellation.failed_payments.push(payment.id.clone());
+                }
+            },
+            PaymentState::PaidToEscrow => match payment::refund(payment, payment.amount).await {
+                Ok(_) => cancellation.refunded_payments.push(payment.id.clone()),
+                Err(e) => {
+                    log(format!(
+                        "Could not refund payment {} of a cancellation due to {:?}",
+                        payment.id, e
+                    ));
+                    cancellation.failed_payments.push(payment.id.clone());
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+// Closes the chats of the task, or only the one about the given bid
+pub async fn close_chats(
+    office_id: &str,
+    task_id: &str,
+    bid_id: Option<&str>,
+    cancellation: &mut Cancellation,
+) {
+    let mut q = format!(
+        r#"SELECT * FROM {} c WHERE c.taskId = "{}" AND (NOT IS_DEFINED(c.closed) OR c.closed = false)"#,
+        CHAT_COLLECTION, task_id
+    );
+    if let Some(bid_id) = bid_id {
+        q.push_str(&format!(r#" AND c.bidId = "{}""#, bid_id));
+    }
+    let chats: Result<Vec<Chat>, _> = query(CHAT_COLLECTION, [office_id], q, -1).await;
+    let chats = match chats {
+        Ok(chats) => chats,
+        Err(e) => {
+            log(format!(
+                "Could not get the chats of cancelled task {} due to {}",
+                task_id, e
+            ));
+            return;
+        }
+    };
+    for chat in chats {
+        let r = maybe_modify(CHAT_COLLECTION, [office_id], &chat.id, |mut chat: Chat| {
+            if chat.closed {
+                return Ok(ModifyReturn::DontReplace(chat));
+            }
+            chat.closed = true;
+            chat.modified = chrono::Utc::now();
+            Ok(ModifyReturn::Replace(chat))
+        })
+        .await;
+        match r {
+            Ok(_) => cancellation.closed_chats.push(chat.id),
+            Err(e) => log(format!("Could not close chat {} due to {}", chat.id, e)),
+        }
+    }
+}
+
+// The craftsmen with a bid on the task that has not been withdrawn
+pub async fn bidders(office_id: &str, task_id: &str) -> Result<Vec<String>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} b WHERE b.taskId = "{}" AND (NOT IS_DEFINED(b.deleted) OR b.deleted = false) AND (NOT IS_DEFINED(b.withdrawal) OR IS_NULL(b.withdrawal))"#,
+        BID_COLLECTION, task_id
+    );
+    let bids: Vec<Bid> = query(BID_COLLECTION, [office_id], q, -1).await?;
+    let mut craftsmen: Vec<String> = bids.into_iter().map(|b| b.craftsman_id).collect();
+    craftsmen.sort();
+    craftsmen.dedup();
+    Ok(craftsmen)
+}
+
+// Sends the PNs of a cancellation, except to whoever cancelled. This should not cause a failure
+// so it runs in a separate thread.
+pub fn notify(recipients: Vec<(String, String)>, initiator_id: &str) {
+    let initiator_id = initiator_id.to_string();
+    tokio::task::spawn(async move {
+        for (user_id, text) in recipients.iter() {
+            if *user_id != initiator_id {
+                notify_user(user_id, text).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,256 @@
+use super::cascade::{
+    accepted_bid, bidders, close_chats, initiator_of, notify, open_change_orders, unwind_payments,
+    verify_refundable, verify_unwindable_by,
+};
+use crate::dispute;
+use crate::fault::Fault;
+use crate::models::{
+    Bid, Cancellation, ChangeOrder, ChangeOrderStatus, Claims, Task, TaskActor, TaskState,
+};
+use crate::{BID_COLLECTION, CANCELLATION_COLLECTION, CHANGE_ORDER_COLLECTION, TASK_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{get, insert, CosmosSaga};
+use uuid::Uuid;
+use warp::reject;
+
+// Cancels the bid the customer has accepted and opens the task for bids again. The payments of the
+// bid are withdrawn or refunded and the chat about the bid is closed.
+pub async fn cancel_accepted_bid(
+    office_id: &str,
+    task_id: &str,
+    bid_id: &str,
+    claims: &Claims,
+    reason: &str,
+) -> Result<(Task, Bid, Cancellation), warp::Rejection> {
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [office_id], task_id).await?;
+    let (bid, payments) = match accepted_bid(office_id, &task).await? {
+        Some((bid, payments)) if bid.id == bid_id => (bid, payments),
+        _ => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Task has not accepted a bid from {}",
+                bid_id
+            ))));
+        }
+    };
+    let initiator = match initiator_of(claims, &task, Some(&bid)) {
+        Some(initiator) => initiator,
+        None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only the customer, the craftsman or an office admin may cancel an accepted bid"
+            ))));
+        }
+    };
+    // NOTE: The money of a task with an open dispute is moved by resolving the dispute
+    dispute::verify_no_open_dispute(office_id, task_id).await?;
+    verify_refundable(&payments)?;
+    verify_unwindable_by(initiator, &payments)?;
+    let change_orders = open_change_orders(office_id, bid_id).await?;
+
+    // NOTE: The task and the bid are changed together, a task that is open again while its bid is
+    // not cancelled could not be cancelled again and the payments would be stuck in escrow. The
+    // change orders of the bid are rejected with it, their payments are unwound with the bid's.
+    let mut saga = CosmosSaga::new();
+    let task = saga
+        .modify(
+            TASK_COLLECTION,
+            [office_id],
+            task_id,
+            |mut task: Task| async {
+                task.accepted_bid = None;
+                if let Err(e) = task.transition(
+                    TaskState::Published,
+                    initiator,
+                    Some(&claims.sub),
+                    Some(reason),
+                ) {
+                    return Err(reject::custom(Fault::Forbidden(format!(
+                        "The bid can not be cancelled: {}",
+                        e
+                    ))));
+                }
+                Ok(task)
+            },
+        )
+        .await?;
+    let bid = saga
+        .modify(BID_COLLECTION, [office_id], bid_id, |bid: Bid| async {
+            Ok(cancelled(bid))
+        })
+        .await?;
+    for change_order in &change_orders {
+        saga.modify(
+            CHANGE_ORDER_COLLECTION,
+            [office_id],
+            &change_order.id,
+            |change_order: ChangeOrder| async { Ok(rejected(change_order)) },
+        )
+        .await?;
+    }
+    saga.finalize().await;
+
+    let mut cancellation = new_cancellation(&task, Some(bid_id), initiator, claims, reason);
+    unwind_payments(&payments, &mut cancellation).await;
+    close_chats(office_id, task_id, Some(bid_id), &mut cancellation).await;
+    insert(CANCELLATION_COLLECTION, [office_id], &cancellation, None).await?;
+
+    let mut recipients = vec![
+        (
+            task.user_id.clone(),
+            format!(
+                "Det antagna budet på ditt jobb \"{}\" har avbrutits: {}",
+                task.title, reason
+            ),
+        ),
+        (
+            bid.craftsman_id.clone(),
+            format!(
+                "Ditt antagna bud på jobbet \"{}\" har avbrutits: {}",
+                task.title, reason
+            ),
+        ),
+    ];
+    for craftsman_id in bidders(office_id, task_id).await? {
+        if craftsman_id != bid.craftsman_id {
+            recipients.push((
+                craftsman_id,
+                format!("Jobbet \"{}\" är öppet för bud igen.", task.title),
+            ));
+        }
+    }
+    notify(recipients, &claims.sub);
+
+    Ok((task, bid, cancellation))
+}
+
+// Cancels the task for good along with the bid the customer has accepted, if any. The payments of
+// the accepted bid are withdrawn or refunded, every chat about the task is closed and every bidder
+// is told.
+pub async fn cancel_task(
+    office_id: &str,
+    task_id: &str,
+    claims: &Claims,
+    reason: &str,
+) -> Result<(Task, Cancellation), warp::Rejection> {
+    let (task, _): (Task, _) = get(TASK_COLLECTION, [office_id], task_id).await?;
+    let accepted = accepted_bid(office_id, &task).await?;
+    let initiator = match initiator_of(claims, &task, None) {
+        Some(initiator) => initiator,
+        None => {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "Only the customer or an office admin may cancel a task"
+            ))));
+        }
+    };
+    let change_orders = match &accepted {
+        Some((bid, payments)) => {
+            dispute::verify_no_open_dispute(office_id, task_id).await?;
+            verify_refundable(payments)?;
+            verify_unwindable_by(initiator, payments)?;
+            open_change_orders(office_id, &bid.id).await?
+        }
+        None => Vec::new(),
+    };
+
+    // NOTE: Like for a bid the task and its accepted bid are changed together, or a failure would
+    // leave the payments of a cancelled task in escrow
+    let mut saga = CosmosSaga::new();
+    let task = saga
+        .modify(
+            TASK_COLLECTION,
+            [office_id],
+            task_id,
+            |mut task: Task| async {
+                match task.transition(
+                    TaskState::Cancelled,
+                    initiator,
+                    Some(&claims.sub),
+                    Some(reason),
+                ) {
+                    Ok(true) => Ok(task),
+                    Ok(false) => Err(reject::custom(Fault::IllegalState(format!(
+                        "The task has already been cancelled"
+                    )))),
+                    Err(e) => Err(reject::custom(Fault::Forbidden(format!(
+                        "The task can not be cancelled: {}",
+                        e
+                    )))),
+                }
+            },
+        )
+        .await?;
+    if let Some((bid, _)) = &accepted {
+        saga.modify(BID_COLLECTION, [office_id], &bid.id, |bid: Bid| async {
+            Ok(cancelled(bid))
+        })
+        .await?;
+    }
+    for change_order in &change_orders {
+        saga.modify(
+            CHANGE_ORDER_COLLECTION,
+            [office_id],
+            &change_order.id,
+            |change_order: ChangeOrder| async { Ok(rejected(change_order)) },
+        )
+        .await?;
+    }
+    saga.finalize().await;
+
+    let mut cancellation = new_cancellation(&task, None, initiator, claims, reason);
+    if let Some((_, payments)) = &accepted {
+        unwind_payments(payments, &mut cancellation).await;
+    }
+    close_chats(office_id, task_id, None, &mut cancellation).await;
+    insert(CANCELLATION_COLLECTION, [office_id], &cancellation, None).await?;
+
+    let mut recipients = vec![(
+        task.user_id.clone(),
+        format!("Ditt jobb \"{}\" har avbrutits: {}", task.title, reason),
+    )];
+    for craftsman_id in bidders(office_id, task_id).await? {
+        recipients.push((
+            craftsman_id,
+            format!(
+                "Jobbet \"{}\" som du har lagt bud på har avbrutits: {}",
+                task.title, reason
+            ),
+        ));
+    }
+    notify(recipients, &claims.sub);
+
+    Ok((task, cancellation))
+}
+
+fn cancelled(mut bid: Bid) -> Bid {
+    bid.is_cancelled = true;
+    bid.modified = Utc::now();
+    bid
+}
+
+fn rejected(mut change_order: ChangeOrder) -> ChangeOrder {
+    change_order.status = ChangeOrderStatus::Rejected;
+    change_order.modified = Utc::now();
+    change_order
+}
+
+fn new_cancellation(
+    task: &Task,
+    bid_id: Option<&str>,
+    initiator: TaskActor,
+    claims: &Claims,
+    reason: &str,
+) -> Cancellation {
+    Cancellation {
+        id: Uuid::new_v4().to_string(),
+        office_id: task.office_id.clone(),
+        task_id: task.id.clone(),
+        bid_id: bid_id.map(String::from),
+        initiator,
+        user_id: claims.sub.clone(),
+        reason: reason.to_string(),
+        cancelled_payments: Vec::new(),
+        refunded_payments: Vec::new(),
+        failed_payments: Vec::new(),
+        closed_chats: Vec::new(),
+        date: Utc::now(),
+    }
+}
@@ -0,0 +1,8 @@
+// Cancelling a task, or the bid the customer has accepted, and everything that follows from it.
+// Money that has not reached the craftsman is withdrawn or refunded, the chats are closed and the
+// bidders are told. Every cancellation is recorded so that office admins can audit it.
+
+mod cascade;
+
+mod flow;
+pub use flow::{cancel_accepted_bid, cancel_task};
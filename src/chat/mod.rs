@@ -0,0 +1,5 @@
+// Messages the server writes in the chat about a bid, so that the customer and the craftsman can
+// follow what happened to the bid where they talk about it.
+
+mod notice;
+pub use notice::{describe_lines, post_notice};
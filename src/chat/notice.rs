@@ -0,0 +1,79 @@
+use crate::models::{Bid, BidLineKind, Chat, Message, PublishStatus};
+use crate::receipt::kronor;
+use crate::util::log;
+use crate::{CHAT_COLLECTION, MESSAGE_COLLECTION};
+use chrono::Utc;
+use cosmos_utils::{insert, query};
+use uuid::Uuid;
+
+// Writes a message from the server in the chat about the bid. The bid has already changed when
+// this is called so errors are only logged.
+pub async fn post_notice(bid: &Bid, text: String) {
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE c.bidId = "{}""#,
+        CHAT_COLLECTION, bid.id
+    );
+    let chats: Result<Vec<Chat>, _> = query(CHAT_COLLECTION, [&bid.office_id], q, -1).await;
+    let chat = match chats.map(|chats| chats.into_iter().find(|c| !c.deleted)) {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            log(format!("Could not find the chat of bid {}", bid.id));
+            return;
+        }
+        Err(e) => {
+            log(format!(
+                "Could not get the chat of bid {} due to {}",
+                bid.id, e
+            ));
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    let message = Message {
+        id: Uuid::new_v4().to_string(),
+        deleted: false,
+        office_id: bid.office_id.clone(),
+        task_id: bid.task_id.clone(),
+        bid_id: bid.id.clone(),
+        chat_id: chat.id,
+        // NOTE: Craftsman id is the same as the user id
+        user_id: bid.craftsman_id.clone(),
+        sent: now,
+        text,
+        is_read: false,
+        publish_status: PublishStatus::Published,
+        system: true,
+        image: None,
+        modified: now,
+    };
+    if let Err(e) = insert(MESSAGE_COLLECTION, [&bid.office_id], &message, None).await {
+        log(format!(
+            "Could not post a notice about bid {} due to {}",
+            bid.id, e
+        ));
+    }
+}
+
+// The lines of a bid as text for the chat, one line per row
+pub fn describe_lines(bid: &Bid) -> String {
+    let mut text = String::from("Specifikation av budet:");
+    for line in bid.lines.iter() {
+        let kind = match (line.kind, line.rot_eligible) {
+            (BidLineKind::Labour, true) => "arbete, ger ROT/RUT-avdrag",
+            (BidLineKind::Labour, false) => "arbete",
+            (BidLineKind::Material, _) => "material",
+        };
+        text.push_str(&format!(
+            "\n- {}: {} {} × {} = {} ({})",
+            line.description,
+            line.quantity.normalize(),
+            line.unit,
+            kronor(line.unit_price),
+            kronor(line.amount()),
+            kind
+        ));
+    }
+    text.push_str("\nPriserna är exkl. moms.");
+    text
+}
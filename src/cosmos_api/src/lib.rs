@@ -942,6 +942,64 @@ pub async fn upload_blob(mut f: FormData, blob_type: &str, expected_content_type
     ));
 }
 
+/// Uploads generated data to the blob storage under the given blob id, replacing any blob that
+/// already has that id
+pub async fn upload_bytes(
+    data: Vec<u8>,
+    content_type: &str,
+    storage_container: &str,
+    blob_id: &str,
+) -> Result<String, CosmosError> {
+    let http_client: Arc<Box<dyn HttpClient>> = Arc::new(Box::new(reqwest::Client::new()));
+
+    let blob_client = StorageAccountClient::new_access_key(
+        http_client.clone(),
+        STORAGE_ACCOUNT.to_string(),
+        STORAGE_MASTER_KEY.to_string(),
+    )
+    .as_storage_client()
+    .as_container_client(storage_container)
+    .as_blob_client(blob_id);
+
+    // Helps preventing spurious data to be uploaded.
+    let digest = md5::compute(&data[..]).into();
+    match blob_client
+        .put_block_blob(data)
+        .content_type(content_type)
+        .hash(&digest)
+        .execute()
+        .await
+    {
+        Ok(_) => Ok(blob_id.to_string()),
+        Err(err) => Err(new_cosmos_error_kind(
+            format!("Could not add blob to storage account {:?}", err),
+            CosmosErrorKind::BlobError,
+        )),
+    }
+}
+
+/// Downloads a blob from the blob storage
+pub async fn download_blob(storage_container: &str, blob_id: &str) -> Result<Vec<u8>, CosmosError> {
+    let http_client: Arc<Box<dyn HttpClient>> = Arc::new(Box::new(reqwest::Client::new()));
+
+    let blob_client = StorageAccountClient::new_access_key(
+        http_client.clone(),
+        STORAGE_ACCOUNT.to_string(),
+        STORAGE_MASTER_KEY.to_string(),
+    )
+    .as_storage_client()
+    .as_container_client(storage_container)
+    .as_blob_client(blob_id);
+
+    match blob_client.get().execute().await {
+        Ok(r) => Ok(r.data.to_vec()),
+        Err(err) => Err(new_cosmos_error_kind(
+            format!("Could not get blob {} from storage account {:?}", blob_id, err),
+            CosmosErrorKind::BlobError,
+        )),
+    }
+}
+
 pub struct CosmosSaga {
     operation_stack: Vec<Operation>,
 }
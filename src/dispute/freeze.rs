@@ -0,0 +1,27 @@
+use crate::fault::Fault;
+use crate::models::Dispute;
+use crate::DISPUTE_COLLECTION;
+use cosmos_utils::query;
+use warp::reject;
+
+pub async fn open_disputes(
+    office_id: &str,
+    task_id: &str,
+) -> Result<Vec<Dispute>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} d WHERE d.taskId = "{}""#,
+        DISPUTE_COLLECTION, task_id
+    );
+    let disputes: Vec<Dispute> = query(DISPUTE_COLLECTION, [office_id], q, -1).await?;
+    Ok(disputes.into_iter().filter(|d| d.is_open()).collect())
+}
+
+// Makes sure no money of the task is released while a dispute about it is open
+pub async fn verify_no_open_dispute(office_id: &str, task_id: &str) -> Result<(), warp::Rejection> {
+    if !open_disputes(office_id, task_id).await?.is_empty() {
+        return Err(reject::custom(Fault::Forbidden(format!(
+            "The task has an open dispute, no payment can be released before it has been resolved"
+        ))));
+    }
+    Ok(())
+}
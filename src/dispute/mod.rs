@@ -0,0 +1,12 @@
+// Complaints ("reklamationer") about the work on a task. While a dispute is open none of the
+// escrowed money of the task is released. An office admin mediates between the customer and the
+// craftsman and settles the dispute, which releases or refunds the disputed payment.
+
+mod freeze;
+pub use freeze::{open_disputes, verify_no_open_dispute};
+
+mod party;
+pub use party::{notify_parties, party_of};
+
+mod settle;
+pub use settle::settle;
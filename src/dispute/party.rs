@@ -0,0 +1,66 @@
+use crate::models::{Claims, Craftsman, Dispute, DisputeParty, RoleFlags, Task, User};
+use crate::push::send_custom_pn;
+use crate::util::{has_role, log};
+use crate::{CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, USER_COLLECTION};
+use cosmos_utils::get;
+
+// Who the caller is in a dispute about the work of the given craftsman, if anyone
+pub fn party_of(claims: &Claims, task: &Task, craftsman_id: &str) -> Option<DisputeParty> {
+    if claims.sub == task.user_id {
+        Some(DisputeParty::Customer)
+    // NOTE: Craftsman id is the same as the user id
+    } else if claims.sub == craftsman_id {
+        Some(DisputeParty::Craftsman)
+    } else if has_role(
+        Some(&task.office_id),
+        claims,
+        RoleFlags::OFFICE_CONTENT_ADMIN
+            | RoleFlags::OFFICE_PERSONNEL_ADMIN
+            | RoleFlags::OFFICE_BILLING_ADMIN,
+    ) {
+        Some(DisputeParty::Mediator)
+    } else {
+        None
+    }
+}
+
+// Lets the customer and the craftsman know that something happened in the dispute, except for
+// whoever made it happen. This should not cause a failure so it runs in a separate thread.
+pub fn notify_parties(dispute: &Dispute, task: &Task, author: DisputeParty, message: String) {
+    let office_id = dispute.office_id.clone();
+    let craftsman_id = dispute.craftsman_id.clone();
+    let customer_id = task.user_id.clone();
+    tokio::task::spawn(async move {
+        let mut recipients: Vec<User> = Vec::new();
+        if author != DisputeParty::Customer {
+            match get(USER_COLLECTION, [&customer_id], &customer_id).await {
+                Ok((user, _)) => recipients.push(user),
+                Err(e) => log(format!(
+                    "Could not get the customer of a dispute due to {}",
+                    e
+                )),
+            }
+        }
+        if author != DisputeParty::Craftsman {
+            let craftsman_user = async {
+                let (craftsman, _): (Craftsman, _) =
+                    get(CRAFTSMAN_COLLECTION, [&office_id], &craftsman_id).await?;
+                let (user, _): (User, _) =
+                    get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+                Result::<_, warp::Rejection>::Ok(user)
+            };
+            match craftsman_user.await {
+                Ok(user) => recipients.push(user),
+                Err(e) => log(format!(
+                    "Could not get the craftsman of a dispute due to {:?}",
+                    e
+                )),
+            }
+        }
+        for user in recipients.iter() {
+            if let Err(e) = send_custom_pn(user, &message, None, &NOTIFICATION_HUB_ACCOUNT).await {
+                log(format!("Could not send dispute PN due to {}", e));
+            }
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use crate::fault::Fault;
+use crate::finish::{finish_task, FinishedBy};
+use crate::ledger;
+use crate::models::{
+    Bid, ChangeOrder, Dispute, DisputeOutcome, Office, Payment, PaymentState, Task,
+};
+use crate::payment;
+use crate::util::log;
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, OFFICE_COLLECTION, PAYMENT_COLLECTION, TASK_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, maybe_modify, ModifyReturn};
+use rust_decimal::prelude::{Decimal, Zero};
+use warp::reject;
+
+// Carries out the outcome of a dispute on the disputed payment. Settling the same dispute again
+// does not move any money twice, the payment remembers both the release and the refund.
+pub async fn settle(
+    dispute: &Dispute,
+    outcome: DisputeOutcome,
+    refund_amount: Option<Decimal>,
+) -> Result<Payment, warp::Rejection> {
+    let office_id = &dispute.office_id;
+    let (payment, _): (Payment, _) =
+        get(PAYMENT_COLLECTION, [office_id], &dispute.payment_id).await?;
+
+    match outcome {
+        DisputeOutcome::FullRelease => release(dispute, &payment, Decimal::zero()).await,
+        DisputeOutcome::PartialRefund => {
+            let amount = match refund_amount {
+                Some(a) if a > Decimal::zero() && a < payment.amount => a,
+                _ => {
+                    return Err(reject::custom(Fault::IllegalArgument(format!(
+                        "A partial refund has to be more than 0 and less than the {} paid",
+                        payment.amount
+                    ))));
+                }
+            };
+            let released = release(dispute, &payment, amount).await?;
+            payment::refund(&released, amount).await?;
+            Ok(released)
+        }
+        DisputeOutcome::FullRefund => {
+            match payment.payment_state {
+                PaymentState::PaidToEscrow => (),
+                // Idempotancy
+                PaymentState::Refunded => return Ok(payment),
+                s => {
+                    return Err(reject::custom(Fault::IllegalState(format!(
+                        "Only payments in escrow can be refunded, this payment is {:?}",
+                        s
+                    ))));
+                }
+            }
+            payment::refund(&payment, payment.amount).await?;
+            Ok(payment)
+        }
+    }
+}
+
+// Releases what is left of the payment after the refund to the craftsman. If the payment is the
+// one the task is finished with the task is finished as well, which releases the accepted change
+// orders and sends the receipts. A retry picks up wherever the last attempt failed.
+async fn release(
+    dispute: &Dispute,
+    payment: &Payment,
+    refund_amount: Decimal,
+) -> Result<Payment, warp::Rejection> {
+    let office_id = &payment.office_id;
+    let released = maybe_modify(
+        PAYMENT_COLLECTION,
+        [office_id],
+        &payment.id,
+        |mut payment: Payment| match payment.payment_state {
+            PaymentState::PaidToEscrow => {
+                payment.payment_state = PaymentState::Finalized;
+                payment.refunded_amount = refund_amount;
+                payment.modified = Utc::now();
+                Ok(ModifyReturn::Replace(payment))
+            }
+            PaymentState::Finalized | PaymentState::PaidToCraftsman
+                if payment.refunded_amount == refund_amount =>
+            {
+                Ok(ModifyReturn::DontReplace(payment))
+            }
+            s => Err(reject::custom(Fault::IllegalState(format!(
+                "Only payments in escrow can be released, this payment is {:?}",
+                s
+            )))),
+        },
+    )
+    .await?;
+    // NOTE: Booking the ledger again is a conflict and a finished task is not finished again
+    let payment = match released {
+        ModifyReturn::Replace(payment) | ModifyReturn::DontReplace(payment) => payment,
+    };
+
+    let (t, b, o) = tokio::join!(
+        get(TASK_COLLECTION, [office_id], &payment.task_id),
+        get(BID_COLLECTION, [office_id], &payment.bid_id),
+        get(OFFICE_COLLECTION, [office_id], office_id)
+    );
+    let (task, _): (Task, _) = t?;
+    let (bid, _): (Bid, _) = b?;
+    let (office, _): (Office, _) = o?;
+    let change_order: Option<ChangeOrder> = match &payment.change_order_id {
+        Some(id) => Some(get(CHANGE_ORDER_COLLECTION, [office_id], id).await?.0),
+        None => None,
+    };
+
+    // NOTE: The brokerage is only taken from what the craftsman gets
+    let costs = payment
+        .costs(&bid, change_order.as_ref())
+        .part(payment.gross_amount() - refund_amount);
+    if let Err(e) =
+        ledger::record_finalized(&payment, &costs, payment.brokerage_percentage(&office)).await
+    {
+        log(format!(
+            "Could not book finalized payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+
+    let finishes_task = match (&payment.milestone_id, bid.last_milestone()) {
+        (Some(milestone_id), Some(last)) => *milestone_id == last.id,
+        _ => task.payment_id.as_ref() == Some(&payment.id),
+    };
+    if finishes_task && !task.finished {
+        finish_task(office_id, &task.id, FinishedBy::Dispute(dispute.id.clone())).await?;
+    }
+    Ok(payment)
+}
@@ -13,5 +13,8 @@ pub use with_range::with_range;
 mod with_since;
 pub use with_since::with_since;
 
+mod with_optional_body;
+pub use with_optional_body::with_optional_body;
+
 mod handle_rejection;
 pub use handle_rejection::handle_rejection;
@@ -0,0 +1,24 @@
+use crate::fault::Fault;
+use crate::util::{DataRequest, Empty};
+use serde::de::DeserializeOwned;
+use warp::hyper::body::Bytes;
+use warp::{reject, Filter, Rejection};
+
+// A json body that older clients may leave out, in which case the request has no data
+pub fn with_optional_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (DataRequest<T, Empty>,), Error = Rejection> + Clone {
+    warp::body::bytes().and_then(|body: Bytes| async move {
+        if body.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(DataRequest {
+                data: None,
+                extra: None,
+            });
+        }
+        serde_json::from_slice(&body).map_err(|e| {
+            reject::custom(Fault::IllegalArgument(format!(
+                "Could not parse the body: {}.",
+                e
+            )))
+        })
+    })
+}
@@ -0,0 +1,570 @@
+use crate::dispute;
+use crate::fault::Fault;
+use crate::ledger;
+use crate::models::{
+    Bid, Craftsman, Office, Payment, PaymentState, Task, TaskActor, TaskState, User,
+};
+use crate::payment;
+use crate::push::send_custom_pn;
+use crate::util::log;
+use crate::{
+    BID_COLLECTION, CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, OFFICE_COLLECTION,
+    PAYMENT_COLLECTION, SENDGRID_API_KEY, TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::Utc;
+use chrono_tz::{Europe::Stockholm, Tz};
+use cosmos_utils::{get, CosmosSaga};
+use rust_decimal::{prelude::Zero, Decimal};
+use sendgrid::v3::*;
+use warp::reject;
+
+// Who finished the task
+pub enum FinishedBy {
+    // The task-owner, by approving the work
+    Customer(String),
+    // Nobody, the customer did not approve the work or open a dispute within the grace period of
+    // the office after the craftsman marked it as finished
+    GracePeriod,
+    // An office admin, by settling the dispute with the given id about the payment the task is
+    // finished with. The payment has already been released by the settlement.
+    Dispute(String),
+}
+
+// Finishes a task by releasing its escrowed payments to the craftsman and sending the receipts.
+// Used when the customer approves the work, when the grace period of the office runs out and when
+// a dispute about the last payment is settled.
+pub async fn finish_task(
+    office_id: &str,
+    task_id: &str,
+    by: FinishedBy,
+) -> Result<Task, warp::Rejection> {
+    match &by {
+        // NOTE: The dispute being settled is still open until the money has been moved
+        FinishedBy::Dispute(dispute_id) => {
+            let open = dispute::open_disputes(office_id, task_id).await?;
+            if open.iter().any(|d| d.id != *dispute_id) {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "The task has another open dispute, no payment can be released before it has been resolved"
+                ))));
+            }
+        }
+        _ => dispute::verify_no_open_dispute(office_id, task_id).await?,
+    }
+
+    // TODO(Jonathan): Should be maybe_modify
+    let mut saga = CosmosSaga::new();
+    let task = saga
+        .modify(
+            TASK_COLLECTION,
+            [office_id],
+            task_id,
+            |mut task: Task| async {
+                match &by {
+                    FinishedBy::Customer(user_id) if *user_id != task.user_id => {
+                        return Err(reject::custom(Fault::Forbidden(format!(
+                            "User is not the task owner"
+                        ))));
+                    }
+                    FinishedBy::GracePeriod if !task.craftsman_indicated_finished => {
+                        return Err(reject::custom(Fault::Forbidden(format!(
+                            "The craftsman has not marked the task as finished"
+                        ))));
+                    }
+                    _ => (),
+                }
+                if task.accepted_bid.is_none() {
+                    return Err(reject::custom(Fault::Forbidden(format!(
+                        "No bid has been accepted for this task"
+                    ))));
+                }
+                // Idempotancy
+                if !task.finished {
+                    let (actor, user_id, reason) = match &by {
+                        FinishedBy::Customer(user_id) => {
+                            (TaskActor::Customer, Some(user_id.as_str()), None)
+                        }
+                        FinishedBy::GracePeriod => (TaskActor::System, None, None),
+                        FinishedBy::Dispute(_) => {
+                            (TaskActor::System, None, Some("The dispute was resolved"))
+                        }
+                    };
+                    if let Err(e) = task.transition(TaskState::Completed, actor, user_id, reason) {
+                        return Err(reject::custom(Fault::Forbidden(format!(
+                            "The task can not be finished: {}",
+                            e
+                        ))));
+                    }
+                }
+                Ok(task)
+            },
+        )
+        .await?;
+
+    let bid_id = match task.accepted_bid.clone() {
+        Some(r) => r,
+        None => {
+            saga.abort().await?;
+            return Err(warp::reject::custom(Fault::IllegalState(
+                "Unreachable state".to_string(),
+            )));
+        }
+    };
+
+    let (bid, _): (Bid, _) = match get(BID_COLLECTION, [office_id], &bid_id).await {
+        Ok(r) => r,
+        Err(e) => {
+            saga.abort().await?;
+            return Err(e.into());
+        }
+    };
+    // NOTE: Finishing a task with milestones approves the last milestone, which is only possible
+    // once every milestone before it has been approved
+    let payment_id = match bid.last_milestone() {
+        Some(last) => {
+            let r = async {
+                let payments = payment::active_payments(office_id, &bid.id).await?;
+                payment::verify_earlier_milestones(&bid, last, &payments, true)?;
+                match payment::milestone_payment(&payments, &last.id) {
+                    Some(p) => Ok(Some(p.id.clone())),
+                    None => Err(reject::custom(Fault::Forbidden(format!(
+                        "The last milestone has to be paid before the task can be finished"
+                    )))),
+                }
+            };
+            match r.await {
+                Ok(payment_id) => payment_id,
+                Err(e) => {
+                    saga.abort().await?;
+                    return Err(e);
+                }
+            }
+        }
+        None => task.payment_id.clone(),
+    };
+
+    // NOTE: Accepted change orders are released together with the bid
+    let change_orders = match payment::accepted_change_orders(office_id, task_id, &bid.id).await {
+        Ok(change_orders) => change_orders,
+        Err(e) => {
+            saga.abort().await?;
+            return Err(e);
+        }
+    };
+
+    let payment_date;
+    let payment: Payment;
+    if let Some(payment_id) = &payment_id {
+        payment = saga.modify(
+            PAYMENT_COLLECTION,
+            [office_id],
+            &payment_id,
+            |mut payment: Payment| async {
+                match payment.payment_state {
+                    PaymentState::PaidToEscrow => {
+                        payment.payment_state = PaymentState::Finalized;
+                        payment.modified = chrono::Utc::now();
+                        Ok(payment)
+                    },
+                    PaymentState::Finalized if matches!(by, FinishedBy::Dispute(_)) => Ok(payment),
+                    s => Err(reject::custom(Fault::Unspecified(format!(
+                        "Could not finish task as payment was not PaidToEscrow but {:?} instead",
+                        s
+                    )))),
+                }
+            },
+        )
+        .await?;
+        payment_date = match payment.payment_date {
+            Some(date) => date,
+            None => {
+                saga.abort().await?;
+                log(format!("Payment does not have a payment date set after payment has been paid to escrow, this should be impossible in finish_task"));
+                return Err(reject::custom(Fault::IllegalState(format!("Payment does not have a payment date set after payment has been paid to escrow, this should be impossible in finish_task"))));
+            }
+        };
+    } else {
+        saga.abort().await?;
+        log(format!("A task with an accepted bid does not have a payment id, this should be impossible in finish_task"));
+        return Err(reject::custom(Fault::IllegalState(format!("A task with an accepted bid does not have a payment id, this should be impossible in finish_task"))));
+    }
+    let mut change_order_payments = Vec::new();
+    for change_order in &change_orders {
+        let change_order_payment_id = match &change_order.payment_id {
+            Some(id) => id,
+            None => {
+                saga.abort().await?;
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "The change order '{}' has to be paid before the task can be finished",
+                    change_order.description
+                ))));
+            }
+        };
+        let change_order_payment = saga.modify(
+            PAYMENT_COLLECTION,
+            [office_id],
+            change_order_payment_id,
+            |mut payment: Payment| async {
+                match payment.payment_state {
+                    PaymentState::PaidToEscrow => {
+                        payment.payment_state = PaymentState::Finalized;
+                        payment.modified = chrono::Utc::now();
+                        Ok(payment)
+                    },
+                    s => Err(reject::custom(Fault::Forbidden(format!(
+                        "The change order '{}' has to be paid before the task can be finished, its payment is {:?}",
+                        change_order.description, s
+                    )))),
+                }
+            },
+        )
+        .await?;
+        change_order_payments.push((change_order, change_order_payment));
+    }
+    saga.finalize().await;
+
+    let (cm_r, to_r, office) = tokio::join!(
+        async {
+            let (craftsman, _): (Craftsman, _) =
+                get(CRAFTSMAN_COLLECTION, [office_id], &bid.craftsman_id).await?;
+            let (craftsman_user, _): (User, _) =
+                get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+            Result::<_, warp::Rejection>::Ok((craftsman, craftsman_user))
+        },
+        async {
+            let (task_owner, _): (User, _) =
+                get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+            Result::<_, warp::Rejection>::Ok(task_owner)
+        },
+        async {
+            let (office, _): (Office, _) = get(OFFICE_COLLECTION, [office_id], office_id).await?;
+            Result::<_, warp::Rejection>::Ok(office)
+        }
+    );
+    // TODO(Jonathan): Bump the "completed jobs" for craftsman
+    let (craftsman, craftsman_user): (Craftsman, User) = cm_r?;
+    let task_owner = to_r?;
+    let office = office?;
+
+    // NOTE: What was refunded after a dispute never reached the craftsman, and the fee in the email
+    // is taken from the same costs as the one in the ledger
+    let kept = payment.gross_amount() - payment.refunded_amount;
+    let costs = payment.costs(&bid, None).part(kept);
+    let (fee_ex_vat, fee_vat) = ledger::brokerage(
+        &costs,
+        payment.brokerage_percentage(&office),
+        payment.tax_date(),
+    );
+    if let Err(e) =
+        ledger::record_finalized(&payment, &costs, payment.brokerage_percentage(&office)).await
+    {
+        log(format!(
+            "Could not book finalized payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+    for (change_order, change_order_payment) in &change_order_payments {
+        if let Err(e) = ledger::record_finalized(
+            change_order_payment,
+            &change_order.costs(),
+            change_order_payment.brokerage_percentage(&office),
+        )
+        .await
+        {
+            log(format!(
+                "Could not book finalized payment {} in the ledger due to {:?}",
+                change_order_payment.id, e
+            ));
+        }
+    }
+
+    // Send PN to the task owner
+    if let Err(e) = send_custom_pn(
+        &task_owner,
+        &match by {
+            FinishedBy::Customer(_) => {
+                format!("Härligt, du har nu godkänt och avslutat ett av dina jobb!")
+            }
+            FinishedBy::GracePeriod => format!(
+                "Jobbet \"{}\" har godkänts och avslutats automatiskt eftersom ingen reklamation gjordes i tid.",
+                task.title
+            ),
+            FinishedBy::Dispute(_) => format!(
+                "Reklamationen är avslutad och jobbet \"{}\" har avslutats.",
+                task.title
+            ),
+        },
+        None,
+        &NOTIFICATION_HUB_ACCOUNT,
+    )
+    .await
+    {
+        log(format!("Could not send PN in finish_task due to {}", e));
+    }
+
+    // Send PN to the craftsman
+    if let Err(e) = send_custom_pn(
+        &craftsman_user,
+        &match by {
+            FinishedBy::Customer(_) => format!(
+                "Bra jobbat! Nu har {} godkänt ett av jobben du hållit på med!",
+                task_owner.name()
+            ),
+            FinishedBy::GracePeriod => format!(
+                "Bra jobbat! Jobbet \"{}\" har godkänts automatiskt och betalningen är på väg till dig.",
+                task.title
+            ),
+            FinishedBy::Dispute(_) => format!(
+                "Reklamationen är avslutad och jobbet \"{}\" har avslutats. Betalningen är på väg till dig.",
+                task.title
+            ),
+        },
+        None,
+        &NOTIFICATION_HUB_ACCOUNT,
+    )
+    .await
+    {
+        log(format!("Could not send PN in finish_task due to {}", e));
+    }
+
+    // Make todays Stockholm date from IANA location.
+    let tz: Tz = Stockholm;
+    let today = Utc::now();
+    let today = today.with_timezone(&tz);
+    let today = today.to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+    let payment_date = payment_date.with_timezone(&tz);
+    let payment_date = payment_date.to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+
+    // Send receipt email to task owner.
+    let mut map = SGMap::new();
+    map.insert(String::from("userFirstName"), task_owner.first_name.clone());
+    map.insert(String::from("userLastName"), task_owner.last_name.clone());
+    map.insert(
+        String::from("craftmanCompanyName"),
+        craftsman.company_name.clone(),
+    );
+    map.insert(
+        String::from("craftmanCompanyNid"),
+        craftsman.org_number.clone(),
+    );
+    map.insert(
+        String::from("craftsmanFirstName"),
+        craftsman_user.first_name.clone(),
+    );
+    map.insert(
+        String::from("craftsmanLastName"),
+        craftsman_user.last_name.clone(),
+    );
+    map.insert(String::from("jobTitle"), task.title.clone());
+    map.insert(String::from("jobTransactionDate"), payment_date.clone());
+    map.insert(String::from("jobFinishDate"), today.clone());
+    map.insert(
+        String::from("jobCraftmanPrice"),
+        bid.labour_cost.to_string(),
+    );
+    map.insert(
+        String::from("craftmanMaterialCost"),
+        bid.material_cost.to_string(),
+    );
+    map.insert(
+        String::from("rotavdrag"),
+        if bid.root_deduction == Decimal::zero() {
+            String::from("Ej applicerbart")
+        } else {
+            bid.root_deduction.to_string()
+        },
+    );
+    map.insert(String::from("vat"), bid.vat.to_string());
+    map.insert(String::from("jobSum"), bid.final_bid.to_string());
+
+    let p = Personalization::new(Email::new("support@toolitapp.com"))
+        .add_to(Email::new(&task_owner.email))
+        .add_dynamic_template_data(map);
+
+    let to_email = Message::new(Email::new("support@toolitapp.com"))
+        .set_template_id("d-e734aff83ce247a3b6ce0b30306b17c9")
+        .add_personalization(p);
+
+    // Turn the crafts into a string of names
+    let mut object_types = match task.crafts.get(0) {
+        Some(c) => c.swedish_name(),
+        None => String::new(),
+    };
+    for craft in task.crafts.iter().skip(1) {
+        object_types.push_str(&format!(", {}", craft.swedish_name()));
+    }
+
+    // Send receipt email to craftsman
+    let mut map = SGMap::new();
+    map.insert(String::from("userFirstName"), task_owner.first_name.clone());
+    map.insert(String::from("userLastName"), task_owner.last_name.clone());
+    map.insert(String::from("userNid"), task_owner.nid.clone());
+    map.insert(String::from("jobAddress"), task.address.clone());
+    map.insert(String::from("jobPostalCode"), task.postcode.clone());
+    map.insert(String::from("jobCity"), task.city.clone());
+    map.insert(String::from("objectType"), object_types.clone());
+    map.insert(
+        String::from("rotRutAvdragChoice"),
+        if task.use_rot_rut {
+            String::from("ja")
+        } else {
+            String::from("nej")
+        },
+    );
+    map.insert(
+        String::from("houseDesignation"),
+        task.property_designation
+            .clone()
+            .unwrap_or(String::from("N/A")),
+    );
+    map.insert(
+        String::from("apartmentCooperative"),
+        task.realestate_union.clone().unwrap_or(String::from("N/A")),
+    );
+    map.insert(
+        String::from("apartmentNr"),
+        task.apartment_number.clone().unwrap_or(String::from("N/A")),
+    );
+    map.insert(String::from("jobTitle"), task.title.clone());
+    map.insert(String::from("jobTransactionDate"), payment_date.clone());
+    map.insert(String::from("jobFinishDate"), today.clone());
+    map.insert(
+        String::from("jobCraftmanPrice"),
+        bid.labour_cost.to_string(),
+    );
+    map.insert(
+        String::from("craftmanMaterialCost"),
+        bid.material_cost.to_string(),
+    );
+    map.insert(
+        String::from("rotavdrag"),
+        if bid.root_deduction == Decimal::zero() {
+            String::from("Ej applicerbart")
+        } else {
+            bid.root_deduction.to_string()
+        },
+    );
+    map.insert(String::from("jobSum"), bid.final_bid.to_string());
+
+    map.insert(String::from("vat"), bid.vat.to_string());
+    map.insert(String::from("brokerageFeeExVat"), fee_ex_vat.to_string());
+    map.insert(String::from("brokerageFeeVat"), fee_vat.to_string());
+    map.insert(
+        String::from("brokerageFeeSum"),
+        (fee_ex_vat + fee_vat).to_string(),
+    );
+
+    let p = Personalization::new(Email::new("support@toolitapp.com"))
+        .add_to(Email::new(&craftsman_user.email))
+        .add_dynamic_template_data(map);
+
+    let craftsman_email = Message::new(Email::new("support@toolitapp.com"))
+        .set_template_id("d-1b5e3f4d1a9a46149b58a6d3c5b19daa")
+        .add_personalization(p);
+
+    // Send receipt email to toolit
+    let mut map = SGMap::new();
+    map.insert(String::from("userFirstName"), task_owner.first_name.clone());
+    map.insert(String::from("userLastName"), task_owner.last_name.clone());
+    map.insert(String::from("userNid"), task_owner.nid.clone());
+    map.insert(String::from("jobAddress"), task.address.clone());
+    map.insert(String::from("jobPostalCode"), task.postcode.clone());
+    map.insert(String::from("jobCity"), task.city.clone());
+    map.insert(String::from("objectType"), object_types);
+    map.insert(
+        String::from("rotRutAvdragChoice"),
+        if task.use_rot_rut {
+            String::from("ja")
+        } else {
+            String::from("nej")
+        },
+    );
+    map.insert(
+        String::from("houseDesignation"),
+        task.property_designation
+            .clone()
+            .unwrap_or(String::from("N/A")),
+    );
+    map.insert(
+        String::from("apartmentCooperative"),
+        task.realestate_union.clone().unwrap_or(String::from("N/A")),
+    );
+    map.insert(
+        String::from("apartmentNr"),
+        task.apartment_number.clone().unwrap_or(String::from("N/A")),
+    );
+    map.insert(String::from("jobTitle"), task.title.clone());
+    map.insert(String::from("jobTransactionDate"), payment_date);
+    map.insert(String::from("jobFinishDate"), today);
+    map.insert(
+        String::from("jobCraftmanPrice"),
+        bid.labour_cost.to_string(),
+    );
+    map.insert(
+        String::from("craftmanMaterialCost"),
+        bid.material_cost.to_string(),
+    );
+    map.insert(
+        String::from("rotavdrag"),
+        if bid.root_deduction == Decimal::zero() {
+            String::from("Ej applicerbart")
+        } else {
+            bid.root_deduction.to_string()
+        },
+    );
+    map.insert(String::from("jobSum"), bid.final_bid.to_string());
+    map.insert(
+        String::from("craftsmanAccountNumber"),
+        craftsman.account_number.to_string(),
+    );
+    map.insert(String::from("vat"), bid.vat.to_string());
+    map.insert(
+        String::from("brokerageFeeSum"),
+        (fee_ex_vat + fee_vat).to_string(),
+    );
+
+    let p = Personalization::new(Email::new("support@toolitapp.com"))
+        .add_to(Email::new(&craftsman_user.email))
+        .add_dynamic_template_data(map);
+
+    let toolit_email = Message::new(Email::new("support@toolitapp.com"))
+        .set_template_id("d-17ab5ef7123848e18dea987d1bc40d20")
+        .add_personalization(p);
+    let sender = Sender::new(SENDGRID_API_KEY.to_string());
+    let (to_r, cr_r, tool_r) = tokio::join!(
+        sender.send(&to_email),
+        sender.send(&craftsman_email),
+        sender.send(&toolit_email)
+    );
+
+    match to_r {
+        Ok(_) => (),
+        Err(e) => {
+            log(format!(
+                "Could not send email to task owner for finishing task {:?}",
+                e
+            ));
+        }
+    }
+
+    match cr_r {
+        Ok(_) => (),
+        Err(e) => {
+            log(format!(
+                "Could not send email to craftsman for finishing task {:?}",
+                e
+            ));
+        }
+    }
+
+    match tool_r {
+        Ok(_) => (),
+        Err(e) => {
+            log(format!(
+                "Could not send email to toolit for finishing task {:?}",
+                e
+            ));
+        }
+    }
+
+    Ok(task)
+}
@@ -0,0 +1,34 @@
+use crate::models::{Craftsman, Location, Task};
+use crate::CRAFTSMAN_COLLECTION;
+use cosmos_utils::query;
+
+// A Cosmos condition that holds when the service area stored at the given path covers the point
+pub fn sql_area_covers(area: &str, point: &Location) -> String {
+    let point = serde_json::to_string(point).unwrap_or_default();
+    format!(
+        r#"(({area}.kind = "Radius" AND ST_DISTANCE({area}.center, {point}) <= {area}.radiusKm * 1000) OR ({area}.kind = "Polygon" AND ST_WITHIN({point}, {area}.area)))"#,
+        area = area,
+        point = point
+    )
+}
+
+// The craftsmen of an office whose service area covers the task and who can bid on it
+pub async fn craftsmen_serving(
+    office_id: &str,
+    task: &Task,
+) -> Result<Vec<Craftsman>, warp::Rejection> {
+    let location = match &task.location {
+        Some(location) => location,
+        None => return Ok(Vec::new()),
+    };
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE IS_DEFINED(c.serviceArea) AND {}"#,
+        CRAFTSMAN_COLLECTION,
+        sql_area_covers("c.serviceArea", location)
+    );
+    let craftsmen: Vec<Craftsman> = query(CRAFTSMAN_COLLECTION, [office_id], q, -1).await?;
+    Ok(craftsmen
+        .into_iter()
+        .filter(|c| !c.deleted && !c.frozen && c.can_bid_on(task))
+        .collect())
+}
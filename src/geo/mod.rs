@@ -0,0 +1,8 @@
+// Geography of tasks and craftsmen. Tasks get a point from their postcode and craftsmen define
+// the area they work in, which Cosmos can then match with its spatial functions.
+
+mod postcode;
+pub use postcode::postcode_location;
+
+mod matching;
+pub use matching::{craftsmen_serving, sql_area_covers};
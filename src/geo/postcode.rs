@@ -0,0 +1,34 @@
+use crate::models::Location;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+lazy_static! {
+    // NOTE: The table is bundled with the service so geocoding never depends on an outside
+    // service. A row can be a whole postcode or just the start of one, which then covers every
+    // postcode in that area. Every two digit postal area has a row so that any postcode in use
+    // gets at least a rough location, denser areas also have rows for their three digit areas.
+    static ref CENTROIDS: HashMap<String, Location> = include_str!("postcodes.csv")
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut columns = line.split(';');
+            let postcode = columns.next()?.trim().to_string();
+            let latitude = columns.next()?.trim().parse().ok()?;
+            let longitude = columns.next()?.trim().parse().ok()?;
+            Some((postcode, Location::point(latitude, longitude)))
+        })
+        .collect();
+}
+
+// The centroid of a swedish postcode, e.g. "123 45", falling back to the most specific area that
+// is in the table. None if it is not a postcode in use.
+pub fn postcode_location(postcode: &str) -> Option<Location> {
+    let digits: String = postcode.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() != 5 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    (2..=5)
+        .rev()
+        .find_map(|len| CENTROIDS.get(&digits[..len]))
+        .cloned()
+}
@@ -0,0 +1,168 @@
+use crate::dispute;
+use crate::finish::{finish_task, FinishedBy};
+use crate::models::{Office, Task, User};
+use crate::push::send_custom_pn;
+use crate::util::log;
+use crate::{
+    NOTIFICATION_HUB_ACCOUNT, OFFICE_COLLECTION, SENDGRID_API_KEY, TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::{Duration, Utc};
+use cosmos_utils::{get, maybe_modify, query_crosspartition, ModifyReturn};
+use sendgrid::v3::*;
+use std::collections::HashMap;
+
+// MAGIC NUMBER: The customer is reminded two days before the task is finished for them
+const REMINDER_DAYS_BEFORE: i64 = 2;
+
+// Reminds customers to approve work the craftsman has marked as finished, and finishes the task
+// for them once the grace period of the office has run out. Tasks with an open dispute are left
+// alone until the dispute has been resolved.
+pub async fn finish_overdue_tasks() -> Result<(), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} t WHERE t.craftsmanIndicatedFinished = true AND (NOT IS_DEFINED(t.finished) OR t.finished = false) AND (NOT IS_DEFINED(t.deleted) OR t.deleted = false)"#,
+        TASK_COLLECTION,
+    );
+    let tasks: Vec<Task> = query_crosspartition(TASK_COLLECTION, [()], q, -1, true).await?;
+
+    let mut offices: HashMap<String, Office> = HashMap::new();
+    for task in tasks {
+        let finished_date = match task.craftsman_finished_date {
+            Some(date) => date,
+            // NOTE: Marked as finished before the date was stored, the grace period starts now
+            None => {
+                if let Err(e) = start_grace_period(&task).await {
+                    log(format!(
+                        "Could not start the grace period of task {} due to {:?}",
+                        task.id, e
+                    ));
+                }
+                continue;
+            }
+        };
+
+        if !offices.contains_key(&task.office_id) {
+            match get(OFFICE_COLLECTION, [&task.office_id], &task.office_id).await {
+                Ok((office, _)) => {
+                    offices.insert(task.office_id.clone(), office);
+                }
+                Err(e) => {
+                    log(format!(
+                        "Could not get office {} in the auto finish job due to {}",
+                        task.office_id, e
+                    ));
+                    continue;
+                }
+            }
+        }
+        let office = &offices[&task.office_id];
+        let deadline = match finished_date.checked_add_signed(office.auto_finish()) {
+            Some(deadline) => deadline,
+            None => {
+                log(format!(
+                    "Could not compute when task {} is finished from {}",
+                    task.id, finished_date
+                ));
+                continue;
+            }
+        };
+
+        let r = async {
+            let now = Utc::now();
+            if now >= deadline {
+                // NOTE: The task is finished once the dispute has been resolved, if it still should be
+                if !dispute::open_disputes(&task.office_id, &task.id)
+                    .await?
+                    .is_empty()
+                {
+                    return Ok(());
+                }
+                finish_task(&task.office_id, &task.id, FinishedBy::GracePeriod).await?;
+            } else if !task.finish_reminder_sent
+                && now >= deadline - Duration::days(REMINDER_DAYS_BEFORE)
+            {
+                remind(&task, office.auto_finish().num_days()).await?;
+            }
+            Result::<_, warp::Rejection>::Ok(())
+        };
+        if let Err(e) = r.await {
+            log(format!(
+                "Could not auto finish task {} due to {:?}",
+                task.id, e
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn start_grace_period(task: &Task) -> Result<(), warp::Rejection> {
+    maybe_modify(
+        TASK_COLLECTION,
+        [&task.office_id],
+        &task.id,
+        |mut task: Task| {
+            if task.craftsman_finished_date.is_some() {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            task.craftsman_finished_date = Some(Utc::now());
+            task.modified = Utc::now();
+            Ok(ModifyReturn::Replace(task))
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn remind(task: &Task, auto_finish_days: i64) -> Result<(), warp::Rejection> {
+    // NOTE: The flag is set first so that several instances of the job do not all remind
+    let marked = maybe_modify(
+        TASK_COLLECTION,
+        [&task.office_id],
+        &task.id,
+        |mut task: Task| {
+            if task.finish_reminder_sent {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            task.finish_reminder_sent = true;
+            task.modified = Utc::now();
+            Ok(ModifyReturn::Replace(task))
+        },
+    )
+    .await?;
+    if let ModifyReturn::DontReplace(_) = marked {
+        return Ok(());
+    }
+
+    let (task_owner, _): (User, _) = get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+    let text = format!(
+        "Hantverkaren har markerat jobbet \"{}\" som klart. Om du inte godkänner jobbet eller gör en reklamation så godkänns det automatiskt {} dagar efter att det markerades som klart.",
+        task.title, auto_finish_days
+    );
+
+    // Send PN to the task owner
+    if let Err(e) = send_custom_pn(&task_owner, &text, None, &NOTIFICATION_HUB_ACCOUNT).await {
+        log(format!(
+            "Could not send PN in the auto finish job due to {}",
+            e
+        ));
+    }
+
+    // Send reminder email to the task owner
+    let p = Personalization::new(Email::new("support@toolitapp.com"))
+        .add_to(Email::new(&task_owner.email));
+    let m = Message::new(Email::new("support@toolitapp.com"))
+        .set_subject("Påminnelse: godkänn ditt jobb")
+        .add_content(
+            Content::new()
+                .set_content_type("text/plain")
+                .set_value(&text),
+        )
+        .add_personalization(p);
+    let sender = Sender::new(SENDGRID_API_KEY.to_string());
+    if let Err(e) = sender.send(&m).await {
+        log(format!(
+            "Could not send reminder email for task {} due to {:?}",
+            task.id, e
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+// Work that runs in the background on an interval rather than in response to a request. Every
+// job has to be safe to run on several instances of the api at once.
+
+use crate::ledger;
+use crate::util::log;
+use futures::Future;
+use std::time::Duration;
+
+mod auto_finish;
+mod payment_timeout;
+mod price_estimates;
+mod search_digest;
+mod task_expiry;
+
+pub fn start() {
+    run_every(
+        Duration::from_secs(60),
+        "payment timeout",
+        payment_timeout::cancel_abandoned_payments,
+    );
+    run_every(
+        Duration::from_secs(10 * 60),
+        "ledger queue",
+        ledger::book_queued,
+    );
+    run_every(
+        Duration::from_secs(60 * 60),
+        "auto finish",
+        auto_finish::finish_overdue_tasks,
+    );
+    run_every(
+        Duration::from_secs(60 * 60),
+        "search digest",
+        search_digest::send_search_digests,
+    );
+    run_every(
+        Duration::from_secs(60 * 60),
+        "area digest",
+        search_digest::send_area_digests,
+    );
+    run_every(
+        Duration::from_secs(60 * 60),
+        "task expiry",
+        task_expiry::expire_stale_tasks,
+    );
+    run_every(
+        Duration::from_secs(24 * 60 * 60),
+        "price estimates",
+        price_estimates::refresh_price_estimates,
+    );
+}
+
+fn run_every<F, Fut>(period: Duration, name: &'static str, job: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), warp::Rejection>> + Send,
+{
+    tokio::task::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = job().await {
+                log(format!("The {} job failed due to {:?}", name, e));
+            }
+        }
+    });
+}
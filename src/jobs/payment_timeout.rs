@@ -0,0 +1,54 @@
+use crate::models::Payment;
+use crate::payment::{self, ProviderStatus};
+use crate::util::log;
+use crate::{PAYMENT_COLLECTION, PAYMENT_TIMEOUT_MINUTES};
+use chrono::{Duration, SecondsFormat, Utc};
+use cosmos_utils::query_crosspartition;
+
+// Cancels swish payments the customer never confirmed in the app, since the bid can not be
+// accepted again while a payment is in progress
+pub async fn cancel_abandoned_payments() -> Result<(), warp::Rejection> {
+    let cutoff = Utc::now() - Duration::minutes(*PAYMENT_TIMEOUT_MINUTES);
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.paymentMethod = "swish" AND p.paymentState = "initialized" AND p.modified < "{}""#,
+        PAYMENT_COLLECTION,
+        cutoff.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    let payments: Vec<Payment> =
+        query_crosspartition(PAYMENT_COLLECTION, [()], q, -1, true).await?;
+
+    for stored in payments {
+        // NOTE: Swish may have failed to call us back, so we ask what happened before cancelling
+        let r = match payment::provider(&stored.payment_method)
+            .retrieve(&stored)
+            .await
+        {
+            Ok(ProviderStatus::Paid {
+                amount,
+                reference,
+                date,
+            }) => {
+                match payment::escrow(&stored.office_id, &stored.id, amount, reference, date).await
+                {
+                    Ok(paid) => {
+                        payment::paid_to_escrow(paid).await;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Ok(ProviderStatus::Pending) => payment::cancel(&stored).await.map(|_| ()),
+            Ok(ProviderStatus::Failed(_)) => payment::fail(&stored.office_id, &stored.id)
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = r {
+            log(format!(
+                "Could not time out payment {} due to {:?}",
+                stored.id, e
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use crate::models::{Bid, CraftType, PriceEstimate, Task, TaskState};
+use crate::util::log;
+use crate::{BID_COLLECTION, PRICE_ESTIMATE_COLLECTION, TASK_COLLECTION};
+use cosmos_utils::{delete, query, query_crosspartition, upsert};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+// MAGIC NUMBER: Fewer accepted bids than this and a single odd job moves the estimate too much
+const MIN_SAMPLE_SIZE: usize = 10;
+// MAGIC NUMBER: Tasks mentioning a keyword are more alike, so fewer of them are needed
+const MIN_KEYWORD_SAMPLE_SIZE: usize = 5;
+// MAGIC NUMBER: Keeps the queries for the accepted bids well below the size limit of Cosmos
+const BIDS_PER_QUERY: usize = 500;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EstimateRef {
+    id: String,
+    office_id: String,
+}
+
+// Rebuilds the price estimates of every office from the bids customers have accepted. Estimates
+// that no longer have enough accepted bids behind them are removed.
+pub async fn refresh_price_estimates() -> Result<(), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} t WHERE IS_DEFINED(t.acceptedBid) AND NOT IS_NULL(t.acceptedBid) AND (NOT IS_DEFINED(t.deleted) OR t.deleted = false)"#,
+        TASK_COLLECTION,
+    );
+    let tasks: Vec<Task> = query_crosspartition(TASK_COLLECTION, [()], q, -1, true).await?;
+
+    let mut offices: HashMap<String, Vec<Task>> = HashMap::new();
+    for task in tasks
+        .into_iter()
+        .filter(|t| t.state() != TaskState::Cancelled)
+    {
+        offices
+            .entry(task.office_id.clone())
+            .or_insert_with(Vec::new)
+            .push(task);
+    }
+
+    let mut refreshed = HashSet::new();
+    for (office_id, tasks) in offices.iter() {
+        let estimates = match office_estimates(office_id, tasks).await {
+            Ok(estimates) => estimates,
+            Err(e) => {
+                log(format!(
+                    "Could not compute the price estimates of office {} due to {:?}",
+                    office_id, e
+                ));
+                // NOTE: The old estimates are kept rather than removed when they could not be
+                // computed
+                refreshed.insert((office_id.clone(), None));
+                continue;
+            }
+        };
+        for estimate in estimates {
+            match upsert(PRICE_ESTIMATE_COLLECTION, [office_id], &estimate, None).await {
+                Ok(_) => {
+                    refreshed.insert((office_id.clone(), Some(estimate.id)));
+                }
+                Err(e) => log(format!(
+                    "Could not store price estimate {} of office {} due to {}",
+                    estimate.id, office_id, e
+                )),
+            }
+        }
+    }
+
+    let q = format!(
+        r#"SELECT e.id, e.officeId FROM {} e"#,
+        PRICE_ESTIMATE_COLLECTION
+    );
+    let stored: Vec<EstimateRef> =
+        query_crosspartition(PRICE_ESTIMATE_COLLECTION, [()], q, -1, true).await?;
+    for estimate in stored {
+        if refreshed.contains(&(estimate.office_id.clone(), None))
+            || refreshed.contains(&(estimate.office_id.clone(), Some(estimate.id.clone())))
+        {
+            continue;
+        }
+        if let Err(e) = delete(
+            PRICE_ESTIMATE_COLLECTION,
+            [&estimate.office_id],
+            &estimate.id,
+            None,
+        )
+        .await
+        {
+            log(format!(
+                "Could not remove price estimate {} of office {} due to {}",
+                estimate.id, estimate.office_id, e
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The estimates of an office that have enough accepted bids behind them
+async fn office_estimates(
+    office_id: &str,
+    tasks: &[Task],
+) -> Result<Vec<PriceEstimate>, warp::Rejection> {
+    // NOTE: The price of a task with several crafts covers all of them and says nothing about what
+    // any one of them costs, so only tasks of a single craft are used
+    let tasks: Vec<&Task> = tasks.iter().filter(|t| t.crafts.len() == 1).collect();
+    let bid_ids: Vec<String> = tasks
+        .iter()
+        .filter_map(|t| t.accepted_bid.as_ref())
+        .map(|id| format!(r#""{}""#, id))
+        .collect();
+    let mut bids = HashMap::new();
+    for chunk in bid_ids.chunks(BIDS_PER_QUERY) {
+        let q = format!(
+            r#"SELECT * FROM {} b WHERE ARRAY_CONTAINS([{}], b.id)"#,
+            BID_COLLECTION,
+            chunk.join(",")
+        );
+        let found: Vec<Bid> = query(BID_COLLECTION, [office_id], q, -1).await?;
+        for bid in found.into_iter().filter(|b| !b.is_cancelled) {
+            bids.insert(bid.id.clone(), bid);
+        }
+    }
+
+    let mut by_craft: HashMap<String, (CraftType, Vec<Decimal>)> = HashMap::new();
+    let mut by_keyword: HashMap<String, (CraftType, String, Vec<Decimal>)> = HashMap::new();
+    for task in tasks {
+        let bid = match task.accepted_bid.as_ref().and_then(|id| bids.get(id)) {
+            Some(bid) => bid,
+            None => continue,
+        };
+        // NOTE: The price before any root deduction so that tasks with and without ROT or RUT can
+        // be compared
+        let price = bid.final_bid + bid.root_deduction;
+        let keywords = PriceEstimate::keywords(&format!("{} {}", task.title, task.description));
+        let craft = &task.crafts[0];
+        by_craft
+            .entry(PriceEstimate::id_of(craft, None))
+            .or_insert_with(|| (craft.clone(), Vec::new()))
+            .1
+            .push(price);
+        for keyword in keywords.iter() {
+            by_keyword
+                .entry(PriceEstimate::id_of(craft, Some(keyword)))
+                .or_insert_with(|| (craft.clone(), keyword.clone(), Vec::new()))
+                .2
+                .push(price);
+        }
+    }
+
+    let mut estimates = Vec::new();
+    for (craft, prices) in by_craft.values_mut() {
+        if prices.len() >= MIN_SAMPLE_SIZE {
+            estimates.extend(PriceEstimate::of(office_id, craft, None, prices));
+        }
+    }
+    for (craft, keyword, prices) in by_keyword.values_mut() {
+        if prices.len() >= MIN_KEYWORD_SAMPLE_SIZE {
+            estimates.extend(PriceEstimate::of(office_id, craft, Some(keyword), prices));
+        }
+    }
+    Ok(estimates)
+}
@@ -0,0 +1,119 @@
+use crate::alerts::notify_user;
+use crate::models::{Craftsman, SavedSearch};
+use crate::util::log;
+use crate::{CRAFTSMAN_COLLECTION, SAVED_SEARCH_COLLECTION};
+use chrono::{Duration, Utc};
+use cosmos_utils::{maybe_modify, query_crosspartition, ModifyReturn};
+
+// Sends the daily digest of the tasks that matched a saved search but were not alerted on right
+// away, either because the craftsman wants a digest or because the daily cap was reached
+pub async fn send_search_digests() -> Result<(), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} s WHERE ARRAY_LENGTH(s.pendingTaskIds) > 0 AND (NOT IS_DEFINED(s.deleted) OR s.deleted = false)"#,
+        SAVED_SEARCH_COLLECTION,
+    );
+    let searches: Vec<SavedSearch> =
+        query_crosspartition(SAVED_SEARCH_COLLECTION, [()], q, -1, true).await?;
+
+    let due = Utc::now() - Duration::days(1);
+    for search in searches
+        .iter()
+        .filter(|s| s.last_digest.is_none_or(|last| last <= due))
+    {
+        // NOTE: The pending tasks are taken first so that several instances of the job do not all
+        // send the digest
+        let taken = maybe_modify(
+            SAVED_SEARCH_COLLECTION,
+            [&search.office_id],
+            &search.id,
+            |mut search: SavedSearch| {
+                if search.pending_task_ids.is_empty()
+                    || search.last_digest.is_some_and(|last| last > due)
+                {
+                    return Ok(ModifyReturn::DontReplace(search));
+                }
+                search.pending_task_ids.clear();
+                search.last_digest = Some(Utc::now());
+                Ok(ModifyReturn::Replace(search))
+            },
+        )
+        .await;
+        match taken {
+            Ok(ModifyReturn::Replace(_)) => {
+                let count = search.pending_task_ids.len();
+                let text = if count == 1 {
+                    format!("Ett nytt jobb matchar din sökning \"{}\".", search.name)
+                } else {
+                    format!(
+                        "{} nya jobb matchar din sökning \"{}\".",
+                        count, search.name
+                    )
+                };
+                // NOTE: Craftsman id is the same as the user id
+                notify_user(&search.craftsman_id, &text).await;
+            }
+            Ok(ModifyReturn::DontReplace(_)) => {}
+            Err(e) => log(format!(
+                "Could not send the digest of saved search {} due to {}",
+                search.id, e
+            )),
+        }
+    }
+    Ok(())
+}
+
+// Sends the daily digest of the tasks in the service area of a craftsman without saved searches
+// that were not alerted on because the daily cap was reached
+pub async fn send_area_digests() -> Result<(), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE ARRAY_LENGTH(c.pendingAreaTaskIds) > 0"#,
+        CRAFTSMAN_COLLECTION,
+    );
+    let craftsmen: Vec<Craftsman> =
+        query_crosspartition(CRAFTSMAN_COLLECTION, [()], q, -1, true).await?;
+
+    let due = Utc::now() - Duration::days(1);
+    for craftsman in craftsmen
+        .iter()
+        .filter(|c| c.last_area_digest.is_none_or(|last| last <= due))
+    {
+        // NOTE: The pending tasks are taken first so that several instances of the job do not all
+        // send the digest
+        let taken = maybe_modify(
+            CRAFTSMAN_COLLECTION,
+            [&craftsman.office_id],
+            &craftsman.id,
+            |mut craftsman: Craftsman| {
+                if craftsman.pending_area_task_ids.is_empty()
+                    || craftsman.last_area_digest.is_some_and(|last| last > due)
+                {
+                    return Ok(ModifyReturn::DontReplace(craftsman));
+                }
+                craftsman.pending_area_task_ids.clear();
+                craftsman.last_area_digest = Some(Utc::now());
+                Ok(ModifyReturn::Replace(craftsman))
+            },
+        )
+        .await;
+        match taken {
+            Ok(ModifyReturn::Replace(_)) => {
+                if craftsman.deleted || craftsman.frozen {
+                    continue;
+                }
+                let count = craftsman.pending_area_task_ids.len();
+                let text = if count == 1 {
+                    String::from("Ett nytt jobb har lagts upp i ditt område.")
+                } else {
+                    format!("{} nya jobb har lagts upp i ditt område.", count)
+                };
+                notify_user(&craftsman.user_id, &text).await;
+            }
+            Ok(ModifyReturn::DontReplace(_)) => {}
+            Err(e) => log(format!(
+                "Could not send the area digest of craftsman {} due to {}",
+                craftsman.id, e
+            )),
+        }
+    }
+    Ok(())
+}
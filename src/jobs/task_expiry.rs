@@ -0,0 +1,190 @@
+use crate::alerts::notify_user;
+use crate::models::{Bid, Office, Task, TaskActor, TaskState};
+use crate::util::log;
+use crate::{BID_COLLECTION, OFFICE_COLLECTION, TASK_COLLECTION};
+use chrono::{Duration, Utc};
+use cosmos_utils::{get, maybe_modify, query, query_crosspartition, ModifyReturn};
+use std::collections::HashMap;
+
+// MAGIC NUMBER: The customer is reminded three days before the task expires
+const REMINDER_DAYS_BEFORE: i64 = 3;
+
+// Reminds customers about published tasks that nobody has accepted a bid on in a while, and
+// expires them once the expiry of the office has run out. An expired task no longer shows up for
+// craftsmen.
+pub async fn expire_stale_tasks() -> Result<(), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} t WHERE t.publishStatus = "Published" AND (NOT IS_DEFINED(t.acceptedBid) OR IS_NULL(t.acceptedBid)) AND (NOT IS_DEFINED(t.finished) OR t.finished = false) AND (NOT IS_DEFINED(t.deleted) OR t.deleted = false)"#,
+        TASK_COLLECTION,
+    );
+    let tasks: Vec<Task> = query_crosspartition(TASK_COLLECTION, [()], q, -1, true).await?;
+
+    let mut offices: HashMap<String, Office> = HashMap::new();
+    for task in tasks
+        .into_iter()
+        .filter(|t| t.state() == TaskState::Published)
+    {
+        let published_date = match task.published_date {
+            Some(date) => date,
+            // NOTE: Published before the date was stored, the task is open from now on
+            None => {
+                if let Err(e) = start_expiry(&task).await {
+                    log(format!(
+                        "Could not start the expiry of task {} due to {:?}",
+                        task.id, e
+                    ));
+                }
+                continue;
+            }
+        };
+
+        if !offices.contains_key(&task.office_id) {
+            match get(OFFICE_COLLECTION, [&task.office_id], &task.office_id).await {
+                Ok((office, _)) => {
+                    offices.insert(task.office_id.clone(), office);
+                }
+                Err(e) => {
+                    log(format!(
+                        "Could not get office {} in the task expiry job due to {}",
+                        task.office_id, e
+                    ));
+                    continue;
+                }
+            }
+        }
+        let office = &offices[&task.office_id];
+        let deadline = match published_date.checked_add_signed(office.task_expiry()) {
+            Some(deadline) => deadline,
+            None => {
+                log(format!(
+                    "Could not compute when task {} expires from {}",
+                    task.id, published_date
+                ));
+                continue;
+            }
+        };
+
+        let r = async {
+            let now = Utc::now();
+            if now >= deadline {
+                expire(&task).await?;
+            } else if !task.expiry_reminder_sent
+                && now >= deadline - Duration::days(REMINDER_DAYS_BEFORE)
+            {
+                remind(&task, deadline - now).await?;
+            }
+            Result::<_, warp::Rejection>::Ok(())
+        };
+        if let Err(e) = r.await {
+            log(format!("Could not expire task {} due to {:?}", task.id, e));
+        }
+    }
+    Ok(())
+}
+
+async fn start_expiry(task: &Task) -> Result<(), warp::Rejection> {
+    maybe_modify(
+        TASK_COLLECTION,
+        [&task.office_id],
+        &task.id,
+        |mut task: Task| {
+            if task.published_date.is_some() {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            task.published_date = Some(Utc::now());
+            task.modified = Utc::now();
+            Ok(ModifyReturn::Replace(task))
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn remind(task: &Task, left: Duration) -> Result<(), warp::Rejection> {
+    // NOTE: The flag is set first so that several instances of the job do not all remind
+    let marked = maybe_modify(
+        TASK_COLLECTION,
+        [&task.office_id],
+        &task.id,
+        |mut task: Task| {
+            if task.expiry_reminder_sent {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            task.expiry_reminder_sent = true;
+            task.modified = Utc::now();
+            Ok(ModifyReturn::Replace(task))
+        },
+    )
+    .await?;
+    if let ModifyReturn::DontReplace(_) = marked {
+        return Ok(());
+    }
+
+    let days = std::cmp::max(left.num_days(), 1);
+    notify_user(
+        &task.user_id,
+        &format!(
+            "Ditt jobb \"{}\" stängs om {} dagar om du inte har antagit något bud. Förläng jobbet om du vill ha fler bud, eller stäng det om du inte längre behöver hjälp.",
+            task.title, days
+        ),
+    )
+    .await;
+    Ok(())
+}
+
+async fn expire(task: &Task) -> Result<(), warp::Rejection> {
+    let expired = maybe_modify(
+        TASK_COLLECTION,
+        [&task.office_id],
+        &task.id,
+        |mut task: Task| {
+            // NOTE: A bid may have been accepted since the task was queried
+            if task.state() != TaskState::Published || task.accepted_bid.is_some() {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            match task.transition(
+                TaskState::Expired,
+                TaskActor::System,
+                None,
+                Some("No bid was accepted in time"),
+            ) {
+                Ok(_) => Ok(ModifyReturn::Replace(task)),
+                Err(e) => {
+                    log(format!("Could not expire task {} due to {}", task.id, e));
+                    Ok(ModifyReturn::DontReplace(task))
+                }
+            }
+        },
+    )
+    .await?;
+    if let ModifyReturn::DontReplace(_) = expired {
+        return Ok(());
+    }
+
+    notify_user(
+        &task.user_id,
+        &format!(
+            "Ditt jobb \"{}\" har stängts eftersom inget bud antogs. Du kan publicera det igen om du fortfarande behöver hjälp.",
+            task.title
+        ),
+    )
+    .await;
+
+    // Let the craftsmen that are waiting on an answer know that there will not be one
+    let q = format!(
+        r#"SELECT * FROM {} b WHERE b.taskId = "{}" AND b.isCancelled = false AND (NOT IS_DEFINED(b.deleted) OR b.deleted = false) AND (NOT IS_DEFINED(b.withdrawal) OR IS_NULL(b.withdrawal))"#,
+        BID_COLLECTION, task.id
+    );
+    let bids: Vec<Bid> = query(BID_COLLECTION, [&task.office_id], q, -1).await?;
+    for bid in bids {
+        notify_user(
+            &bid.craftsman_id,
+            &format!(
+                "Jobbet \"{}\" som du har lagt bud på har stängts utan att något bud antogs.",
+                task.title
+            ),
+        )
+        .await;
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use crate::models::{LedgerAccount, LedgerEntry};
+use rust_decimal::{prelude::Zero, Decimal};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountBalance {
+    pub account: LedgerAccount,
+
+    pub debit: Decimal,
+
+    pub credit: Decimal,
+
+    // Debit minus credit
+    pub balance: Decimal,
+}
+
+// Sums the given entries into one balance per account. Accounts without any entries are still
+// included with a zero balance.
+pub fn balances(entries: &[LedgerEntry]) -> Vec<AccountBalance> {
+    LedgerAccount::all()
+        .iter()
+        .map(|account| {
+            let mut debit = Decimal::zero();
+            let mut credit = Decimal::zero();
+            for entry in entries {
+                if entry.debit == *account {
+                    debit += entry.amount;
+                }
+                if entry.credit == *account {
+                    credit += entry.amount;
+                }
+            }
+            AccountBalance {
+                account: *account,
+                debit,
+                credit,
+                balance: debit - credit,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,19 @@
+// The ledger is an append-only double-entry book of every money movement tied to a payment. Each
+// entry debits one account and credits another by the same amount, so the balances of all
+// accounts always sum to zero. Entries that can not be booked when the money moves are queued and
+// booked later by a job.
+
+mod record;
+pub use record::{
+    brokerage, record_finalized, record_paid_to_craftsman, record_paid_to_escrow,
+    record_refund_finished, record_refund_initialized,
+};
+
+mod queue;
+pub use queue::{book_queued, has_queued};
+
+mod balance;
+pub use balance::{balances, AccountBalance};
+
+mod sie;
+pub use sie::sie4;
@@ -0,0 +1,70 @@
+use crate::models::LedgerEntry;
+use crate::util::log;
+use crate::{LEDGER_COLLECTION, LEDGER_QUEUE_COLLECTION};
+use chrono::{DateTime, SecondsFormat, Utc};
+use cosmos_utils::{delete, insert, query, query_crosspartition, upsert, CosmosErrorKind};
+use warp::reject;
+
+// Entries that could not be booked when the money moved. They wait in the queue until the ledger
+// job books them, with the ids they would have had so that an entry which did reach the ledger
+// only hits a conflict.
+pub(super) async fn enqueue(entries: &[LedgerEntry]) -> Result<(), warp::Rejection> {
+    for entry in entries {
+        upsert(LEDGER_QUEUE_COLLECTION, [&entry.office_id], entry, None).await?;
+    }
+    Ok(())
+}
+
+// The entries of a payment that are still waiting to be booked
+pub(super) async fn queued(
+    office_id: &str,
+    payment_id: &str,
+) -> Result<Vec<LedgerEntry>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} l WHERE l.paymentId = "{}""#,
+        LEDGER_QUEUE_COLLECTION, payment_id
+    );
+    let entries: Vec<LedgerEntry> = query(LEDGER_QUEUE_COLLECTION, [office_id], q, -1).await?;
+    Ok(entries)
+}
+
+// Whether any entry booked before the given time is still waiting in the queue. Once it is booked
+// it lands among the entries before it, which moves the verification numbers of an export.
+pub async fn has_queued(office_id: &str, before: DateTime<Utc>) -> Result<bool, warp::Rejection> {
+    let q = format!(
+        r#"SELECT VALUE l.id FROM {} l WHERE l.booked < "{}""#,
+        LEDGER_QUEUE_COLLECTION,
+        before.to_rfc3339_opts(SecondsFormat::Secs, true)
+    );
+    let ids: Vec<String> = query(LEDGER_QUEUE_COLLECTION, [office_id], q, -1).await?;
+    Ok(!ids.is_empty())
+}
+
+// Books every queued entry and removes it from the queue. Safe to run on several instances at
+// once since booking an entry twice is a conflict.
+pub async fn book_queued() -> Result<(), warp::Rejection> {
+    let q = format!(r#"SELECT * FROM {} l"#, LEDGER_QUEUE_COLLECTION);
+    let entries: Vec<LedgerEntry> =
+        query_crosspartition(LEDGER_QUEUE_COLLECTION, [()], q, -1, true).await?;
+    for entry in entries {
+        if let Err(e) = insert(LEDGER_COLLECTION, [&entry.office_id], &entry, None).await {
+            match e.kind {
+                CosmosErrorKind::Conflict => (),
+                _ => {
+                    log(format!(
+                        "Could not book queued ledger entry {} due to {}",
+                        entry.id, e
+                    ));
+                    continue;
+                }
+            }
+        }
+        if let Err(e) = delete(LEDGER_QUEUE_COLLECTION, [&entry.office_id], &entry.id, None).await {
+            match e.kind {
+                CosmosErrorKind::NotFound => (),
+                _ => return Err(reject::custom(e)),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use super::queue;
+use crate::fault::Fault;
+use crate::models::{
+    LedgerAccount, LedgerEntry, LedgerTransition, Payment, PaymentMethod, PaymentState,
+};
+use crate::tax::{self, Costs};
+use crate::util::log;
+use crate::LEDGER_COLLECTION;
+use chrono::{NaiveDate, Utc};
+use cosmos_utils::{insert, query, CosmosErrorKind};
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+use warp::reject;
+
+// Returns the brokerage fee excluding vat and the vat on the brokerage fee for a bid, or for
+// whatever part of it a payment covers, at the vat of the given date
+pub fn brokerage(
+    costs: &Costs,
+    brokerage_percentage: Decimal,
+    date: NaiveDate,
+) -> (Decimal, Decimal) {
+    let fee_ex_vat = ((costs.material_cost + costs.labour_cost) * brokerage_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    // NOTE: The vat is on the fee itself. It does not follow the vat of the work, which the
+    // customer reports under reverse charge and which is reduced by a ROT or RUT deduction.
+    let fee_vat = (fee_ex_vat * tax::rules_at(date).vat_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    (fee_ex_vat, fee_vat)
+}
+
+// The account the customer's money is actually held in, which depends on how they paid
+fn cash_account(payment: &Payment) -> LedgerAccount {
+    match payment.payment_method {
+        PaymentMethod::Swish => LedgerAccount::SwishAccount,
+        PaymentMethod::Invoice => LedgerAccount::BankAccount,
+    }
+}
+
+// Writes one entry per line. The entry ids are derived from the payment and the reference of the
+// transition, so a transition which is retried will hit a conflict instead of booking the same
+// money twice. The money has already moved when this is called, so entries that can not be
+// written are queued for the ledger job instead of being lost.
+async fn book(
+    payment: &Payment,
+    transition: LedgerTransition,
+    reference: &str,
+    lines: &[(LedgerAccount, LedgerAccount, Decimal)],
+) -> Result<(), warp::Rejection> {
+    let booked = Utc::now();
+    let mut entries = Vec::new();
+    for (i, (debit, credit, amount)) in lines.iter().enumerate() {
+        if amount.is_zero() {
+            continue;
+        }
+        entries.push(LedgerEntry {
+            id: format!("{}-{}-{}", payment.id, reference, i),
+            office_id: payment.office_id.clone(),
+            task_id: payment.task_id.clone(),
+            bid_id: payment.bid_id.clone(),
+            payment_id: payment.id.clone(),
+            craftsman_id: payment.craftsman_id.clone(),
+            transition,
+            debit: *debit,
+            credit: *credit,
+            amount: *amount,
+            currency: payment.currency.clone(),
+            booked,
+        });
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        if let Err(e) = insert(LEDGER_COLLECTION, [&entry.office_id], entry, None).await {
+            match e.kind {
+                CosmosErrorKind::Conflict => (),
+                _ => {
+                    if let Err(queue_error) = queue::enqueue(&entries[i..]).await {
+                        log(format!(
+                            "Could not queue the {:?} entries of payment {} due to {:?}",
+                            transition, payment.id, queue_error
+                        ));
+                        return Err(reject::custom(e));
+                    }
+                    log(format!(
+                        "Queued the {:?} entries of payment {} since they could not be booked due to {}",
+                        transition, payment.id, e
+                    ));
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// The customer has paid into the swish intermediate account or our bank account
+pub async fn record_paid_to_escrow(payment: &Payment) -> Result<(), warp::Rejection> {
+    book(
+        payment,
+        LedgerTransition::PaidToEscrow,
+        "escrow",
+        &[
+            (cash_account(payment), LedgerAccount::Escrow, payment.amount),
+            // NOTE: Toolit puts the discount into escrow so that the craftsman is paid in full
+            (
+                LedgerAccount::Discounts,
+                LedgerAccount::Escrow,
+                payment.discount_amount(),
+            ),
+        ],
+    )
+    .await
+}
+
+// The task is finished and the escrowed money is split into brokerage and what is owed to the
+// craftsman
+pub async fn record_finalized(
+    payment: &Payment,
+    costs: &Costs,
+    brokerage_percentage: Decimal,
+) -> Result<(), warp::Rejection> {
+    // NOTE: The brokerage is taken from what the payment covers, which for a milestone is only
+    // its share of the bid
+    let (fee_ex_vat, fee_vat) = brokerage(costs, brokerage_percentage, payment.tax_date());
+    // NOTE: Money given back to the customer on a partial refund is booked as a refund
+    let payout = payment.gross_amount() - payment.refunded_amount - fee_ex_vat - fee_vat;
+    book(
+        payment,
+        LedgerTransition::Finalized,
+        "finalized",
+        &[
+            (
+                LedgerAccount::Escrow,
+                LedgerAccount::BrokerageRevenue,
+                fee_ex_vat,
+            ),
+            (LedgerAccount::Escrow, LedgerAccount::BrokerageVat, fee_vat),
+            (
+                LedgerAccount::Escrow,
+                LedgerAccount::CraftsmanPayable,
+                payout,
+            ),
+        ],
+    )
+    .await
+}
+
+// The money owed to the craftsman has left the account the customer paid into
+pub async fn record_paid_to_craftsman(payment: &Payment) -> Result<(), warp::Rejection> {
+    // NOTE: The payable amount was fixed when the payment was finalized so we read it back from
+    // the ledger, or from the queue if it has not been booked yet, instead of recalculating the
+    // brokerage.
+    let q = format!(
+        r#"SELECT * FROM {} l WHERE l.paymentId = "{}""#,
+        LEDGER_COLLECTION, payment.id
+    );
+    let mut entries: Vec<LedgerEntry> =
+        query(LEDGER_COLLECTION, [&payment.office_id], q, -1).await?;
+    for entry in queue::queued(&payment.office_id, &payment.id).await? {
+        if !entries.iter().any(|e| e.id == entry.id) {
+            entries.push(entry);
+        }
+    }
+    let payable: Vec<&LedgerEntry> = entries
+        .iter()
+        .filter(|e| e.transition == LedgerTransition::Finalized)
+        .filter(|e| e.credit == LedgerAccount::CraftsmanPayable)
+        .collect();
+    if payable.is_empty() {
+        return Err(reject::custom(Fault::IllegalState(format!(
+            "Payment {} has not been booked as finalized, its payout can not be booked",
+            payment.id
+        ))));
+    }
+    let payable = payable
+        .iter()
+        .fold(Decimal::zero(), |sum, entry| sum + entry.amount);
+    book(
+        payment,
+        LedgerTransition::PaidToCraftsman,
+        "payout",
+        &[(
+            LedgerAccount::CraftsmanPayable,
+            cash_account(payment),
+            payable,
+        )],
+    )
+    .await
+}
+
+// A refund has been requested but not yet confirmed
+pub async fn record_refund_initialized(
+    payment: &Payment,
+    refund_id: &str,
+    amount: Decimal,
+) -> Result<(), warp::Rejection> {
+    book(
+        payment,
+        LedgerTransition::RefundInitialized,
+        &format!("refund-{}-init", refund_id),
+        &[(LedgerAccount::Escrow, LedgerAccount::Refunds, amount)],
+    )
+    .await
+}
+
+// The customer has either been paid back or the refund failed, in which case the money goes
+// back into escrow
+pub async fn record_refund_finished(
+    payment: &Payment,
+    refund_id: &str,
+    amount: Decimal,
+    refunded: bool,
+) -> Result<(), warp::Rejection> {
+    if refunded {
+        // NOTE: The customer only gets back what they paid, the discount Toolit put into escrow
+        // goes back to Toolit when the whole payment is refunded
+        let discount = match payment.payment_state {
+            PaymentState::Refunded => payment.discount_amount(),
+            _ => Decimal::zero(),
+        };
+        book(
+            payment,
+            LedgerTransition::Refunded,
+            &format!("refund-{}-finish", refund_id),
+            &[
+                (LedgerAccount::Refunds, cash_account(payment), amount),
+                (LedgerAccount::Escrow, LedgerAccount::Discounts, discount),
+            ],
+        )
+        .await
+    } else {
+        book(
+            payment,
+            LedgerTransition::RefundFailed,
+            &format!("refund-{}-finish", refund_id),
+            &[(LedgerAccount::Refunds, LedgerAccount::Escrow, amount)],
+        )
+        .await
+    }
+}
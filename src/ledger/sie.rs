@@ -0,0 +1,203 @@
+use crate::models::{AccountKind, Bid, LedgerAccount, LedgerEntry, LedgerTransition, Office};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::{Europe::Stockholm, Tz};
+use rust_decimal::{prelude::Zero, Decimal};
+use std::collections::HashMap;
+
+// The verification series used for everything exported from Toolit
+const SERIES: &str = "T";
+
+// Builds a SIE4 file with all ledger entries booked between `from` and `to` (inclusive) as
+// verifications. Both dates have to be in the same fiscal year of the office, the balances and
+// results are those of that fiscal year up to `to`. The verifications are numbered in the order
+// they were booked in the fiscal year, so overlapping exports agree on the numbers as long as no
+// entry is booked with an earlier date after the export, see ledger::has_queued. The entries booked before the fiscal year are
+// only used for the opening balances, so `entries` should contain everything up to and including
+// `to`. The returned bytes are encoded in PC8 (code page 437) as required by the format.
+pub fn sie4(
+    office: &Office,
+    from: NaiveDate,
+    to: NaiveDate,
+    entries: &[LedgerEntry],
+    bids: &HashMap<String, Bid>,
+) -> Vec<u8> {
+    let tz: Tz = Stockholm;
+    let date = |entry: &LedgerEntry| entry.booked.with_timezone(&tz).naive_local().date();
+    let (year_start, year_end) = office.chart_of_accounts.fiscal_year(to);
+
+    let mut opening: HashMap<LedgerAccount, Decimal> = HashMap::new();
+    let mut period: HashMap<LedgerAccount, Decimal> = HashMap::new();
+    // Every call to the ledger books its lines with the same timestamp, which lets us group the
+    // lines back into the verification they belong to.
+    let mut verifications: Vec<Vec<&LedgerEntry>> = Vec::new();
+    let mut sorted: Vec<&LedgerEntry> = entries.iter().filter(|e| date(e) <= to).collect();
+    sorted.sort_by(|a, b| a.booked.cmp(&b.booked).then(a.id.cmp(&b.id)));
+    for entry in sorted {
+        let balances = if date(entry) < year_start {
+            &mut opening
+        } else {
+            &mut period
+        };
+        *balances.entry(entry.debit).or_insert(Decimal::zero()) += entry.amount;
+        *balances.entry(entry.credit).or_insert(Decimal::zero()) -= entry.amount;
+
+        // NOTE: Every verification of the fiscal year is numbered, also the ones before `from`, so
+        // that a verification gets the same number in every export of the year
+        if date(entry) < year_start {
+            continue;
+        }
+        match verifications.last_mut() {
+            Some(v)
+                if v[0].payment_id == entry.payment_id
+                    && v[0].transition == entry.transition
+                    && v[0].booked == entry.booked =>
+            {
+                v.push(entry)
+            }
+            _ => verifications.push(vec![entry]),
+        }
+    }
+
+    let office_name = office.name.get(0).map(|n| n.s.clone()).unwrap_or_default();
+    let mut out = String::new();
+    out.push_str("#FLAGGA 0\r\n");
+    out.push_str("#FORMAT PC8\r\n");
+    out.push_str("#SIETYP 4\r\n");
+    out.push_str("#PROGRAM \"Toolit\" 1.0\r\n");
+    out.push_str(&format!(
+        "#GEN {}\r\n",
+        Utc::now().with_timezone(&tz).format("%Y%m%d")
+    ));
+    out.push_str(&format!("#FNAMN {}\r\n", quote(&office_name)));
+    out.push_str(&format!(
+        "#RAR 0 {} {}\r\n",
+        year_start.format("%Y%m%d"),
+        year_end.format("%Y%m%d")
+    ));
+    out.push_str("#VALUTA SEK\r\n");
+
+    for account in LedgerAccount::all().iter() {
+        let chart = office.chart_of_accounts.get(*account);
+        out.push_str(&format!(
+            "#KONTO {} {}\r\n",
+            chart.number,
+            quote(&chart.name)
+        ));
+        out.push_str(&format!(
+            "#KTYP {} {}\r\n",
+            chart.number,
+            chart.kind.sie_code()
+        ));
+    }
+
+    for account in LedgerAccount::all().iter() {
+        let chart = office.chart_of_accounts.get(*account);
+        let ib = opening.get(account).copied().unwrap_or(Decimal::zero());
+        let movement = period.get(account).copied().unwrap_or(Decimal::zero());
+        match chart.kind {
+            AccountKind::Asset | AccountKind::Liability => {
+                out.push_str(&format!("#IB 0 {} {}\r\n", chart.number, amount(ib)));
+                out.push_str(&format!(
+                    "#UB 0 {} {}\r\n",
+                    chart.number,
+                    amount(ib + movement)
+                ));
+            }
+            AccountKind::Income | AccountKind::Cost => {
+                out.push_str(&format!("#RES 0 {} {}\r\n", chart.number, amount(movement)));
+            }
+        }
+    }
+
+    for (i, verification) in verifications.iter().enumerate() {
+        let first = verification[0];
+        if date(first) < from {
+            continue;
+        }
+        out.push_str(&format!(
+            "#VER {} {} {} {}\r\n{{\r\n",
+            SERIES,
+            i + 1,
+            date(first).format("%Y%m%d"),
+            quote(&text(first, bids.get(&first.bid_id)))
+        ));
+        for entry in verification {
+            let debit = office.chart_of_accounts.get(entry.debit);
+            let credit = office.chart_of_accounts.get(entry.credit);
+            out.push_str(&format!(
+                "   #TRANS {} {{}} {}\r\n",
+                debit.number,
+                amount(entry.amount)
+            ));
+            out.push_str(&format!(
+                "   #TRANS {} {{}} {}\r\n",
+                credit.number,
+                amount(-entry.amount)
+            ));
+        }
+        out.push_str("}\r\n");
+    }
+
+    pc8(&out)
+}
+
+// The verification text, the bid breakdown is added for finalized payments since those are the
+// ones the brokerage is calculated from
+fn text(entry: &LedgerEntry, bid: Option<&Bid>) -> String {
+    let reference = entry.payment_id.split('-').next().unwrap_or_default();
+    match (entry.transition, bid) {
+        (LedgerTransition::PaidToEscrow, _) => format!("Betalning till escrow {}", reference),
+        (LedgerTransition::Finalized, Some(bid)) => format!(
+            "Slutförd betalning {} arbete {} material {} moms {}",
+            reference,
+            amount(bid.labour_cost),
+            amount(bid.material_cost),
+            amount(bid.vat)
+        ),
+        (LedgerTransition::Finalized, None) => format!("Slutförd betalning {}", reference),
+        (LedgerTransition::PaidToCraftsman, _) => {
+            format!("Utbetalning till hantverkare {}", reference)
+        }
+        (LedgerTransition::RefundInitialized, _) => format!("Återbetalning påbörjad {}", reference),
+        (LedgerTransition::Refunded, _) => format!("Återbetalning {}", reference),
+        (LedgerTransition::RefundFailed, _) => format!("Misslyckad återbetalning {}", reference),
+    }
+}
+
+fn amount(d: Decimal) -> String {
+    format!("{:.2}", d)
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Encodes the string as code page 437. Characters that do not exist in the code page are replaced
+// with a question mark.
+fn pc8(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| match c {
+            c if c.is_ascii() => c as u8,
+            'Ç' => 0x80,
+            'ü' => 0x81,
+            'é' => 0x82,
+            'â' => 0x83,
+            'ä' => 0x84,
+            'à' => 0x85,
+            'å' => 0x86,
+            'ç' => 0x87,
+            'ê' => 0x88,
+            'ë' => 0x89,
+            'è' => 0x8A,
+            'Ä' => 0x8E,
+            'Å' => 0x8F,
+            'É' => 0x90,
+            'æ' => 0x91,
+            'Æ' => 0x92,
+            'ö' => 0x94,
+            'Ö' => 0x99,
+            'Ü' => 0x9A,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -6,12 +6,26 @@ use lazy_static::lazy_static;
 use rust_decimal::Decimal;
 use std::time::Duration;
 use warp::{http::Method, Filter};
+mod alerts;
 mod api;
+mod cancel;
+mod chat;
 mod models;
 use models::*;
 mod fault;
 mod filters;
+mod finish;
+mod geo;
+mod dispute;
+mod jobs;
+mod ledger;
+mod payment;
+mod pdf;
 mod push;
+mod receipt;
+mod rot_rut;
+mod statement;
+mod tax;
 mod test_utils;
 mod util;
 #[macro_use]
@@ -36,6 +50,7 @@ lazy_static! {
         key: "notification-hub-key".to_string(),
     };
     static ref CERTIFICATE_STORAGE_CONTAINER: String = std::env::var("CERTIFICATE_STORAGE_CONTAINER").unwrap();
+    static ref RECEIPT_STORAGE_CONTAINER: String = std::env::var("RECEIPT_STORAGE_CONTAINER").unwrap();
 
     // TODO(Jonathan): Fill in certificate information
     static ref BANKID_CERT_PATH: String = String::from(
@@ -51,6 +66,9 @@ lazy_static! {
     static ref SWISH_CERT_PATH: String = std::env::var("SWISH_CERT_PATH").unwrap();
     static ref SWISH_CERT_PASS: String = std::env::var("SWISH_CERT_PASS").unwrap();
     static ref SWISH_INTERMEDIATE_ACCOUNT_NUMBER: String = String::from("1234914271");
+    static ref INVOICE_BANKGIRO: String = String::from("5555-5555");
+    // The number of minutes a swish payment can wait for the customer before it is cancelled
+    static ref PAYMENT_TIMEOUT_MINUTES: i64 = 15;
     static ref BASE_CALLBACK_URL: String = String::from("https://toolit-api-play.azurewebsites.net");
 
     static ref APPLICATION_INSIGHTS_INSTRUMENTATION_KEY: String =
@@ -85,6 +103,7 @@ lazy_static! {
         key: std::env::var("PUSH_NOTIFICATION_HUB_KEY").unwrap(),
     };
     static ref CERTIFICATE_STORAGE_CONTAINER: String = std::env::var("CERTIFICATE_STORAGE_CONTAINER").unwrap();
+    static ref RECEIPT_STORAGE_CONTAINER: String = std::env::var("RECEIPT_STORAGE_CONTAINER").unwrap();
 
     // TODO(Jonathan): Fill in certificate information
     static ref BANKID_CERT_PATH: String = std::env::var("BANKID_CERT_PATH").unwrap();
@@ -96,6 +115,12 @@ lazy_static! {
     static ref SWISH_CERT_PATH: String = std::env::var("SWISH_CERT_PATH").unwrap();
     static ref SWISH_CERT_PASS: String = std::env::var("SWISH_CERT_PASS").unwrap();
     static ref SWISH_INTERMEDIATE_ACCOUNT_NUMBER: String = std::env::var("SWISH_INTERMEDIATE_ACCOUNT_NUMBER").unwrap();
+    static ref INVOICE_BANKGIRO: String = std::env::var("INVOICE_BANKGIRO").unwrap();
+    // The number of minutes a swish payment can wait for the customer before it is cancelled
+    static ref PAYMENT_TIMEOUT_MINUTES: i64 = std::env::var("PAYMENT_TIMEOUT_MINUTES")
+        .ok()
+        .and_then(|m| m.parse().ok())
+        .unwrap_or(15);
     static ref BASE_CALLBACK_URL: String = std::env::var("BASE_CALLBACK_URL").unwrap();
 
     static ref APPLICATION_INSIGHTS_INSTRUMENTATION_KEY: String =
@@ -142,6 +167,16 @@ const BID_COLLECTION: &str = "bids";
 const AUTH_EMAIL_COLLECTION: &str = "auth_emails";
 const AUTH_NID_COLLECTION: &str = "auth_nids";
 const AD_COLLECTION: &str = "ads";
+const LEDGER_COLLECTION: &str = "ledger_entries";
+const LEDGER_QUEUE_COLLECTION: &str = "ledger_queue";
+const TAX_DEDUCTION_COLLECTION: &str = "tax_deductions";
+const CHANGE_ORDER_COLLECTION: &str = "change_orders";
+const DISPUTE_COLLECTION: &str = "disputes";
+const CAMPAIGN_COLLECTION: &str = "campaigns";
+const REFERRAL_CODE_COLLECTION: &str = "referral_codes";
+const SAVED_SEARCH_COLLECTION: &str = "saved_searches";
+const CANCELLATION_COLLECTION: &str = "cancellations";
+const PRICE_ESTIMATE_COLLECTION: &str = "price_estimates";
 
 fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     let chats = warp::path("chats");
@@ -155,8 +190,15 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
     let payments = warp::path("payments");
     let tasks = warp::path("tasks");
     let bids = warp::path("bids");
+    let milestones = warp::path("milestones");
+    let change_orders = warp::path("change_orders");
+    let disputes = warp::path("disputes");
+    let campaigns = warp::path("campaigns");
+    let saved_searches = warp::path("saved_searches");
+    let statements = warp::path("statements");
     let password = warp::path("password");
     let ads = warp::path("ads");
+    let ledger = warp::path("ledger");
 
     let cors = warp::cors()
         .allow_any_origin()
@@ -188,6 +230,15 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::user_get));
+    let user_deductions_get = maybe_box!(users
+        .and(warp::path::param())
+        .and(warp::path("deductions"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::user_deductions_get));
     let user_delete = maybe_box!(users
         .and(warp::path::param())
         .and(warp::path::end())
@@ -376,6 +427,15 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::craftsman_post));
+    let craftsman_area_migrate = maybe_box!(offices
+        .and(warp::path::param())
+        .and(craftsmen)
+        .and(warp::path("service_area_migration"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::craftsman_area_migrate));
     let craft_apply = maybe_box!(offices
         .and(warp::path::param())
         .and(craftsmen)
@@ -618,6 +678,237 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::payment_get));
+    let payment_cancel = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(bids)
+        .and(warp::path::param())
+        .and(payments)
+        .and(warp::path::param())
+        .and(warp::path("cancel"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::payment_cancel));
+    let payment_reconcile = maybe_box!(offices
+        .and(warp::path::param())
+        .and(payments)
+        .and(warp::path("reconcile"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::payment_reconcile));
+    let payment_receipt_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(bids)
+        .and(warp::path::param())
+        .and(payments)
+        .and(warp::path::param())
+        .and(warp::path("receipt"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::payment_receipt_get));
+    let ledger_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(ledger)
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::ledger_get));
+    let craftsman_ledger_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(craftsmen)
+        .and(warp::path::param())
+        .and(ledger)
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::craftsman_ledger_get));
+    let rot_claim_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(craftsmen)
+        .and(warp::path::param())
+        .and(warp::path("rot"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::rot_claim_get));
+    let craftsman_statement_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(craftsmen)
+        .and(warp::path::param())
+        .and(warp::path("statement"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::craftsman_statement_get));
+    let craftsman_statement_export = maybe_box!(offices
+        .and(warp::path::param())
+        .and(craftsmen)
+        .and(warp::path::param())
+        .and(warp::path("statement"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::craftsman_statement_export));
+    let sie_export = maybe_box!(offices
+        .and(warp::path::param())
+        .and(ledger)
+        .and(warp::path("sie"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::sie_export));
+    let office_chart_of_accounts_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(ledger)
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::office_chart_of_accounts_put));
+    let office_task_expiry_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(warp::path("task_expiry"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::office_task_expiry_put));
+    let office_auto_finish_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(warp::path("auto_finish"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::office_auto_finish_put));
+    let office_brokerage_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(warp::path("brokerage"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::office_brokerage_put));
+    let campaign_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(campaigns)
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::campaign_post));
+    let campaign_delete = maybe_box!(offices
+        .and(warp::path::param())
+        .and(campaigns)
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::campaign_delete));
+    let referral_code_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(campaigns)
+        .and(warp::path::param())
+        .and(warp::path("referral_code"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::referral_code_post));
+    let saved_searches_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(saved_searches)
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::saved_searches_get));
+    let saved_search_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(saved_searches)
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::saved_search_post));
+    let saved_search_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(saved_searches)
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::saved_search_put));
+    let saved_search_delete = maybe_box!(offices
+        .and(warp::path::param())
+        .and(saved_searches)
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::saved_search_delete));
+    let task_state_migrate = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path("state_migration"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::task_state_migrate));
+    let task_search = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::task_search));
+    let price_estimate_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path("price_estimate"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::price_estimate_post));
     let task_post = maybe_box!(offices
         .and(warp::path::param())
         .and(tasks)
@@ -672,6 +963,27 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::task_status_put));
+    let task_cancel = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(warp::path("cancel"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::task_cancel));
+    let task_extend = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(warp::path("extend"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::task_extend));
     let task_delete = maybe_box!(offices
         .and(warp::path::param())
         .and(tasks)
@@ -734,6 +1046,16 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::bid_delete));
+    let bid_comparison_get = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(warp::path("bid_comparison"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::bid_comparison_get));
     let bid_get = maybe_box!(offices
         .and(warp::path::param())
         .and(tasks)
@@ -754,9 +1076,141 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(warp::path("accept"))
         .and(warp::path::end())
         .and(warp::put())
+        .and(filters::with_optional_body())
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::bid_accept));
+    let milestone_pay = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(bids)
+        .and(warp::path::param())
+        .and(milestones)
+        .and(warp::path::param())
+        .and(warp::path("pay"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::milestone_pay));
+    let milestone_approve = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(bids)
+        .and(warp::path::param())
+        .and(milestones)
+        .and(warp::path::param())
+        .and(warp::path("approve"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::milestone_approve));
+    let change_order_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(change_orders)
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::change_order_post));
+    let change_order_accept = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(change_orders)
+        .and(warp::path::param())
+        .and(warp::path("accept"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::change_order_accept));
+    let change_order_reject = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(change_orders)
+        .and(warp::path::param())
+        .and(warp::path("reject"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::change_order_reject));
+    let dispute_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(disputes)
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::dispute_post));
+    let dispute_statement_post = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(disputes)
+        .and(warp::path::param())
+        .and(statements)
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::dispute_statement_post));
+    let dispute_image_put = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(disputes)
+        .and(warp::path::param())
+        .and(statements)
+        .and(warp::path::param())
+        .and(warp::path("image"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and(warp::body::content_length_limit(1024 * 1000 * 16)) // 16 mb.
+        .and(warp::filters::multipart::form().max_length(1024 * 1000 * 16)) // 16 mb.
+        .and_then(api::dispute_image_put));
+    let dispute_resolve = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(disputes)
+        .and(warp::path::param())
+        .and(warp::path("resolve"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::dispute_resolve));
+    let bid_withdraw = maybe_box!(offices
+        .and(warp::path::param())
+        .and(tasks)
+        .and(warp::path::param())
+        .and(bids)
+        .and(warp::path::param())
+        .and(warp::path("withdraw"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(warp::body::json())
+        .and(filters::with_token())
+        .and(filters::with_version())
+        .and_then(api::bid_withdraw));
     let bid_cancel = maybe_box!(offices
         .and(warp::path::param())
         .and(tasks)
@@ -766,6 +1220,7 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .and(warp::path("cancel"))
         .and(warp::path::end())
         .and(warp::put())
+        .and(filters::with_optional_body())
         .and(filters::with_token())
         .and(filters::with_version())
         .and_then(api::bid_cancel));
@@ -946,6 +1401,7 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
 
     let routes = maybe_box!(main
         .or(user_get)
+        .or(user_deductions_get)
         .or(user_delete)
         .or(user_put)
         .or(user_image_put)
@@ -968,6 +1424,7 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .or(craftsman_note_put)
         .or(craftsman_note_delete)
         .or(craftsman_post)
+        .or(craftsman_area_migrate)
         .or(craft_apply)
         .or(craft_approve)
         .or(craft_reject)
@@ -988,8 +1445,33 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .or(payment_mark_paid)
         .or(payment_delete)
         .or(payment_get)
+        .or(payment_receipt_get)
+        .or(payment_reconcile)
+        .or(payment_cancel)
+        .or(ledger_get)
+        .or(craftsman_ledger_get)
+        .or(rot_claim_get)
+        .or(craftsman_statement_get)
+        .or(craftsman_statement_export)
+        .or(sie_export)
+        .or(office_chart_of_accounts_put)
+        .or(office_auto_finish_put)
+        .or(office_task_expiry_put)
+        .or(office_brokerage_put)
+        .or(campaign_post)
+        .or(campaign_delete)
+        .or(referral_code_post)
+        .or(saved_searches_get)
+        .or(saved_search_post)
+        .or(saved_search_put)
+        .or(saved_search_delete)
+        .or(task_state_migrate)
+        .or(task_search)
+        .or(price_estimate_post)
         .or(task_post)
         .or(task_put)
+        .or(task_cancel)
+        .or(task_extend)
         .or(task_delete)
         .or(task_get)
         .or(task_image_put)
@@ -1000,8 +1482,19 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
         .or(bid_put)
         .or(bid_delete)
         .or(bid_get)
+        .or(bid_comparison_get)
         .or(bid_accept)
         .or(bid_cancel)
+        .or(bid_withdraw)
+        .or(milestone_pay)
+        .or(milestone_approve)
+        .or(change_order_post)
+        .or(change_order_accept)
+        .or(change_order_reject)
+        .or(dispute_post)
+        .or(dispute_statement_post)
+        .or(dispute_image_put)
+        .or(dispute_resolve)
         .or(message_post)
         .or(message_put)
         .or(message_image_put)
@@ -1025,6 +1518,7 @@ fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection>
 #[tokio::main]
 async fn main() {
     let routes = routes();
+    jobs::start();
 
     if cfg!(debug_assertions) {
         warp::serve(routes).run(([127, 0, 0, 1], 3030)).await
@@ -1,5 +1,7 @@
-use crate::util::{is_false, log};
-use chrono::{DateTime, Utc};
+use crate::models::{BidLine, BidLineKind, BidRevision, Brokerage, DeductionType, Milestone};
+use crate::tax::{verify_cost, CostError, Costs, TaxRules};
+use crate::util::{is_empty, is_false, is_none};
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
 use serde::{Deserialize, Serialize};
 
@@ -37,68 +39,208 @@ pub struct Bid {
     // Total added tax
     pub vat: Decimal,
 
+    // Set by the server when the customer is a business, the bid is then without vat
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub reverse_charge: bool,
+
+    // What the bid is made up of. When a bid has lines its labour and material costs are the sums
+    // of them, and only the lines marked as eligible get the deduction.
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub lines: Vec<BidLine>,
+
+    // Estimated hours of labour, required when the customer claims a ROT or RUT deduction
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub labour_hours: Option<Decimal>,
+
+    // The first day the craftsman can start the work
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub earliest_start: Option<NaiveDate>,
+
+    // Larger tasks are paid in stages, a bid without milestones is paid in full when accepted
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+
+    // Set by the server from the brokerage of the office when the bid is posted
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub brokerage: Option<Brokerage>,
+
     pub is_cancelled: bool,
 
+    // The bid can not be accepted after this, it is open until it is withdrawn if not set
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+
+    // Counts the times the craftsman has revised the bid, the earlier versions are kept in
+    // `revisions`
+    #[serde(default)]
+    pub revision: u32,
+
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub revisions: Vec<BidRevision>,
+
+    // Set by the server when the craftsman withdraws the bid
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub withdrawal: Option<BidWithdrawal>,
+
     pub modified: DateTime<Utc>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BidWithdrawal {
+    pub reason: String,
+    pub date: DateTime<Utc>,
+}
+
 impl Bid {
-    // NOTE: Make sure the final price is correct
-    // We round to 2 decimals at every step
-    pub fn cost_is_correct(&self, use_rut_root: bool) -> bool {
-        // MAGIC NUMBER: 25% for the vat tax
-        let vat_percentage = Decimal::new(25, 2);
-        // MAGIC NUMBER: 30% for the root deduction
-        let root_percentage: Decimal = if use_rut_root {
-            Decimal::new(3, 1)
-        } else {
-            Decimal::zero()
-        };
+    pub fn is_withdrawn(&self) -> bool {
+        self.withdrawal.is_some()
+    }
 
-        let labour_cost = self
-            .labour_cost
-            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
-        let material_cost = self
-            .material_cost
-            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
-
-        let labour_cost_vat = (labour_cost * vat_percentage)
-            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
-        let labour_cost_inc_vat = labour_cost + labour_cost_vat;
-
-        let material_cost_vat = (material_cost * vat_percentage)
-            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
-        let material_cost_inc_vat = material_cost + material_cost_vat;
-
-        let root_deduction = (labour_cost_inc_vat * root_percentage)
-            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
-        let vat = (labour_cost_vat - (labour_cost_vat * root_percentage)) + material_cost_vat;
-        let final_bid = labour_cost_inc_vat + material_cost_inc_vat - root_deduction;
-
-        if self.final_bid != final_bid {
-            log(format!(
-                "The final bid should have been {} but it was {}",
-                final_bid, self.final_bid
-            ));
-            return false;
+    pub fn is_expired(&self, at: DateTime<Utc>) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| at > valid_until)
+    }
+
+    // Sets the labour and material costs from the lines, a bid without lines keeps the costs it
+    // was given
+    pub fn apply_lines(&mut self) -> Result<(), String> {
+        if self.lines.is_empty() {
+            return Ok(());
         }
+        for line in self.lines.iter() {
+            line.verify()?;
+        }
+        self.labour_cost = self.lines_sum(|l| l.kind == BidLineKind::Labour);
+        self.material_cost = self.lines_sum(|l| l.kind == BidLineKind::Material);
+        Ok(())
+    }
 
-        if self.vat != vat {
-            log(format!(
-                "The vat should have been {} but it was {}",
-                vat, self.vat
-            ));
-            return false;
+    // The part of the labour cost that gets the deduction of the task
+    pub fn deductible_labour_cost(&self) -> Decimal {
+        if self.lines.is_empty() {
+            return self.labour_cost;
+        }
+        self.lines_sum(|l| l.kind == BidLineKind::Labour && l.rot_eligible)
+    }
+
+    fn lines_sum<F: Fn(&BidLine) -> bool>(&self, filter: F) -> Decimal {
+        self.lines
+            .iter()
+            .filter(|l| filter(l))
+            .fold(Decimal::zero(), |sum, l| sum + l.amount())
+    }
+
+    pub fn costs(&self) -> Costs {
+        Costs {
+            labour_cost: self.labour_cost,
+            deductible_labour_cost: self.deductible_labour_cost(),
+            material_cost: self.material_cost,
+            vat: self.vat,
+            root_deduction: self.root_deduction,
+            final_amount: self.final_bid,
+            reverse_charge: self.reverse_charge,
         }
+    }
+
+    // NOTE: Make sure the final price is correct given the tax rules, the deduction the task is
+    // eligible for, how much of the yearly deduction the customer has left and whether the
+    // customer reports the vat
+    pub fn verify_cost(
+        &self,
+        rules: &TaxRules,
+        deduction: Option<DeductionType>,
+        allowance: Option<Decimal>,
+        reverse_charge: bool,
+    ) -> Result<(), CostError> {
+        let costs = Costs {
+            reverse_charge,
+            ..self.costs()
+        };
+        verify_cost(&costs, rules, deduction, allowance)
+    }
 
-        if self.root_deduction != root_deduction {
-            log(format!(
-                "The root deduction should have been {} but it was {}",
-                root_deduction, self.root_deduction
+    // Makes sure the milestones, if any, cover exactly the final bid
+    pub fn verify_milestones(&self) -> Result<(), String> {
+        if self.milestones.is_empty() {
+            return Ok(());
+        }
+        let mut sum = Decimal::zero();
+        for (i, milestone) in self.milestones.iter().enumerate() {
+            if milestone.amount <= Decimal::zero() {
+                return Err(format!(
+                    "the milestone '{}' has to have a positive amount",
+                    milestone.description
+                ));
+            }
+            if self.milestones[..i]
+                .iter()
+                .any(|m| m.order == milestone.order)
+            {
+                return Err(format!(
+                    "more than one milestone has the order {}",
+                    milestone.order
+                ));
+            }
+            sum += milestone.amount;
+        }
+        if sum != self.final_bid {
+            return Err(format!(
+                "the milestones add up to {} but the final bid is {}",
+                sum, self.final_bid
             ));
-            return false;
         }
+        Ok(())
+    }
+
+    pub fn milestones_in_order(&self) -> Vec<&Milestone> {
+        let mut milestones: Vec<&Milestone> = self.milestones.iter().collect();
+        milestones.sort_by_key(|m| m.order);
+        milestones
+    }
+
+    pub fn milestone(&self, milestone_id: &str) -> Option<&Milestone> {
+        self.milestones.iter().find(|m| m.id == milestone_id)
+    }
 
-        true
+    pub fn first_milestone(&self) -> Option<&Milestone> {
+        self.milestones.iter().min_by_key(|m| m.order)
+    }
+
+    pub fn last_milestone(&self) -> Option<&Milestone> {
+        self.milestones.iter().max_by_key(|m| m.order)
+    }
+
+    // The part of an amount of the whole bid, e.g. the root deduction, that belongs to a
+    // milestone. It is calculated on the running total so that the parts of all the milestones
+    // add up to exactly the amount.
+    pub fn milestone_share(&self, milestone_id: &str, amount: Decimal) -> Decimal {
+        let milestone = match self.milestone(milestone_id) {
+            Some(m) => m,
+            None => return amount,
+        };
+        if self.final_bid.is_zero() {
+            return Decimal::zero();
+        }
+        let before = self
+            .milestones
+            .iter()
+            .filter(|m| m.order < milestone.order)
+            .fold(Decimal::zero(), |sum, m| sum + m.amount);
+        let through = before + milestone.amount;
+        let part = |paid: Decimal| {
+            (amount * paid / self.final_bid)
+                .round_dp_with_strategy(2, RoundingStrategy::BankersRounding)
+        };
+        part(through) - part(before)
     }
 }
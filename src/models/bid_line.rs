@@ -0,0 +1,66 @@
+use crate::util::is_false;
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BidLineKind {
+    Labour,
+    Material,
+}
+
+// A row of a bid, e.g. 12 m² of tiles or 8 hours of work. The labour and material costs of a bid
+// with lines are the sums of its lines.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BidLine {
+    pub description: String,
+
+    pub quantity: Decimal,
+
+    // E.g. "st", "m²" or "h"
+    pub unit: String,
+
+    // Price of one unit without the tax added
+    pub unit_price: Decimal,
+
+    pub kind: BidLineKind,
+
+    // Whether the labour on the line gets the ROT or RUT deduction of the task, only labour can
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub rot_eligible: bool,
+}
+
+impl BidLine {
+    // The price of the line without the tax added
+    pub fn amount(&self) -> Decimal {
+        (self.quantity * self.unit_price)
+            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding)
+    }
+
+    pub fn verify(&self) -> Result<(), String> {
+        if self.description.trim().is_empty() {
+            return Err(String::from("every line needs a description"));
+        }
+        if self.quantity <= Decimal::zero() {
+            return Err(format!(
+                "the line '{}' has to have a positive quantity",
+                self.description
+            ));
+        }
+        if self.unit_price < Decimal::zero() {
+            return Err(format!(
+                "the line '{}' can not have a negative price",
+                self.description
+            ));
+        }
+        if self.rot_eligible && self.kind != BidLineKind::Labour {
+            return Err(format!(
+                "the line '{}' is material, only labour gets ROT or RUT",
+                self.description
+            ));
+        }
+        Ok(())
+    }
+}
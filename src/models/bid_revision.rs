@@ -0,0 +1,68 @@
+use crate::models::{Bid, BidLine, Milestone};
+use crate::util::{is_empty, is_none};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// A version of a bid that has since been revised by the craftsman, kept so that the customer can
+// see what changed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BidRevision {
+    pub revision: u32,
+
+    pub bid_message: String,
+
+    pub final_bid: Decimal,
+
+    pub root_deduction: Decimal,
+
+    pub material_cost: Decimal,
+
+    pub labour_cost: Decimal,
+
+    pub vat: Decimal,
+
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub lines: Vec<BidLine>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub labour_hours: Option<Decimal>,
+
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub earliest_start: Option<NaiveDate>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+
+    // When this version was replaced by the next one
+    pub replaced: DateTime<Utc>,
+}
+
+impl BidRevision {
+    pub fn of(bid: &Bid, replaced: DateTime<Utc>) -> BidRevision {
+        BidRevision {
+            revision: bid.revision,
+            bid_message: bid.bid_message.clone(),
+            final_bid: bid.final_bid,
+            root_deduction: bid.root_deduction,
+            material_cost: bid.material_cost,
+            labour_cost: bid.labour_cost,
+            vat: bid.vat,
+            lines: bid.lines.clone(),
+            labour_hours: bid.labour_hours,
+            milestones: bid.milestones.clone(),
+            earliest_start: bid.earliest_start,
+            valid_until: bid.valid_until,
+            replaced,
+        }
+    }
+}
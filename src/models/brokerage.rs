@@ -0,0 +1,73 @@
+use crate::ledger;
+use crate::models::CraftType;
+use crate::tax::{self, Costs};
+use crate::util::is_empty;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// The brokerage of an office for one kind of craft, instead of the office default
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CraftBrokerage {
+    pub craft_type: CraftType,
+
+    pub percentage: Decimal,
+}
+
+// A lower brokerage during a campaign, for every craft or only for some of them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokeragePromotion {
+    pub description: String,
+
+    pub percentage: Decimal,
+
+    // The promotion is for every craft if this is empty
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub craft_types: Vec<CraftType>,
+
+    pub starts: DateTime<Utc>,
+
+    pub ends: DateTime<Utc>,
+}
+
+impl BrokeragePromotion {
+    pub fn applies_to(&self, crafts: &[CraftType], at: DateTime<Utc>) -> bool {
+        self.starts <= at
+            && at < self.ends
+            && (self.craft_types.is_empty() || crafts.iter().any(|c| self.craft_types.contains(c)))
+    }
+}
+
+// What Toolit takes of a bid or a payment, fixed when the bid is posted so that the craftsman
+// knows what they get paid out
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Brokerage {
+    pub percentage: Decimal,
+
+    // The brokerage fee excluding vat
+    pub fee_ex_vat: Decimal,
+
+    // Vat on the brokerage fee
+    pub fee_vat: Decimal,
+
+    // What is left for the craftsman
+    pub payout: Decimal,
+}
+
+impl Brokerage {
+    // NOTE: The fee is estimated at today's vat, the ledger books it at the vat of the day the
+    // payment is made
+    pub fn new(percentage: Decimal, costs: &Costs) -> Self {
+        let (fee_ex_vat, fee_vat) = ledger::brokerage(costs, percentage, tax::today());
+        Brokerage {
+            percentage,
+            fee_ex_vat,
+            fee_vat,
+            payout: costs.final_amount - fee_ex_vat - fee_vat,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::util::{is_false, is_none};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CampaignKind {
+    // Lowers what the customer pays
+    CustomerDiscount,
+    // Lowers the brokerage the craftsman pays
+    BrokerageDiscount,
+    // Every user gets a code of their own. The customer using it gets a discount and the user
+    // whose code it was gets credit towards their own tasks.
+    Referral,
+}
+
+// A marketing campaign of an office. The money a campaign costs is paid by Toolit, the craftsman
+// is paid as if the customer had paid the full price.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Campaign {
+    #[serde(default)]
+    pub id: String,
+
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub deleted: bool,
+
+    pub office_id: String,
+
+    pub name: String,
+
+    pub kind: CampaignKind,
+
+    // The code customers enter, referral campaigns use the codes of the users instead
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub code: Option<String>,
+
+    // A fixed amount taken off the price
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub discount_amount: Option<Decimal>,
+
+    // A fraction of the price taken off it, e.g. 0.1 for 10%
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub discount_percentage: Option<Decimal>,
+
+    // The brokerage of payments made with a brokerage discount
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub brokerage_percentage: Option<Decimal>,
+
+    // What the user whose referral code was used gets credited
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub referrer_credit: Option<Decimal>,
+
+    // The number of times the campaign can be used in total
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+
+    #[serde(default = "one")]
+    pub max_uses_per_customer: u32,
+
+    // The number of payments the campaign has been taken for, counted when the payment is made and
+    // given back if it is never paid
+    #[serde(default)]
+    pub uses: u32,
+
+    pub starts: DateTime<Utc>,
+
+    pub ends: DateTime<Utc>,
+
+    pub modified: DateTime<Utc>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl Campaign {
+    pub fn is_running(&self, at: DateTime<Utc>) -> bool {
+        !self.deleted && self.starts <= at && at < self.ends
+    }
+}
+
+// The personal code of a user in a referral campaign
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferralCode {
+    pub id: String,
+
+    pub office_id: String,
+
+    pub campaign_id: String,
+
+    pub user_id: String,
+
+    pub code: String,
+
+    pub modified: DateTime<Utc>,
+}
+
+// What was taken off the price of a payment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Discount {
+    pub customer_id: String,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub campaign_id: Option<String>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub code: Option<String>,
+
+    // The user to credit once the payment has reached escrow
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub referrer_id: Option<String>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub referrer_credit: Option<Decimal>,
+
+    // The part of the amount that was taken from the referral credit of the customer
+    #[serde(default)]
+    pub credit: Decimal,
+
+    // Everything taken off the price, including the credit
+    pub amount: Decimal,
+}
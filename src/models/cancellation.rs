@@ -0,0 +1,49 @@
+use crate::models::TaskActor;
+use crate::util::{is_empty, is_none};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// A record of a task or an accepted bid being cancelled and of everything that followed from it,
+// kept so that office admins can see who cancelled what and where the money went
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Cancellation {
+    #[serde(default)]
+    pub id: String,
+
+    pub office_id: String,
+
+    pub task_id: String,
+
+    // Set when only the accepted bid was cancelled, the task is then open for bids again
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub bid_id: Option<String>,
+
+    pub initiator: TaskActor,
+
+    pub user_id: String,
+
+    pub reason: String,
+
+    // Payments that had not been paid and were withdrawn
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub cancelled_payments: Vec<String>,
+
+    // Payments in escrow that are being paid back to the customer
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub refunded_payments: Vec<String>,
+
+    // Payments that could not be withdrawn or refunded and have to be handled by an admin
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub failed_payments: Vec<String>,
+
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub closed_chats: Vec<String>,
+
+    pub date: DateTime<Utc>,
+}
@@ -0,0 +1,109 @@
+use crate::models::DeductionType;
+use crate::tax::{verify_cost, CostError, Costs, TaxRules};
+use crate::util::{is_false, is_none};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeOrderStatus {
+    // Waiting for the task owner to accept or reject it
+    Proposed,
+    // The task owner has agreed to pay for the extra work
+    Accepted,
+    Rejected,
+}
+
+// Extra work ("ÄTA-arbete") the craftsman finds on site after the bid was accepted. An accepted
+// change order is paid into escrow separately from the bid and released when the task is finished.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeOrder {
+    #[serde(default)]
+    pub id: String,
+
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub deleted: bool,
+
+    pub office_id: String,
+
+    pub task_id: String,
+
+    // The accepted bid the change order adds to
+    #[serde(default)]
+    pub bid_id: String,
+
+    #[serde(default)]
+    pub craftsman_id: String,
+
+    pub description: String,
+
+    // The total price including the tax and removing the root deduction
+    pub final_amount: Decimal,
+
+    // The total root deduction
+    pub root_deduction: Decimal,
+
+    // Material cost without the tax added
+    pub material_cost: Decimal,
+
+    // Labour cost without the tax added
+    pub labour_cost: Decimal,
+
+    // Total added tax
+    pub vat: Decimal,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub labour_hours: Option<Decimal>,
+
+    // Set by the server when the customer is a business, the change order is then without vat
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub reverse_charge: bool,
+
+    #[serde(default = "proposed")]
+    pub status: ChangeOrderStatus,
+
+    // The payment made when the change order was accepted
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub payment_id: Option<String>,
+
+    pub modified: DateTime<Utc>,
+}
+
+fn proposed() -> ChangeOrderStatus {
+    ChangeOrderStatus::Proposed
+}
+
+impl ChangeOrder {
+    pub fn costs(&self) -> Costs {
+        Costs {
+            labour_cost: self.labour_cost,
+            deductible_labour_cost: self.labour_cost,
+            material_cost: self.material_cost,
+            vat: self.vat,
+            root_deduction: self.root_deduction,
+            final_amount: self.final_amount,
+            reverse_charge: self.reverse_charge,
+        }
+    }
+
+    // NOTE: Change orders follow the same rules as bids
+    pub fn verify_cost(
+        &self,
+        rules: &TaxRules,
+        deduction: Option<DeductionType>,
+        allowance: Option<Decimal>,
+        reverse_charge: bool,
+    ) -> Result<(), CostError> {
+        let costs = Costs {
+            reverse_charge,
+            ..self.costs()
+        };
+        verify_cost(&costs, rules, deduction, allowance)
+    }
+}
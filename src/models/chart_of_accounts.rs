@@ -0,0 +1,150 @@
+use crate::models::LedgerAccount;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+// The kind of account as used by SIE, T for assets, S for liabilities, I for income and K for
+// costs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountKind {
+    Asset,
+    Liability,
+    Income,
+    Cost,
+}
+
+impl AccountKind {
+    pub fn sie_code(&self) -> &'static str {
+        match self {
+            AccountKind::Asset => "T",
+            AccountKind::Liability => "S",
+            AccountKind::Income => "I",
+            AccountKind::Cost => "K",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartAccount {
+    pub ledger_account: LedgerAccount,
+
+    // The account number in the office's bookkeeping, usually a BAS account
+    pub number: u32,
+
+    pub name: String,
+
+    pub kind: AccountKind,
+}
+
+// Maps the accounts of the ledger to the accounts used by an office's bookkeeping
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartOfAccounts {
+    pub accounts: Vec<ChartAccount>,
+
+    // The month the office's fiscal year starts in, January unless it has a broken fiscal year
+    #[serde(default = "fiscal_year_start_month")]
+    pub fiscal_year_start_month: u32,
+}
+
+fn fiscal_year_start_month() -> u32 {
+    1
+}
+
+impl ChartOfAccounts {
+    // Returns the mapping for a ledger account, falling back to the default BAS account if the
+    // office has not mapped it
+    pub fn get(&self, ledger_account: LedgerAccount) -> ChartAccount {
+        match self
+            .accounts
+            .iter()
+            .find(|a| a.ledger_account == ledger_account)
+        {
+            Some(a) => a.clone(),
+            None => ChartOfAccounts::default()
+                .accounts
+                .into_iter()
+                .find(|a| a.ledger_account == ledger_account)
+                .unwrap(),
+        }
+    }
+
+    // The first and last day of the fiscal year the date is in
+    pub fn fiscal_year(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let month = self.fiscal_year_start_month;
+        let year = if date.month() >= month {
+            date.year()
+        } else {
+            date.year() - 1
+        };
+        let start = NaiveDate::from_ymd(year, month, 1);
+        let end = NaiveDate::from_ymd(year + 1, month, 1).pred();
+        (start, end)
+    }
+}
+
+impl Default for ChartOfAccounts {
+    // NOTE: The default accounts are taken from the BAS chart of accounts
+    fn default() -> Self {
+        let account = |ledger_account, number, name: &str, kind| ChartAccount {
+            ledger_account,
+            number,
+            name: name.to_string(),
+            kind,
+        };
+        ChartOfAccounts {
+            accounts: vec![
+                account(
+                    LedgerAccount::SwishAccount,
+                    1930,
+                    "Företagskonto",
+                    AccountKind::Asset,
+                ),
+                account(
+                    LedgerAccount::BankAccount,
+                    1940,
+                    "Övriga bankkonton",
+                    AccountKind::Asset,
+                ),
+                account(
+                    LedgerAccount::Escrow,
+                    2420,
+                    "Förskott från kunder",
+                    AccountKind::Liability,
+                ),
+                account(
+                    LedgerAccount::CraftsmanPayable,
+                    2440,
+                    "Leverantörsskulder",
+                    AccountKind::Liability,
+                ),
+                account(
+                    LedgerAccount::BrokerageRevenue,
+                    3041,
+                    "Försäljning tjänster 25% sv",
+                    AccountKind::Income,
+                ),
+                account(
+                    LedgerAccount::BrokerageVat,
+                    2611,
+                    "Utgående moms på försäljning inom Sverige, 25%",
+                    AccountKind::Liability,
+                ),
+                account(
+                    LedgerAccount::Refunds,
+                    2890,
+                    "Övriga kortfristiga skulder",
+                    AccountKind::Liability,
+                ),
+                account(
+                    LedgerAccount::Discounts,
+                    5990,
+                    "Övriga kostnader för reklam och PR",
+                    AccountKind::Cost,
+                ),
+            ],
+            fiscal_year_start_month: fiscal_year_start_month(),
+        }
+    }
+}
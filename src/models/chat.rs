@@ -19,4 +19,9 @@ pub struct Chat {
     #[serde(skip_serializing_if = "util::is_false")]
     #[serde(default)]
     pub deleted: bool,
+
+    // No more messages can be sent once the task or the bid the chat is about has been cancelled
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub closed: bool,
 }
@@ -1,7 +1,7 @@
-use super::Craft;
-use crate::models::Rating;
+use super::{Craft, CraftStatus, CraftType, Task};
+use crate::models::{Rating, ServiceArea};
 use crate::util;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -38,7 +38,16 @@ pub struct Craftsman {
     #[serde(default)]
     pub company_postal: Option<String>,
 
-    pub work_area: String,
+    // Where the craftsman takes jobs, used to match them with tasks
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub service_area: Option<ServiceArea>,
+
+    // The free text area craftsmen gave before there were service areas, kept until it has been
+    // migrated into a service area
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub work_area: Option<String>,
 
     pub completed_jobs: usize,
 
@@ -56,5 +65,49 @@ pub struct Craftsman {
 
     pub f_tax: bool,
 
+    // The alerts about new tasks in the service area of a craftsman without saved searches, counted
+    // per day like the alerts of a saved search
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub area_alerts_sent_on: Option<NaiveDate>,
+
+    #[serde(default)]
+    pub area_alerts_sent: u32,
+
+    // Tasks in the service area that are waiting for the next digest
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub pending_area_task_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub last_area_digest: Option<DateTime<Utc>>,
+
     pub modified: DateTime<Utc>,
 }
+
+impl Craftsman {
+    pub fn approved_crafts(&self) -> Vec<CraftType> {
+        self.crafts
+            .iter()
+            .filter(|c| c.status == CraftStatus::Approved)
+            .map(|c| c.craft_type.clone())
+            .collect()
+    }
+
+    // How many alerts about tasks in the service area have been sent on the given day
+    pub fn area_alerts_sent_on(&self, day: NaiveDate) -> u32 {
+        if self.area_alerts_sent_on == Some(day) {
+            self.area_alerts_sent
+        } else {
+            0
+        }
+    }
+
+    // A craftsman can only bid on tasks where they have at least one approved craft
+    pub fn can_bid_on(&self, task: &Task) -> bool {
+        self.approved_crafts()
+            .iter()
+            .any(|craft| task.crafts.contains(craft))
+    }
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+// Who the customer of a task is, which decides how the work is taxed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub enum CustomerType {
+    // A private person, who pays vat and can get ROT and RUT deductions
+    Private,
+    // A company buying construction services, which accounts for the vat itself under the reverse
+    // charge rules ("omvänd betalningsskyldighet")
+    Business,
+}
+
+impl Default for CustomerType {
+    fn default() -> Self {
+        CustomerType::Private
+    }
+}
+
+impl CustomerType {
+    pub fn is_business(&self) -> bool {
+        *self == CustomerType::Business
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+// The two kinds of tax deductions a private customer can get on labour
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub enum DeductionType {
+    // Repairs, conversion and extension ("reparation, ombyggnad, tillbyggnad")
+    Rot,
+    // Cleaning, maintenance and laundry ("rengöring, underhåll, tvätt")
+    Rut,
+}
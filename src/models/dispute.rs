@@ -0,0 +1,98 @@
+use crate::util::{is_empty, is_false, is_none};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DisputeParty {
+    Customer,
+    Craftsman,
+    // An office admin helping the parties come to an agreement
+    Mediator,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DisputeOutcome {
+    // The craftsman gets paid in full
+    FullRelease,
+    // The customer gets part of the payment back and the craftsman gets the rest
+    PartialRefund,
+    // The customer gets the whole payment back
+    FullRefund,
+}
+
+// One entry in the timeline of a dispute
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DisputeStatement {
+    pub id: String,
+
+    pub author_id: String,
+
+    pub party: DisputeParty,
+
+    pub text: String,
+
+    // Photos of the work as evidence
+    #[serde(skip_serializing_if = "is_empty")]
+    #[serde(default)]
+    pub images: Vec<String>,
+
+    pub created: DateTime<Utc>,
+}
+
+// A complaint ("reklamation") about the work paid for by a payment. The escrowed money of the task
+// is not released while a dispute is open.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Dispute {
+    #[serde(default)]
+    pub id: String,
+
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub deleted: bool,
+
+    pub office_id: String,
+
+    pub task_id: String,
+
+    pub payment_id: String,
+
+    pub craftsman_id: String,
+
+    pub opened_by: DisputeParty,
+
+    #[serde(default)]
+    pub statements: Vec<DisputeStatement>,
+
+    // The first admin to take part in the dispute
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub mediator_id: Option<String>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub outcome: Option<DisputeOutcome>,
+
+    // What the customer gets back on a partial refund
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub refund_amount: Option<Decimal>,
+
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub resolved: Option<DateTime<Utc>>,
+
+    pub created: DateTime<Utc>,
+
+    pub modified: DateTime<Utc>,
+}
+
+impl Dispute {
+    pub fn is_open(&self) -> bool {
+        !self.deleted && self.outcome.is_none()
+    }
+}
@@ -0,0 +1,27 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// An invoice the customer pays by bank transfer to our bankgiro. The payment is matched to the
+// invoice through the OCR reference.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Invoice {
+    pub ocr: String,
+
+    pub bankgiro: String,
+
+    pub due_date: NaiveDate,
+}
+
+// A single incoming transaction on the bankgiro, as read from the bank's statement
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BankTransaction {
+    // The OCR reference the customer paid with
+    pub reference: String,
+
+    pub amount: Decimal,
+
+    pub date: NaiveDate,
+}
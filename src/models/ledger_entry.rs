@@ -0,0 +1,83 @@
+use crate::models::Currency;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum LedgerAccount {
+    // Money actually held in the Swish intermediate account (asset)
+    SwishAccount,
+    // Money actually held in the bank account invoices are paid to (asset)
+    BankAccount,
+    // Money held on behalf of customers until the task is finished (liability)
+    Escrow,
+    // Money owed to craftsmen for finished tasks (liability)
+    CraftsmanPayable,
+    // Toolit's brokerage fee excluding vat (revenue)
+    BrokerageRevenue,
+    // Vat on the brokerage fee (liability)
+    BrokerageVat,
+    // Refunds that have been initialized but not yet confirmed by swish (liability)
+    Refunds,
+    // Discounts from campaigns and referral credit that Toolit pays for (cost)
+    Discounts,
+}
+
+impl LedgerAccount {
+    pub fn all() -> [LedgerAccount; 8] {
+        [
+            LedgerAccount::SwishAccount,
+            LedgerAccount::BankAccount,
+            LedgerAccount::Escrow,
+            LedgerAccount::CraftsmanPayable,
+            LedgerAccount::BrokerageRevenue,
+            LedgerAccount::BrokerageVat,
+            LedgerAccount::Refunds,
+            LedgerAccount::Discounts,
+        ]
+    }
+}
+
+// The payment transition that caused an entry to be written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LedgerTransition {
+    PaidToEscrow,
+    Finalized,
+    PaidToCraftsman,
+    RefundInitialized,
+    Refunded,
+    RefundFailed,
+}
+
+// NOTE: Ledger entries are append-only, they are never modified nor deleted. A mistake is
+// corrected by writing a new entry going the other way.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    #[serde(default)]
+    pub id: String,
+
+    pub office_id: String,
+
+    pub task_id: String,
+
+    pub bid_id: String,
+
+    pub payment_id: String,
+
+    pub craftsman_id: String,
+
+    pub transition: LedgerTransition,
+
+    pub debit: LedgerAccount,
+
+    pub credit: LedgerAccount,
+
+    pub amount: Decimal,
+
+    pub currency: Currency,
+
+    pub booked: DateTime<Utc>,
+}
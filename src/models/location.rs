@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+// A GeoJSON point, which is the shape Cosmos needs for its spatial functions. Note that the
+// coordinates are longitude first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub coordinates: [f64; 2],
+}
+
+impl Location {
+    pub fn point(latitude: f64, longitude: f64) -> Self {
+        Location {
+            kind: String::from("Point"),
+            coordinates: [longitude, latitude],
+        }
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.coordinates[1]
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.coordinates[0]
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.kind == "Point"
+            && (-90.0..=90.0).contains(&self.latitude())
+            && (-180.0..=180.0).contains(&self.longitude())
+    }
+
+    // The great circle distance in kilometers
+    pub fn distance_km(&self, other: &Location) -> f64 {
+        // MAGIC NUMBER: The mean radius of the earth
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude().to_radians(), other.latitude().to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude() - self.longitude()).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
@@ -33,6 +33,12 @@ pub struct Message {
 
     pub publish_status: PublishStatus,
 
+    // Written by the server about something that happened to the bid rather than by the customer
+    // or the craftsman
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub system: bool,
+
     #[serde(skip_serializing_if = "util::is_none")]
     #[serde(default)]
     pub image: Option<String>,
@@ -0,0 +1,21 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// A stage of a larger task which the customer pays into escrow and approves on its own. Whether a
+// milestone has been paid or approved is given by the state of the payment pointing to it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Milestone {
+    #[serde(default)]
+    pub id: String,
+
+    pub description: String,
+
+    // What the customer pays for the milestone, the amounts of all milestones of a bid add up to
+    // the final bid
+    pub amount: Decimal,
+
+    // Milestones are paid and approved in ascending order, the last one is approved by finishing
+    // the task
+    pub order: u32,
+}
@@ -7,7 +7,7 @@ pub use role_flags::RoleFlags;
 mod device;
 pub use device::Device;
 mod bid;
-pub use bid::Bid;
+pub use bid::{Bid, BidWithdrawal};
 mod office;
 pub use office::Office;
 mod user;
@@ -40,3 +40,41 @@ mod i18n_string;
 pub use i18n_string::I18nString;
 mod publish_status;
 pub use publish_status::PublishStatus;
+mod ledger_entry;
+pub use ledger_entry::{LedgerAccount, LedgerEntry, LedgerTransition};
+mod chart_of_accounts;
+pub use chart_of_accounts::{AccountKind, ChartOfAccounts};
+mod deduction_type;
+pub use deduction_type::DeductionType;
+mod tax_deduction_usage;
+pub use tax_deduction_usage::TaxDeductionUsage;
+mod invoice;
+pub use invoice::{BankTransaction, Invoice};
+mod milestone;
+pub use milestone::Milestone;
+mod change_order;
+pub use change_order::{ChangeOrder, ChangeOrderStatus};
+mod dispute;
+pub use dispute::{Dispute, DisputeOutcome, DisputeParty, DisputeStatement};
+mod brokerage;
+pub use brokerage::{Brokerage, BrokeragePromotion, CraftBrokerage};
+mod campaign;
+pub use campaign::{Campaign, CampaignKind, Discount, ReferralCode};
+mod customer_type;
+pub use customer_type::CustomerType;
+mod location;
+pub use location::Location;
+mod service_area;
+pub use service_area::ServiceArea;
+mod saved_search;
+pub use saved_search::{AlertFrequency, SavedSearch};
+mod task_state;
+pub use task_state::{TaskActor, TaskState, TaskStateChange};
+mod cancellation;
+pub use cancellation::Cancellation;
+mod bid_revision;
+pub use bid_revision::BidRevision;
+mod bid_line;
+pub use bid_line::{BidLine, BidLineKind};
+mod price_estimate;
+pub use price_estimate::PriceEstimate;
@@ -1,6 +1,6 @@
-use crate::models::I18nString;
+use crate::models::{BrokeragePromotion, ChartOfAccounts, CraftBrokerage, CraftType, I18nString};
 use crate::util;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use geojson::GeoJson;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
@@ -17,9 +17,85 @@ pub struct Office {
 
     pub name: Vec<I18nString>,
 
+    // The brokerage of crafts without a brokerage of their own
+    #[serde(default = "brokerage_percentage")]
     pub brokerage_percentage: Decimal,
 
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub craft_brokerage: Vec<CraftBrokerage>,
+
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub brokerage_promotions: Vec<BrokeragePromotion>,
+
     pub area: GeoJson,
 
+    // Used when exporting the ledger to the office's bookkeeping
+    #[serde(default)]
+    pub chart_of_accounts: ChartOfAccounts,
+
+    // The number of days the customer has to approve the work or open a dispute after the
+    // craftsman marked a task as finished, before the task is finished automatically
+    #[serde(default = "auto_finish_days")]
+    pub auto_finish_days: i64,
+
+    // The number of days a published task stays open without an accepted bid before it expires
+    #[serde(default = "task_expiry_days")]
+    pub task_expiry_days: i64,
+
     pub modified: DateTime<Utc>,
 }
+
+fn brokerage_percentage() -> Decimal {
+    *crate::BROKERAGE_PERCENTAGE
+}
+
+// MAGIC NUMBER: Two weeks unless the office has decided otherwise
+fn auto_finish_days() -> i64 {
+    14
+}
+
+// MAGIC NUMBER: A month unless the office has decided otherwise
+fn task_expiry_days() -> i64 {
+    30
+}
+
+impl Office {
+    // MAGIC NUMBER: No customer needs more than a year to approve the work
+    pub const MAX_AUTO_FINISH_DAYS: i64 = 365;
+
+    // MAGIC NUMBER: No task stays open for more than a year
+    pub const MAX_TASK_EXPIRY_DAYS: i64 = 365;
+
+    // How long the customer has to approve the work, capped for offices that stored a longer
+    // grace period
+    pub fn auto_finish(&self) -> Duration {
+        Duration::days(self.auto_finish_days.min(Office::MAX_AUTO_FINISH_DAYS))
+    }
+
+    // How long a published task stays open, capped for offices that stored a longer expiry
+    pub fn task_expiry(&self) -> Duration {
+        Duration::days(self.task_expiry_days.min(Office::MAX_TASK_EXPIRY_DAYS))
+    }
+
+    // The brokerage of a task with the given crafts. The lowest running promotion wins, otherwise
+    // the highest brokerage of the crafts is used.
+    pub fn brokerage_percentage_for(&self, crafts: &[CraftType], at: DateTime<Utc>) -> Decimal {
+        let promotion = self
+            .brokerage_promotions
+            .iter()
+            .filter(|p| p.applies_to(crafts, at))
+            .map(|p| p.percentage)
+            .min();
+        if let Some(percentage) = promotion {
+            return percentage;
+        }
+        self.craft_brokerage
+            .iter()
+            .filter(|b| crafts.contains(&b.craft_type))
+            .map(|b| b.percentage)
+            .max()
+            .unwrap_or(self.brokerage_percentage)
+    }
+}
@@ -1,7 +1,10 @@
-use crate::models::PaymentMethod;
+use crate::models::{
+    Bid, Brokerage, ChangeOrder, DeductionType, Discount, Invoice, Office, PaymentMethod,
+};
+use crate::tax::{self, Costs};
 use crate::util;
-use chrono::{DateTime, Utc};
-use rust_decimal::Decimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::prelude::{Decimal, Zero};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
@@ -48,6 +51,16 @@ pub struct Payment {
 
     pub bid_id: String,
 
+    // Set when the payment only covers one milestone of the bid
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub milestone_id: Option<String>,
+
+    // Set when the payment is for a change order rather than the bid
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub change_order_id: Option<String>,
+
     pub craftsman_id: String,
 
     #[serde(skip_serializing_if = "util::is_none")]
@@ -58,6 +71,11 @@ pub struct Payment {
 
     pub payment_method: PaymentMethod,
 
+    // Set for payments made by invoice
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub invoice: Option<Invoice>,
+
     // When the swish payment was provided to the escrow
     pub payment_date: Option<DateTime<Utc>>,
 
@@ -65,5 +83,126 @@ pub struct Payment {
 
     pub currency: Currency,
 
+    // The ROT or RUT deduction given on the payment, it counts towards the customer's yearly
+    // ceiling once the payment reaches escrow
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub deduction_type: Option<DeductionType>,
+
+    #[serde(default)]
+    pub root_deduction: Decimal,
+
+    // What the customer got back while the rest of the payment was released to the craftsman,
+    // e.g. after a dispute
+    #[serde(default)]
+    pub refunded_amount: Decimal,
+
+    // The refund asked of the provider. It is stored before the provider is asked so that the
+    // customer is never paid back twice.
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub refund_id: Option<String>,
+
+    // A promo code or referral credit used by the customer. The amount is what the customer pays,
+    // the discount is paid by Toolit.
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub discount: Option<Discount>,
+
+    // What Toolit takes of the payment when it is released, at the brokerage of the bid
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub brokerage: Option<Brokerage>,
+
+    // The blob id of the receipt sent to the customer once the payment reached escrow
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub receipt_id: Option<String>,
+
     pub modified: DateTime<Utc>,
 }
+
+impl Payment {
+    // The number we use for the payment on receipts and towards Skatteverket, which accepts at
+    // most 20 characters
+    pub fn invoice_number(&self) -> String {
+        let mut encode_buf = uuid::Uuid::encode_buffer();
+        match uuid::Uuid::parse_str(&self.id) {
+            Ok(id) => id.to_simple().encode_upper(&mut encode_buf)[..20].to_string(),
+            Err(_) => self.id.chars().take(20).collect(),
+        }
+    }
+
+    // What the payment is worth to the craftsman, which is what the customer paid and the discount
+    pub fn gross_amount(&self) -> Decimal {
+        match &self.discount {
+            Some(discount) => self.amount + discount.amount,
+            None => self.amount,
+        }
+    }
+
+    pub fn discount_amount(&self) -> Decimal {
+        match &self.discount {
+            Some(discount) => discount.amount,
+            None => Decimal::zero(),
+        }
+    }
+
+    // The part of an amount of the whole bid that this payment covers
+    pub fn share_of(&self, bid: &Bid, amount: Decimal) -> Decimal {
+        match &self.milestone_id {
+            Some(milestone_id) => bid.milestone_share(milestone_id, amount),
+            None => amount,
+        }
+    }
+
+    pub fn is_partially_refunded(&self) -> bool {
+        match self.payment_state {
+            PaymentState::Finalized | PaymentState::PaidToCraftsman => {
+                !self.refunded_amount.is_zero()
+            }
+            _ => false,
+        }
+    }
+
+    // What the payment pays for, which is either the change order it was made for or its share of
+    // the bid
+    pub fn costs(&self, bid: &Bid, change_order: Option<&ChangeOrder>) -> Costs {
+        if let Some(change_order) = change_order {
+            return change_order.costs();
+        }
+        Costs {
+            labour_cost: self.share_of(bid, bid.labour_cost),
+            deductible_labour_cost: self.share_of(bid, bid.deductible_labour_cost()),
+            material_cost: self.share_of(bid, bid.material_cost),
+            vat: self.share_of(bid, bid.vat),
+            root_deduction: self.share_of(bid, bid.root_deduction),
+            final_amount: self.gross_amount(),
+            reverse_charge: bid.reverse_charge,
+        }
+    }
+
+    // The Swedish date the payment reached escrow, which decides the tax rules of the payment.
+    // Payments that have not been paid yet go by today's rules.
+    pub fn tax_date(&self) -> NaiveDate {
+        match self.payment_date {
+            Some(date) => tax::date_of(date),
+            None => tax::today(),
+        }
+    }
+
+    // NOTE: Payments made before the brokerage was stored on them use the default of the office
+    pub fn brokerage_percentage(&self, office: &Office) -> Decimal {
+        match &self.brokerage {
+            Some(brokerage) => brokerage.percentage,
+            None => office.brokerage_percentage,
+        }
+    }
+
+    pub fn labour_hours(&self, bid: &Bid, change_order: Option<&ChangeOrder>) -> Option<Decimal> {
+        match change_order {
+            Some(change_order) => change_order.labour_hours,
+            None => bid.labour_hours.map(|hours| self.share_of(bid, hours)),
+        }
+    }
+}
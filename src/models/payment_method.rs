@@ -1,7 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum PaymentMethod {
     Swish,
+    // Paid by bank transfer to our bankgiro using the OCR reference of the invoice
+    Invoice,
+}
+
+impl Default for PaymentMethod {
+    fn default() -> Self {
+        PaymentMethod::Swish
+    }
 }
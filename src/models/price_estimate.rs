@@ -0,0 +1,96 @@
+use crate::models::CraftType;
+use crate::util::is_none;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// MAGIC NUMBER: Words shorter than this say little about the job
+const MIN_KEYWORD_LENGTH: usize = 4;
+
+// Common swedish words that are long enough to be keywords but say nothing about the job
+const STOP_WORDS: &[&str] = &[
+    "alla", "allt", "andra", "bara", "behöver", "blir", "dessa", "detta", "eller", "finns", "från",
+    "göra", "hela", "inte", "jobb", "jobbet", "kunna", "mellan", "mycket", "också", "samt",
+    "sedan", "till", "under", "vara", "vill", "även", "över",
+];
+
+// The going price of an office for a craft, or for the tasks of a craft mentioning a keyword,
+// taken from the bids customers have accepted. Computed by the price estimate job.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceEstimate {
+    // The craft and keyword, see PriceEstimate::id_of
+    pub id: String,
+
+    pub office_id: String,
+
+    pub craft: CraftType,
+
+    // Missing for the estimate of every task of the craft
+    #[serde(skip_serializing_if = "is_none")]
+    #[serde(default)]
+    pub keyword: Option<String>,
+
+    // The number of accepted bids the estimate is built from
+    pub sample_size: usize,
+
+    // The lower quartile, median and upper quartile of the accepted bids
+    pub low: Decimal,
+    pub median: Decimal,
+    pub high: Decimal,
+
+    pub computed: DateTime<Utc>,
+}
+
+impl PriceEstimate {
+    pub fn id_of(craft: &CraftType, keyword: Option<&str>) -> String {
+        match keyword {
+            Some(keyword) => format!("{:?}-{}", craft, keyword),
+            None => format!("{:?}", craft),
+        }
+    }
+
+    // Builds the estimate from the prices of the accepted bids, None if there are none
+    pub fn of(
+        office_id: &str,
+        craft: &CraftType,
+        keyword: Option<&str>,
+        prices: &mut [Decimal],
+    ) -> Option<PriceEstimate> {
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort();
+        Some(PriceEstimate {
+            id: PriceEstimate::id_of(craft, keyword),
+            office_id: office_id.to_string(),
+            craft: craft.clone(),
+            keyword: keyword.map(String::from),
+            sample_size: prices.len(),
+            low: percentile(prices, 25),
+            median: percentile(prices, 50),
+            high: percentile(prices, 75),
+            computed: Utc::now(),
+        })
+    }
+
+    // The words of a task that estimates are kept for, lowercase and without duplicates
+    pub fn keywords(text: &str) -> Vec<String> {
+        let mut keywords: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(|w| w.to_lowercase())
+            .filter(|w| w.chars().count() >= MIN_KEYWORD_LENGTH)
+            .filter(|w| !w.chars().all(|c| c.is_numeric()))
+            .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+            .collect();
+        keywords.sort();
+        keywords.dedup();
+        keywords
+    }
+}
+
+// Nearest rank percentile of sorted prices
+fn percentile(sorted: &[Decimal], p: usize) -> Decimal {
+    let rank = (sorted.len() * p + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
@@ -0,0 +1,147 @@
+use crate::models::{CraftType, ServiceArea, Task};
+use crate::util;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub enum AlertFrequency {
+    // A push notification for every new task, up to a daily cap after which the rest wait for
+    // the digest
+    Immediate,
+    // One push notification a day summing up the new tasks
+    DailyDigest,
+    Off,
+}
+
+impl Default for AlertFrequency {
+    fn default() -> Self {
+        AlertFrequency::Immediate
+    }
+}
+
+// Search criteria a craftsman has saved to be alerted when a matching task is published
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    #[serde(default)]
+    pub id: String,
+
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub deleted: bool,
+
+    pub office_id: String,
+
+    // Set by the server to the caller
+    #[serde(default)]
+    pub craftsman_id: String,
+
+    pub name: String,
+
+    // Any of these crafts, or any craft the craftsman is approved for if empty
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub crafts: Vec<CraftType>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub area: Option<ServiceArea>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub city: Option<String>,
+
+    // Matches the start of the postcode
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub postcode: Option<String>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub min_price: Option<Decimal>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub max_price: Option<Decimal>,
+
+    #[serde(default)]
+    pub frequency: AlertFrequency,
+
+    // The day the immediate alerts were counted for and how many were sent that day
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub alerts_sent_on: Option<NaiveDate>,
+
+    #[serde(default)]
+    pub alerts_sent: u32,
+
+    // Matching tasks that are waiting for the next digest
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub pending_task_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub last_digest: Option<DateTime<Utc>>,
+
+    pub modified: DateTime<Utc>,
+}
+
+impl SavedSearch {
+    // Makes sure the criteria can match anything at all
+    pub fn verify(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("the search needs a name"));
+        }
+        if let Some(area) = &self.area {
+            if !area.is_valid() {
+                return Err(String::from(
+                    "the area has to be a radius of at most 500 km or a polygon",
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_price, self.max_price) {
+            if min > max {
+                return Err(format!(
+                    "the lowest price {} is more than the highest {}",
+                    min, max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Matches everything but the area, which is left to Cosmos
+    pub fn matches(&self, task: &Task) -> bool {
+        if !self.crafts.is_empty() && !task.crafts.iter().any(|c| self.crafts.contains(c)) {
+            return false;
+        }
+        if let Some(city) = &self.city {
+            if task.city.trim().to_lowercase() != city.trim().to_lowercase() {
+                return false;
+            }
+        }
+        if let Some(postcode) = &self.postcode {
+            if !task
+                .postcode
+                .replace(' ', "")
+                .starts_with(&postcode.replace(' ', ""))
+            {
+                return false;
+            }
+        }
+        self.min_price.is_none_or(|min| task.price >= min)
+            && self.max_price.is_none_or(|max| task.price <= max)
+    }
+
+    // How many immediate alerts have been sent on the given day
+    pub fn alerts_sent_on(&self, day: NaiveDate) -> u32 {
+        if self.alerts_sent_on == Some(day) {
+            self.alerts_sent
+        } else {
+            0
+        }
+    }
+}
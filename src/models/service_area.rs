@@ -0,0 +1,55 @@
+use crate::models::Location;
+use geojson::{GeoJson, Value};
+use serde::{Deserialize, Serialize};
+
+// MAGIC NUMBER: Nobody drives further than this for a job
+const MAX_RADIUS_KM: f64 = 500.0;
+
+// The area a craftsman takes jobs in, either a distance around a point or a polygon drawn on the
+// map
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "PascalCase")]
+pub enum ServiceArea {
+    Radius {
+        center: Location,
+        #[serde(rename = "radiusKm")]
+        radius_km: f64,
+    },
+    Polygon {
+        area: GeoJson,
+    },
+}
+
+impl ServiceArea {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            ServiceArea::Radius { center, radius_km } => {
+                center.is_valid() && *radius_km > 0.0 && *radius_km <= MAX_RADIUS_KM
+            }
+            ServiceArea::Polygon { area } => match area {
+                GeoJson::Geometry(geometry) => match &geometry.value {
+                    Value::Polygon(_) | Value::MultiPolygon(_) => true,
+                    _ => false,
+                },
+                _ => false,
+            },
+        }
+    }
+
+    // A Cosmos condition that holds when the point at the given path is in the area
+    pub fn sql_contains(&self, path: &str) -> String {
+        match self {
+            ServiceArea::Radius { center, radius_km } => format!(
+                "ST_DISTANCE({}, {}) <= {}",
+                path,
+                serde_json::to_string(center).unwrap_or_default(),
+                radius_km * 1000.0
+            ),
+            ServiceArea::Polygon { area } => format!(
+                "ST_WITHIN({}, {})",
+                path,
+                serde_json::to_string(area).unwrap_or_default()
+            ),
+        }
+    }
+}
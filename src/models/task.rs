@@ -1,5 +1,8 @@
 use crate::models::CraftType;
+use crate::models::CustomerType;
+use crate::models::Location;
 use crate::models::PublishStatus;
+use crate::models::{TaskState, TaskStateChange};
 use crate::util;
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
@@ -31,6 +34,15 @@ pub struct Task {
 
     pub publish_status: PublishStatus,
 
+    // Set by the server, read it through state() since older tasks do not have it
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub state: Option<TaskState>,
+
+    #[serde(skip_serializing_if = "util::is_empty")]
+    #[serde(default)]
+    pub state_history: Vec<TaskStateChange>,
+
     #[serde(skip_serializing_if = "util::is_empty")]
     #[serde(default)]
     pub videos: Vec<String>,
@@ -43,6 +55,11 @@ pub struct Task {
 
     pub postcode: String,
 
+    // Where the work is done, used to find tasks close to a craftsman
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub location: Option<Location>,
+
     #[serde(skip_serializing_if = "util::is_none")]
     #[serde(default)]
     pub date_done: Option<DateTime<Utc>>,
@@ -63,16 +80,51 @@ pub struct Task {
     #[serde(default)]
     pub craftsman_indicated_finished: bool,
 
+    // When the craftsman marked the task as finished, the grace period of the office starts then
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub craftsman_finished_date: Option<DateTime<Utc>>,
+
+    // The customer has been reminded to finish the task before it is finished automatically
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub finish_reminder_sent: bool,
+
     #[serde(skip_serializing_if = "util::is_false")]
     #[serde(default)]
     pub rated: bool,
 
+    // Craftsmen have been alerted about the task, which only happens the first time it is
+    // published
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub search_alerts_sent: bool,
+
+    // When the task was last published, the task expires if no bid has been accepted a number of
+    // days after this
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub published_date: Option<DateTime<Utc>>,
+
+    // The customer has been reminded that the task is about to expire
+    #[serde(skip_serializing_if = "util::is_false")]
+    #[serde(default)]
+    pub expiry_reminder_sent: bool,
+
     #[serde(skip_serializing_if = "util::is_none")]
     #[serde(default)]
     pub payment_id: Option<String>,
 
     pub use_rot_rut: bool,
 
+    #[serde(default)]
+    pub customer_type: CustomerType,
+
+    // The organization number of the customer, required for business customers
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub org_number: Option<String>,
+
     #[serde(skip_serializing_if = "util::is_none")]
     #[serde(default)]
     pub realestate_union: Option<String>,
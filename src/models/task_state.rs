@@ -0,0 +1,174 @@
+use crate::models::{PublishStatus, Task};
+use crate::util;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Where a task is in its life. A task normally goes from Draft through Published, BidAccepted
+// (the customer is paying), InProgress (the money is in escrow) and CraftsmanDone to Completed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub enum TaskState {
+    Draft,
+    Published,
+    BidAccepted,
+    InProgress,
+    CraftsmanDone,
+    Completed,
+    Cancelled,
+    Expired,
+}
+
+// Who moved a task between two states
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub enum TaskActor {
+    Customer,
+    Craftsman,
+    Admin,
+    // Jobs and payment callbacks
+    System,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStateChange {
+    pub from: TaskState,
+    pub to: TaskState,
+    pub actor: TaskActor,
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "util::is_none")]
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub date: DateTime<Utc>,
+}
+
+// Who may move a task from one state to another, every transition that is not listed here is
+// illegal
+fn allowed_actors(from: TaskState, to: TaskState) -> &'static [TaskActor] {
+    use TaskActor::*;
+    use TaskState::*;
+    match (from, to) {
+        (Draft, Published) => &[Customer, Admin],
+        (Published, Draft) => &[Customer, Admin],
+        (Published, BidAccepted) => &[Customer],
+        // NOTE: The payment of the accepted bid failed or the bid was cancelled
+        (BidAccepted, Published) => &[Customer, Craftsman, Admin, System],
+        // NOTE: Money that reaches escrow puts the task in progress whatever happened to it while
+        // the customer was paying
+        (Draft, InProgress) | (Published, InProgress) | (BidAccepted, InProgress) => &[System],
+        (Expired, InProgress) => &[System],
+        // NOTE: The accepted bid was cancelled before any work was done
+        (InProgress, Published) => &[Customer, Craftsman, Admin],
+        (InProgress, CraftsmanDone) => &[Craftsman],
+        (InProgress, Completed) | (CraftsmanDone, Completed) => &[Customer, Admin, System],
+        (Draft, Cancelled) | (Published, Cancelled) | (BidAccepted, Cancelled) => {
+            &[Customer, Admin, System]
+        }
+        (Expired, Cancelled) => &[Customer, Admin, System],
+        (InProgress, Cancelled) | (CraftsmanDone, Cancelled) => &[Admin, System],
+        (Published, Expired) => &[System],
+        (Expired, Published) => &[Customer, Admin],
+        _ => &[],
+    }
+}
+
+impl Task {
+    // The state of the task. Tasks stored before the state existed get it from the flags that
+    // were used instead until they have been migrated.
+    pub fn state(&self) -> TaskState {
+        match self.state {
+            Some(state) => state,
+            None => self.legacy_state(),
+        }
+    }
+
+    pub fn legacy_state(&self) -> TaskState {
+        if self.finished {
+            TaskState::Completed
+        } else if self.deleted {
+            TaskState::Cancelled
+        } else if self.craftsman_indicated_finished {
+            TaskState::CraftsmanDone
+        } else if self.accepted_bid.is_some() {
+            TaskState::InProgress
+        } else {
+            match self.publish_status {
+                PublishStatus::Published => TaskState::Published,
+                PublishStatus::Unpublished | PublishStatus::Flagged => TaskState::Draft,
+            }
+        }
+    }
+
+    // Moves the task to a new state, recording the change and keeping the old flags in sync.
+    // Returns false if the task already was in the state.
+    pub fn transition(
+        &mut self,
+        to: TaskState,
+        actor: TaskActor,
+        user_id: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<bool, String> {
+        let from = self.state();
+        if from == to {
+            return Ok(false);
+        }
+        let actors = allowed_actors(from, to);
+        if actors.is_empty() {
+            return Err(format!("a task can not go from {:?} to {:?}", from, to));
+        }
+        if !actors.contains(&actor) {
+            return Err(format!(
+                "a {:?} can not move a task from {:?} to {:?}",
+                actor, from, to
+            ));
+        }
+        // NOTE: Only a content admin can publish a task that has been flagged
+        if to == TaskState::Published
+            && actor != TaskActor::Admin
+            && matches!(self.publish_status, PublishStatus::Flagged)
+        {
+            return Err(String::from(
+                "a flagged task can only be published by an admin",
+            ));
+        }
+
+        let now = Utc::now();
+        match to {
+            // NOTE: A cancelled task is hidden from craftsmen for good
+            TaskState::Draft | TaskState::Cancelled => {
+                if !matches!(self.publish_status, PublishStatus::Flagged) {
+                    self.publish_status = PublishStatus::Unpublished;
+                }
+            }
+            TaskState::Published => {
+                self.publish_status = PublishStatus::Published;
+                self.published_date = Some(now);
+                self.expiry_reminder_sent = false;
+            }
+            // NOTE: An expired task is hidden from craftsmen until the customer publishes it again
+            TaskState::Expired => self.publish_status = PublishStatus::Unpublished,
+            TaskState::CraftsmanDone => {
+                self.craftsman_indicated_finished = true;
+                self.craftsman_finished_date = Some(now);
+            }
+            TaskState::Completed => {
+                self.finished = true;
+                self.date_done = Some(now);
+            }
+            TaskState::BidAccepted | TaskState::InProgress => {}
+        }
+        self.state = Some(to);
+        self.state_history.push(TaskStateChange {
+            from,
+            to,
+            actor,
+            user_id: user_id.map(String::from),
+            reason: reason.map(String::from),
+            date: now,
+        });
+        self.modified = now;
+        Ok(true)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::models::DeductionType;
+use chrono::{DateTime, Utc};
+use rust_decimal::{prelude::Zero, Decimal};
+use serde::{Deserialize, Serialize};
+
+// How much ROT and RUT deduction a customer has used during a year. The id is the user id followed
+// by the year.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxDeductionUsage {
+    #[serde(default)]
+    pub id: String,
+
+    pub user_id: String,
+
+    pub year: i32,
+
+    pub rot: Decimal,
+
+    pub rut: Decimal,
+
+    pub modified: DateTime<Utc>,
+}
+
+impl TaxDeductionUsage {
+    pub fn new(user_id: &str, year: i32) -> Self {
+        TaxDeductionUsage {
+            id: TaxDeductionUsage::id(user_id, year),
+            user_id: user_id.to_string(),
+            year,
+            rot: Decimal::zero(),
+            rut: Decimal::zero(),
+            modified: Utc::now(),
+        }
+    }
+
+    pub fn id(user_id: &str, year: i32) -> String {
+        format!("{}-{}", user_id, year)
+    }
+
+    pub fn add(&mut self, deduction_type: DeductionType, amount: Decimal) {
+        match deduction_type {
+            DeductionType::Rot => self.rot += amount,
+            DeductionType::Rut => self.rut += amount,
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::models::Device;
 use crate::util;
 use crate::Role;
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -60,6 +61,11 @@ pub struct User {
     #[serde(default)]
     pub office_ids: Vec<String>,
 
+    // Earned when someone uses the referral code of the user, and taken off the price of the
+    // tasks of the user
+    #[serde(default)]
+    pub referral_credit: Decimal,
+
     pub modified: DateTime<Utc>,
 }
 
@@ -0,0 +1,62 @@
+use super::new_payment;
+use crate::fault::Fault;
+use crate::models::{
+    Bid, Brokerage, ChangeOrder, ChangeOrderStatus, DeductionType, Payment, PaymentMethod,
+};
+use crate::CHANGE_ORDER_COLLECTION;
+use cosmos_utils::query;
+use warp::reject;
+
+// A new payment of an accepted change order. It belongs to the bid the change order was made on
+// so it is refunded and paid out the same way as the payments of the bid.
+pub fn new_change_order_payment(
+    bid: &Bid,
+    change_order: &ChangeOrder,
+    payment_method: PaymentMethod,
+    deduction_type: Option<DeductionType>,
+) -> Payment {
+    let mut payment = new_payment(bid, None, payment_method, deduction_type);
+    payment.change_order_id = Some(change_order.id.clone());
+    payment.amount = change_order.final_amount;
+    payment.root_deduction = change_order.root_deduction;
+    payment.deduction_type = if change_order.root_deduction.is_zero() {
+        None
+    } else {
+        deduction_type
+    };
+    payment.brokerage = bid
+        .brokerage
+        .as_ref()
+        .map(|b| Brokerage::new(b.percentage, &change_order.costs()));
+    payment
+}
+
+// The change orders that are released together with the bid when the task is finished. Every
+// change order of the bid has to have been either accepted or rejected by then, the ones made on
+// an earlier bid of the task are left out.
+pub async fn accepted_change_orders(
+    office_id: &str,
+    task_id: &str,
+    bid_id: &str,
+) -> Result<Vec<ChangeOrder>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE c.taskId = "{}" AND c.bidId = "{}""#,
+        CHANGE_ORDER_COLLECTION, task_id, bid_id
+    );
+    let change_orders: Vec<ChangeOrder> =
+        query(CHANGE_ORDER_COLLECTION, [office_id], q, -1).await?;
+    let mut accepted = Vec::new();
+    for change_order in change_orders.into_iter().filter(|c| !c.deleted) {
+        match change_order.status {
+            ChangeOrderStatus::Proposed => {
+                return Err(reject::custom(Fault::Forbidden(format!(
+                    "The change order '{}' has to be accepted or rejected first",
+                    change_order.description
+                ))));
+            }
+            ChangeOrderStatus::Accepted => accepted.push(change_order),
+            ChangeOrderStatus::Rejected => (),
+        }
+    }
+    Ok(accepted)
+}
@@ -0,0 +1,392 @@
+use crate::fault::Fault;
+use crate::models::{
+    Bid, Brokerage, Campaign, CampaignKind, Discount, Payment, PaymentState, ReferralCode, User,
+};
+use crate::push::send_custom_pn;
+use crate::util::log;
+use crate::{
+    CAMPAIGN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, PAYMENT_COLLECTION, REFERRAL_CODE_COLLECTION,
+    USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, modify, query};
+use rust_decimal::prelude::{Decimal, One, RoundingStrategy, Zero};
+use warp::reject;
+
+// Codes are case insensitive and only made up of letters, digits and dashes
+pub fn normalize_code(code: &str) -> Result<String, warp::Rejection> {
+    let code = code.trim().to_uppercase();
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(reject::custom(Fault::IllegalArgument(format!(
+            "'{}' is not a valid code",
+            code
+        ))));
+    }
+    Ok(code)
+}
+
+// Finds the campaign of a code, and the user the code belongs to if it is a referral code
+async fn find_code(
+    office_id: &str,
+    code: &str,
+) -> Result<(Campaign, Option<String>), warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE c.code = "{}""#,
+        CAMPAIGN_COLLECTION, code
+    );
+    let campaigns: Vec<Campaign> = query(CAMPAIGN_COLLECTION, [office_id], q, -1).await?;
+    if let Some(campaign) = campaigns.into_iter().find(|c| !c.deleted) {
+        return Ok((campaign, None));
+    }
+
+    let q = format!(
+        r#"SELECT * FROM {} r WHERE r.code = "{}""#,
+        REFERRAL_CODE_COLLECTION, code
+    );
+    let referral_codes: Vec<ReferralCode> =
+        query(REFERRAL_CODE_COLLECTION, [office_id], q, -1).await?;
+    match referral_codes.into_iter().next() {
+        Some(referral_code) => {
+            let (campaign, _): (Campaign, _) =
+                get(CAMPAIGN_COLLECTION, [office_id], &referral_code.campaign_id).await?;
+            Ok((campaign, Some(referral_code.user_id)))
+        }
+        None => Err(reject::custom(Fault::NotFound(format!(
+            "There is no campaign with the code {}",
+            code
+        )))),
+    }
+}
+
+// The payments a campaign has been used for, not counting payments that were never paid
+async fn campaign_payments(
+    office_id: &str,
+    campaign_id: &str,
+) -> Result<Vec<Payment>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.discount.campaignId = "{}""#,
+        PAYMENT_COLLECTION, campaign_id
+    );
+    let payments: Vec<Payment> = query(PAYMENT_COLLECTION, [office_id], q, -1).await?;
+    Ok(payments
+        .into_iter()
+        .filter(|p| match p.payment_state {
+            PaymentState::Failed | PaymentState::Error(_) => false,
+            _ => !p.deleted,
+        })
+        .collect())
+}
+
+// Takes a promo code and, if asked for, the referral credit of the customer off a new payment of
+// the bid. What the customer pays is lowered while the craftsman still gets paid the full price.
+pub async fn apply_discount(
+    payment: &mut Payment,
+    bid: &Bid,
+    customer: &User,
+    code: Option<&str>,
+    use_credit: bool,
+) -> Result<(), warp::Rejection> {
+    let mut discount = Discount {
+        customer_id: customer.id.clone(),
+        campaign_id: None,
+        code: None,
+        referrer_id: None,
+        referrer_credit: None,
+        credit: Decimal::zero(),
+        amount: Decimal::zero(),
+    };
+    let mut paid_uses = 0;
+
+    if let Some(code) = code {
+        let code = normalize_code(code)?;
+        let (campaign, referrer_id) = find_code(&payment.office_id, &code).await?;
+        if !campaign.is_running(Utc::now()) {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "The code {} can not be used right now",
+                code
+            ))));
+        }
+        if referrer_id.as_ref() == Some(&customer.id) {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "Your own referral code can not be used by yourself"
+            ))));
+        }
+
+        // NOTE: The total is only checked here to fail early, the use is taken below
+        let used = campaign_payments(&payment.office_id, &campaign.id).await?;
+        if let Some(max_uses) = campaign.max_uses {
+            if std::cmp::max(campaign.uses as usize, used.len()) >= max_uses as usize {
+                return Err(reject::custom(Fault::Ineligible(format!(
+                    "The code {} has been used up",
+                    code
+                ))));
+            }
+        }
+        paid_uses = used.len() as u32;
+        let used_by_customer = used
+            .iter()
+            .filter(|p| p.discount.as_ref().map(|d| &d.customer_id) == Some(&customer.id))
+            .count();
+        if used_by_customer >= campaign.max_uses_per_customer as usize {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "The code {} has already been used",
+                code
+            ))));
+        }
+
+        match campaign.kind {
+            CampaignKind::BrokerageDiscount => {
+                if let Some(percentage) = campaign.brokerage_percentage {
+                    // NOTE: A campaign never raises the brokerage the craftsman pays
+                    let percentage = match &payment.brokerage {
+                        Some(brokerage) if brokerage.percentage < percentage => {
+                            brokerage.percentage
+                        }
+                        _ => percentage,
+                    };
+                    payment.brokerage = Some(Brokerage::new(percentage, &payment.costs(bid, None)));
+                }
+            }
+            CampaignKind::CustomerDiscount | CampaignKind::Referral => {
+                discount.amount = match (campaign.discount_amount, campaign.discount_percentage) {
+                    (Some(amount), _) => amount,
+                    (None, Some(percentage)) => (payment.amount * percentage)
+                        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding),
+                    (None, None) => Decimal::zero(),
+                };
+                if referrer_id.is_some() {
+                    discount.referrer_credit = campaign.referrer_credit;
+                }
+                discount.referrer_id = referrer_id;
+            }
+        }
+        discount.campaign_id = Some(campaign.id.clone());
+        discount.code = Some(code);
+    }
+
+    // MAGIC NUMBER: Swish does not take payments of less than 1 kr
+    let max_discount = std::cmp::max(payment.amount - Decimal::one(), Decimal::zero());
+    discount.amount = std::cmp::min(discount.amount, max_discount);
+    if use_credit && customer.referral_credit > Decimal::zero() {
+        discount.credit = std::cmp::min(customer.referral_credit, max_discount - discount.amount);
+        discount.amount += discount.credit;
+    }
+
+    if discount.campaign_id.is_none() && discount.amount.is_zero() {
+        return Ok(());
+    }
+
+    // NOTE: The use of the code and the credit are taken now rather than once the customer has
+    // paid, or the last use of a code or the same credit could be spent on several payments at
+    // once. They are given back by release_discount if the payment is never paid.
+    if let Some(campaign_id) = &discount.campaign_id {
+        take_use(&payment.office_id, campaign_id, paid_uses).await?;
+    }
+    if !discount.credit.is_zero() {
+        if let Err(e) = take_credit(&customer.id, discount.credit).await {
+            if let Some(campaign_id) = &discount.campaign_id {
+                give_back_use(&payment.office_id, campaign_id, &payment.id).await;
+            }
+            return Err(e);
+        }
+    }
+
+    payment.amount -= discount.amount;
+    payment.discount = Some(discount);
+    Ok(())
+}
+
+// Takes a use of the campaign, unless it has been used up. Campaigns from before the uses were
+// counted start from the payments that have been made with them.
+async fn take_use(
+    office_id: &str,
+    campaign_id: &str,
+    paid_uses: u32,
+) -> Result<(), warp::Rejection> {
+    modify(
+        CAMPAIGN_COLLECTION,
+        [office_id],
+        campaign_id,
+        |mut campaign: Campaign| {
+            let uses = std::cmp::max(campaign.uses, paid_uses);
+            if campaign.max_uses.is_some_and(|max_uses| uses >= max_uses) {
+                return Err(reject::custom(Fault::Ineligible(format!(
+                    "The campaign has been used up"
+                ))));
+            }
+            campaign.uses = uses + 1;
+            campaign.modified = Utc::now();
+            Ok(campaign)
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+async fn give_back_use(office_id: &str, campaign_id: &str, payment_id: &str) {
+    let r = modify(
+        CAMPAIGN_COLLECTION,
+        [office_id],
+        campaign_id,
+        |mut campaign: Campaign| {
+            campaign.uses = campaign.uses.saturating_sub(1);
+            campaign.modified = Utc::now();
+            Ok(campaign)
+        },
+    )
+    .await;
+    if let Err(e) = r {
+        log(format!(
+            "Could not give back the use of campaign {} by payment {} due to {}",
+            campaign_id, payment_id, e
+        ));
+    }
+}
+
+// Takes referral credit from the customer, unless they do not have enough left
+async fn take_credit(user_id: &str, credit: Decimal) -> Result<(), warp::Rejection> {
+    modify(USER_COLLECTION, [user_id], user_id, |mut user: User| {
+        if user.referral_credit < credit {
+            return Err(reject::custom(Fault::Ineligible(format!(
+                "Only {} kr of referral credit is left",
+                user.referral_credit
+            ))));
+        }
+        user.referral_credit -= credit;
+        user.modified = Utc::now();
+        Ok(user)
+    })
+    .await?;
+    Ok(())
+}
+
+// Gives back the use of the code and the credit taken by apply_discount for a payment that was
+// never paid. None of it should fail the payment so errors are only logged.
+pub async fn release_discount(payment: &Payment) {
+    let discount = match &payment.discount {
+        Some(discount) => discount,
+        None => return,
+    };
+
+    if let Some(campaign_id) = &discount.campaign_id {
+        give_back_use(&payment.office_id, campaign_id, &payment.id).await;
+    }
+
+    if !discount.credit.is_zero() {
+        let r = modify(
+            USER_COLLECTION,
+            [&discount.customer_id],
+            &discount.customer_id,
+            |mut user: User| {
+                user.referral_credit += discount.credit;
+                user.modified = Utc::now();
+                Ok(user)
+            },
+        )
+        .await;
+        if let Err(e) = r {
+            log(format!(
+                "Could not give back the referral credit taken by payment {} due to {}",
+                payment.id, e
+            ));
+        }
+    }
+}
+
+// Credits the referrer once the customer has paid. The credit used by the customer was already
+// taken by apply_discount. None of it should fail the payment so errors are only logged.
+pub async fn settle_discount(payment: &Payment) {
+    let discount = match &payment.discount {
+        Some(discount) => discount,
+        None => return,
+    };
+
+    if let (Some(referrer_id), Some(credit)) = (&discount.referrer_id, discount.referrer_credit) {
+        let r = modify(
+            USER_COLLECTION,
+            [referrer_id],
+            referrer_id,
+            |mut user: User| {
+                user.referral_credit += credit;
+                user.modified = Utc::now();
+                Ok(user)
+            },
+        )
+        .await;
+        match r {
+            Ok(referrer) => {
+                if let Err(e) = send_custom_pn(
+                    &referrer,
+                    &format!(
+                        "Någon har använt din kod! Du har fått {} kr i rabatt på ditt nästa jobb.",
+                        credit
+                    ),
+                    None,
+                    &NOTIFICATION_HUB_ACCOUNT,
+                )
+                .await
+                {
+                    log(format!("Could not send PN in settle_discount due to {}", e));
+                }
+            }
+            Err(e) => {
+                log(format!(
+                    "Could not credit the referrer of payment {} due to {}",
+                    payment.id, e
+                ));
+            }
+        }
+    }
+}
+
+// Undoes the referral credit moved by apply_discount and settle_discount once the whole payment has
+// been refunded. The customer gets back the credit they used and the referrer loses what they got, as far as they
+// have not used it already. None of it should fail the refund so errors are only logged.
+pub async fn reverse_discount(payment: &Payment) {
+    let discount = match &payment.discount {
+        Some(discount) => discount,
+        None => return,
+    };
+
+    if !discount.credit.is_zero() {
+        let r = modify(
+            USER_COLLECTION,
+            [&discount.customer_id],
+            &discount.customer_id,
+            |mut user: User| {
+                user.referral_credit += discount.credit;
+                user.modified = Utc::now();
+                Ok(user)
+            },
+        )
+        .await;
+        if let Err(e) = r {
+            log(format!(
+                "Could not give back the referral credit used by refunded payment {} due to {}",
+                payment.id, e
+            ));
+        }
+    }
+
+    if let (Some(referrer_id), Some(credit)) = (&discount.referrer_id, discount.referrer_credit) {
+        let r = modify(
+            USER_COLLECTION,
+            [referrer_id],
+            referrer_id,
+            |mut user: User| {
+                // NOTE: Credit the referrer has already used is not taken back
+                user.referral_credit =
+                    std::cmp::max(user.referral_credit - credit, Decimal::zero());
+                user.modified = Utc::now();
+                Ok(user)
+            },
+        )
+        .await;
+        if let Err(e) = r {
+            log(format!(
+                "Could not take back the credit of the referrer of refunded payment {} due to {}",
+                payment.id, e
+            ));
+        }
+    }
+}
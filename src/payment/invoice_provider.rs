@@ -0,0 +1,126 @@
+use super::{Initiated, PaymentProvider, ProviderStatus, RefundOutcome};
+use crate::models::{Invoice, Payment, PaymentState, User};
+use crate::{tax, INVOICE_BANKGIRO};
+use chrono::Duration;
+use futures::future::{BoxFuture, FutureExt};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+// MAGIC NUMBER: The number of days the customer has to pay an invoice
+const DUE_DAYS: i64 = 30;
+
+// MAGIC NUMBER: The number of digits taken from the payment id, the OCR reference becomes two
+// digits longer with the length and check digits
+const REFERENCE_DIGITS: usize = 12;
+
+// Payments by invoice paid to our bankgiro. There is nobody to call on the other side, the
+// payment is marked as paid when the bank statement is reconciled or by a billing admin.
+pub struct InvoiceProvider;
+
+impl PaymentProvider for InvoiceProvider {
+    fn initiate<'a>(
+        &'a self,
+        payment: &'a mut Payment,
+        _payer: &'a User,
+    ) -> BoxFuture<'a, Result<Initiated, warp::Rejection>> {
+        async move {
+            let invoice = Invoice {
+                ocr: ocr_reference(&payment.id),
+                bankgiro: INVOICE_BANKGIRO.to_string(),
+                due_date: tax::today() + Duration::days(DUE_DAYS),
+            };
+            payment.invoice = Some(invoice.clone());
+            Ok(Initiated {
+                payment_request_token: None,
+                invoice: Some(invoice),
+            })
+        }
+        .boxed()
+    }
+
+    fn retrieve<'a>(
+        &'a self,
+        payment: &'a Payment,
+    ) -> BoxFuture<'a, Result<ProviderStatus, warp::Rejection>> {
+        async move {
+            Ok(match (&payment.payment_state, payment.payment_date) {
+                (PaymentState::Initialized, _) | (_, None) => ProviderStatus::Pending,
+                (PaymentState::Failed, _) => {
+                    ProviderStatus::Failed(format!("The invoice was never paid"))
+                }
+                (_, Some(date)) => ProviderStatus::Paid {
+                    amount: payment.amount,
+                    reference: payment.invoice.as_ref().map(|i| i.ocr.clone()),
+                    date,
+                },
+            })
+        }
+        .boxed()
+    }
+
+    // NOTE: Invoices are refunded by a billing admin making a bank transfer back to the customer,
+    // so the refund is done once it is requested
+    fn refund<'a>(
+        &'a self,
+        _payment: &'a Payment,
+        _refund_id: &'a str,
+        _amount: Decimal,
+    ) -> BoxFuture<'a, Result<RefundOutcome, warp::Rejection>> {
+        async move { Ok(RefundOutcome::Completed) }.boxed()
+    }
+
+    // NOTE: An unpaid invoice only exists with us, so there is nobody to tell
+    fn cancel<'a>(&'a self, _payment: &'a Payment) -> BoxFuture<'a, Result<(), warp::Rejection>> {
+        async move { Ok(()) }.boxed()
+    }
+}
+
+// Makes an OCR reference from the payment id. The reference ends with a length digit followed by
+// a luhn check digit, which lets the bank reject mistyped references.
+pub fn ocr_reference(payment_id: &str) -> String {
+    let number = match Uuid::parse_str(payment_id) {
+        Ok(id) => id.as_u128(),
+        Err(_) => payment_id
+            .bytes()
+            .fold(0u128, |n, b| n.wrapping_mul(31).wrapping_add(b as u128)),
+    };
+    let mut reference = format!(
+        "{:0width$}",
+        number % 10u128.pow(REFERENCE_DIGITS as u32),
+        width = REFERENCE_DIGITS
+    );
+    reference.push_str(&((REFERENCE_DIGITS + 2) % 10).to_string());
+    let check = luhn_check_digit(&reference);
+    reference.push_str(&check.to_string());
+    reference
+}
+
+pub fn is_valid_ocr(reference: &str) -> bool {
+    let len = reference.len();
+    len >= 2
+        && reference.chars().all(|c| c.is_ascii_digit())
+        && reference[len - 2..len - 1] == ((len % 10).to_string())
+        && luhn_check_digit(&reference[..len - 1]).to_string() == reference[len - 1..]
+}
+
+fn luhn_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 0 {
+                let d = d * 2;
+                if d > 9 {
+                    d - 9
+                } else {
+                    d
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    (10 - sum % 10) % 10
+}
@@ -0,0 +1,116 @@
+use crate::fault::Fault;
+use crate::models::{
+    Bid, Brokerage, Currency, DeductionType, Milestone, Payment, PaymentMethod, PaymentState,
+};
+use crate::PAYMENT_COLLECTION;
+use chrono::Utc;
+use cosmos_utils::query;
+use rust_decimal::prelude::{Decimal, Zero};
+use uuid::Uuid;
+use warp::reject;
+
+// A new payment of the whole bid, or only of one of its milestones
+pub fn new_payment(
+    bid: &Bid,
+    milestone: Option<&Milestone>,
+    payment_method: PaymentMethod,
+    deduction_type: Option<DeductionType>,
+) -> Payment {
+    let (amount, root_deduction) = match milestone {
+        Some(milestone) => (
+            milestone.amount,
+            bid.milestone_share(&milestone.id, bid.root_deduction),
+        ),
+        None => (bid.final_bid, bid.root_deduction),
+    };
+    let mut payment = Payment {
+        id: Uuid::new_v4().to_string(),
+        office_id: bid.office_id.clone(),
+        task_id: bid.task_id.clone(),
+        bid_id: bid.id.clone(),
+        milestone_id: milestone.map(|m| m.id.clone()),
+        change_order_id: None,
+        craftsman_id: bid.craftsman_id.clone(),
+        swish_payment_id: None,
+        payment_date: None,
+        payment_state: PaymentState::Initialized,
+        payment_method,
+        invoice: None,
+        amount,
+        currency: Currency::SEK,
+        deduction_type: if root_deduction.is_zero() {
+            None
+        } else {
+            deduction_type
+        },
+        root_deduction,
+        refunded_amount: Decimal::zero(),
+        refund_id: None,
+        discount: None,
+        brokerage: None,
+        receipt_id: None,
+        modified: Utc::now(),
+        deleted: false,
+    };
+    payment.brokerage = bid
+        .brokerage
+        .as_ref()
+        .map(|b| Brokerage::new(b.percentage, &payment.costs(bid, None)));
+    payment
+}
+
+// The payments of a bid that are in progress or have been paid, there is at most one per
+// milestone
+pub async fn active_payments(
+    office_id: &str,
+    bid_id: &str,
+) -> Result<Vec<Payment>, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.bidId = "{}""#,
+        PAYMENT_COLLECTION, bid_id
+    );
+    let payments: Vec<Payment> = query(PAYMENT_COLLECTION, [office_id], q, -1).await?;
+    Ok(payments
+        .into_iter()
+        .filter(|p| match p.payment_state {
+            PaymentState::Failed | PaymentState::Error(_) => false,
+            _ => !p.deleted,
+        })
+        .collect())
+}
+
+pub fn milestone_payment<'a>(payments: &'a [Payment], milestone_id: &str) -> Option<&'a Payment> {
+    payments
+        .iter()
+        .find(|p| p.milestone_id.as_deref() == Some(milestone_id))
+}
+
+// Makes sure every milestone before the given one has been paid into escrow or, if `approved` is
+// set, has been approved by the customer
+pub fn verify_earlier_milestones(
+    bid: &Bid,
+    milestone: &Milestone,
+    payments: &[Payment],
+    approved: bool,
+) -> Result<(), warp::Rejection> {
+    for earlier in bid
+        .milestones_in_order()
+        .into_iter()
+        .filter(|m| m.order < milestone.order)
+    {
+        let state = milestone_payment(payments, &earlier.id).map(|p| &p.payment_state);
+        let ok = match state {
+            Some(PaymentState::Finalized) | Some(PaymentState::PaidToCraftsman) => true,
+            Some(PaymentState::PaidToEscrow) => !approved,
+            _ => false,
+        };
+        if !ok {
+            return Err(reject::custom(Fault::Forbidden(format!(
+                "The milestone '{}' has to be {} first",
+                earlier.description,
+                if approved { "approved" } else { "paid" }
+            ))));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+// The ways customers can pay into escrow. Every payment method has a provider which knows how to
+// initiate, look up, refund and cancel payments of that method.
+
+mod provider;
+pub use provider::{provider, Initiated, PaymentProvider, ProviderStatus, RefundOutcome};
+
+mod swish_provider;
+pub use swish_provider::SwishProvider;
+
+mod invoice_provider;
+pub use invoice_provider::{is_valid_ocr, InvoiceProvider};
+
+mod transition;
+pub use transition::{cancel, escrow, fail, paid_to_escrow, refund, refunded};
+
+mod milestone;
+pub use milestone::{active_payments, milestone_payment, new_payment, verify_earlier_milestones};
+
+mod change_order;
+pub use change_order::{accepted_change_orders, new_change_order_payment};
+
+mod discount;
+pub use discount::{
+    apply_discount, normalize_code, release_discount, reverse_discount, settle_discount,
+};
@@ -0,0 +1,78 @@
+use super::{InvoiceProvider, SwishProvider};
+use crate::models::{Invoice, Payment, PaymentMethod, User};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+// What the customer needs in order to pay once a payment has been initiated
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Initiated {
+    // Used by the app to open swish
+    #[serde(skip_serializing_if = "crate::util::is_none")]
+    pub payment_request_token: Option<String>,
+
+    #[serde(skip_serializing_if = "crate::util::is_none")]
+    pub invoice: Option<Invoice>,
+}
+
+// The status of a payment as reported by the provider
+#[derive(Debug, Clone)]
+pub enum ProviderStatus {
+    // The customer has not paid yet
+    Pending,
+    Paid {
+        amount: Decimal,
+        // The provider's reference of the transaction
+        reference: Option<String>,
+        date: DateTime<Utc>,
+    },
+    // The customer declined or the provider could not handle the payment
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefundOutcome {
+    // The provider confirms the refund later through a callback
+    Pending,
+    // The customer has already been paid back
+    Completed,
+}
+
+// A way for customers to pay into escrow. Every provider is responsible for talking to whatever
+// is on the other side of the payment, the state of the payment in cosmos is handled by the
+// caller.
+pub trait PaymentProvider: Send + Sync {
+    // Asks the customer to pay. Provider specific information is set on the payment before it is
+    // stored by the caller.
+    fn initiate<'a>(
+        &'a self,
+        payment: &'a mut Payment,
+        payer: &'a User,
+    ) -> BoxFuture<'a, Result<Initiated, warp::Rejection>>;
+
+    // Asks the provider whether the customer has paid
+    fn retrieve<'a>(
+        &'a self,
+        payment: &'a Payment,
+    ) -> BoxFuture<'a, Result<ProviderStatus, warp::Rejection>>;
+
+    // Pays the customer back, either the whole payment or a part of it
+    fn refund<'a>(
+        &'a self,
+        payment: &'a Payment,
+        refund_id: &'a str,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<RefundOutcome, warp::Rejection>>;
+
+    // Withdraws a payment the customer has not paid yet
+    fn cancel<'a>(&'a self, payment: &'a Payment) -> BoxFuture<'a, Result<(), warp::Rejection>>;
+}
+
+pub fn provider(method: &PaymentMethod) -> Box<dyn PaymentProvider> {
+    match method {
+        PaymentMethod::Swish => Box::new(SwishProvider),
+        PaymentMethod::Invoice => Box::new(InvoiceProvider),
+    }
+}
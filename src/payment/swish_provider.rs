@@ -0,0 +1,184 @@
+use super::{Initiated, PaymentProvider, ProviderStatus, RefundOutcome};
+use crate::fault::Fault;
+use crate::models::{Currency, Payment, User};
+use crate::{
+    BASE_CALLBACK_URL, SWISH_CERT_PASS, SWISH_CERT_PATH, SWISH_INTERMEDIATE_ACCOUNT_NUMBER,
+};
+use futures::future::{BoxFuture, FutureExt};
+use rust_decimal::Decimal;
+use swish::SwishClient;
+use uuid::Uuid;
+use warp::reject;
+
+// Payments through Swish to our intermediate account. Swish calls us back when the customer has
+// paid or a refund has gone through.
+pub struct SwishProvider;
+
+// NOTE: When sending payment IDs to swish the ID has to be a simple UUID with all uppercase
+// characters
+fn simple_id(id: &str) -> Result<String, warp::Rejection> {
+    let id = Uuid::parse_str(id).map_err(|_| {
+        reject::custom(Fault::IllegalState(format!(
+            "Could not parse payment id as a UUID"
+        )))
+    })?;
+    let mut uuid_encode_buf = Uuid::encode_buffer();
+    Ok(id
+        .to_simple()
+        .encode_upper(&mut uuid_encode_buf)
+        .to_string())
+}
+
+async fn client() -> Result<SwishClient, warp::Rejection> {
+    SwishClient::new(SWISH_CERT_PATH.as_str(), &SWISH_CERT_PASS, None, None)
+        .await
+        .map_err(|e| reject::custom(Fault::from(e)))
+}
+
+impl PaymentProvider for SwishProvider {
+    fn initiate<'a>(
+        &'a self,
+        payment: &'a mut Payment,
+        payer: &'a User,
+    ) -> BoxFuture<'a, Result<Initiated, warp::Rejection>> {
+        async move {
+            let id = simple_id(&payment.id)?;
+            // Make payment to intermediate account, will create a payment on success
+            // https://toolit-api-play.azurewebsites.net/offices/<id>/tasks/<id>/bids/<id>/payments/<id>
+            let callback_url = format!(
+                "{}/offices/{}/tasks/{}/bids/{}/payments/{}/escrow",
+                BASE_CALLBACK_URL.as_str(),
+                payment.office_id,
+                payment.task_id,
+                payment.bid_id,
+                payment.id
+            );
+
+            let swish_client = client().await?;
+            let payment_req = swish::PaymentRequest::V2(swish::PaymentRequestV2 {
+                id: &id,
+                callback_url: &callback_url,
+                payee_alias: &SWISH_INTERMEDIATE_ACCOUNT_NUMBER,
+                amount: payment.amount,
+                currency: swish::Currency::SEK,
+                payee_payment_reference: None,
+                // NOTE: We are setting payer-alias to None in order to use the m-commerce feature
+                // and get a token back
+                payer_alias: None,
+                payer_ssn: Some(&payer.nid),
+                payer_age_limit: None,
+                message: Some(format!("Betalning för avslutat toolit arbete")),
+            });
+
+            let resp = swish_client
+                .payment_request(payment_req)
+                .await
+                .map_err(|e| Fault::from(e))?;
+            Ok(Initiated {
+                payment_request_token: resp.payment_request_token,
+                invoice: None,
+            })
+        }
+        .boxed()
+    }
+
+    fn retrieve<'a>(
+        &'a self,
+        payment: &'a Payment,
+    ) -> BoxFuture<'a, Result<ProviderStatus, warp::Rejection>> {
+        async move {
+            let id = simple_id(&payment.id)?;
+            let swish_payment_object = client()
+                .await?
+                .payment_retrieve_from_id(&id)
+                .await
+                .map_err(|e| Fault::from(e))?;
+
+            if swish_payment_object.id != id {
+                return Err(reject::custom(Fault::IllegalArgument(format!(
+                    "Payment id does not mach provided payment"
+                ))));
+            }
+
+            Ok(match swish_payment_object.status {
+                swish::PaymentStatus::PAID => ProviderStatus::Paid {
+                    amount: swish_payment_object.amount,
+                    reference: Some(swish_payment_object.payment_reference),
+                    date: swish_payment_object
+                        .date_paid
+                        .unwrap_or(swish_payment_object.date_created),
+                },
+                swish::PaymentStatus::CREATED => ProviderStatus::Pending,
+                s => ProviderStatus::Failed(format!(
+                    "Swish reported the payment as {:?} with the error {:?}",
+                    s, swish_payment_object.error_message
+                )),
+            })
+        }
+        .boxed()
+    }
+
+    fn refund<'a>(
+        &'a self,
+        payment: &'a Payment,
+        refund_id: &'a str,
+        amount: Decimal,
+    ) -> BoxFuture<'a, Result<RefundOutcome, warp::Rejection>> {
+        async move {
+            // NOTE we embed the refund id in the url in order to allow lookup by the finish
+            // function
+            let callback_url = format!(
+                "{}/offices/{}/tasks/{}/bids/{}/payments/{}/refund/{}/finish",
+                &*BASE_CALLBACK_URL,
+                payment.office_id,
+                payment.task_id,
+                payment.bid_id,
+                payment.id,
+                refund_id
+            );
+            let simple_payment_id = simple_id(&payment.id)?;
+
+            let refund_req = swish::RefundRequest {
+                // NOTE: We set the payer_payment_reference to the payment id in order to use this
+                // in the finishing step of the refund
+                payer_payment_reference: Some(&simple_payment_id),
+                original_payment_reference: payment.swish_payment_id.as_deref().ok_or_else(
+                    || {
+                        reject::custom(Fault::IllegalState(format!(
+                            "No swish ID not found in payment"
+                        )))
+                    },
+                )?,
+                callback_url: &callback_url,
+                payer_alias: &*SWISH_INTERMEDIATE_ACCOUNT_NUMBER,
+                payee_alias: None,
+                amount,
+                currency: match payment.currency {
+                    Currency::SEK => swish::Currency::SEK,
+                },
+                message: Some(format!("Refund due to cancelled job")),
+                instruction_uuid: Some(refund_id),
+            };
+            client()
+                .await?
+                .refund_request(refund_req, swish::Version::V2)
+                .await
+                .map_err(|e| Fault::from(e))?;
+            Ok(RefundOutcome::Pending)
+        }
+        .boxed()
+    }
+
+    fn cancel<'a>(&'a self, payment: &'a Payment) -> BoxFuture<'a, Result<(), warp::Rejection>> {
+        async move {
+            let id = simple_id(&payment.id)?;
+            client()
+                .await?
+                .cancel_payment_request(id, swish::Version::V1)
+                .await
+                .map_err(|e| Fault::from(e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
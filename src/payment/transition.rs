@@ -0,0 +1,408 @@
+use super::{provider, RefundOutcome};
+use crate::fault::Fault;
+use crate::ledger;
+use crate::models::{
+    Craftsman, Payment, PaymentMethod, PaymentState, Task, TaskActor, TaskState, User,
+};
+use crate::push::send_custom_pn;
+use crate::receipt;
+use crate::tax;
+use crate::util::log;
+use crate::{
+    CRAFTSMAN_COLLECTION, NOTIFICATION_HUB_ACCOUNT, PAYMENT_COLLECTION, TASK_COLLECTION,
+    USER_COLLECTION,
+};
+use chrono::{DateTime, Datelike, Utc};
+use chrono_tz::Europe::Stockholm;
+use cosmos_utils::{get, maybe_modify, modify, ModifyReturn};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use warp::reject;
+
+// Moves an initialized payment into escrow once the provider reports that the customer has paid
+// the full amount
+pub async fn escrow(
+    office_id: &str,
+    payment_id: &str,
+    amount: Decimal,
+    reference: Option<String>,
+    date: DateTime<Utc>,
+) -> Result<Payment, warp::Rejection> {
+    let payment = modify(PAYMENT_COLLECTION, [office_id], payment_id, |mut payment: Payment| {
+        if payment.amount != amount {
+            return Err(reject::custom(Fault::Unspecified(format!(
+                "Payment was not successful due to incorrect amount"
+            ))));
+        }
+        match payment.payment_state {
+            PaymentState::Initialized => {
+                payment.payment_state = PaymentState::PaidToEscrow;
+                payment.payment_date = Some(date);
+                if payment.payment_method == PaymentMethod::Swish {
+                    payment.swish_payment_id = reference.clone();
+                }
+                payment.modified = Utc::now();
+                Ok(payment)
+            }
+            s => Err(reject::custom(Fault::Unspecified(format!(
+                "Payment could not go through as it was not in the initialzed state but rather in {:?}",
+                s
+            )))),
+        }
+    })
+    .await?;
+    Ok(payment)
+}
+
+// Marks a payment the customer never paid as failed, which lets the bid be accepted again
+pub async fn fail(office_id: &str, payment_id: &str) -> Result<Payment, warp::Rejection> {
+    let payment = modify(
+        PAYMENT_COLLECTION,
+        [office_id],
+        payment_id,
+        |mut payment: Payment| match payment.payment_state {
+            PaymentState::Initialized => {
+                payment.payment_state = PaymentState::Failed;
+                payment.modified = Utc::now();
+                Ok(payment)
+            }
+            s => Err(reject::custom(Fault::Unspecified(format!(
+                "Payment could not be marked as failed as it was not Initialized but rather {:?}",
+                s
+            )))),
+        },
+    )
+    .await?;
+    super::release_discount(&payment).await;
+
+    // NOTE: The bid can be accepted again, or another one, once the payment of the accepted bid
+    // has failed
+    let reopened = maybe_modify(
+        TASK_COLLECTION,
+        [office_id],
+        &payment.task_id,
+        |mut task: Task| {
+            if task.state() != TaskState::BidAccepted || task.accepted_bid.is_some() {
+                return Ok(ModifyReturn::DontReplace(task));
+            }
+            match task.transition(
+                TaskState::Published,
+                TaskActor::System,
+                None,
+                Some("The payment failed"),
+            ) {
+                Ok(_) => Ok(ModifyReturn::Replace(task)),
+                Err(_) => Ok(ModifyReturn::DontReplace(task)),
+            }
+        },
+    )
+    .await;
+    if let Err(e) = reopened {
+        log(format!(
+            "Could not reopen task {} after payment {} failed due to {}",
+            payment.task_id, payment.id, e
+        ));
+    }
+    Ok(payment)
+}
+
+// Withdraws a payment from the provider before the customer has paid it
+pub async fn cancel(payment: &Payment) -> Result<Payment, warp::Rejection> {
+    provider(&payment.payment_method).cancel(payment).await?;
+    fail(&payment.office_id, &payment.id).await
+}
+
+// Everything that follows from the customer's money reaching escrow. None of it should fail the
+// payment so errors are only logged.
+pub async fn paid_to_escrow(payment: Payment) {
+    if let Err(e) = ledger::record_paid_to_escrow(&payment).await {
+        log(format!(
+            "Could not book escrow payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+    super::settle_discount(&payment).await;
+
+    // Set the task to have accepted this bid
+    let task = match modify(TASK_COLLECTION, [&payment.office_id], &payment.task_id, |mut task: Task| {
+        // NOTE: The later milestones of a bid are paid after the bid was accepted, the task keeps
+        // the payment it was accepted with
+        if task.accepted_bid.as_ref() == Some(&payment.bid_id) {
+            return Ok(task);
+        }
+        if let Some(prev_accepted) = task.accepted_bid {
+            log(format!("Accepted a bid when one was already accepted, new bid: {}, old bid: {}. This should not be possible", payment.bid_id.clone(), prev_accepted));
+        }
+        task.accepted_bid = Some(payment.bid_id.clone());
+        task.payment_id = Some(payment.id.clone());
+        task.modified = Utc::now();
+        if let Err(e) = task.transition(TaskState::InProgress, TaskActor::System, None, None) {
+            log(format!("The task {} could not be put in progress after its payment reached escrow due to {}", task.id, e));
+        }
+        Ok(task)
+    }).await {
+        Ok(task) => task,
+        Err(e) => {
+            log(format!("Could not change task after payment has gone through to escrow, this is a critical error due to {}", e));
+            return;
+        },
+    };
+
+    // The deduction counts towards the customer's yearly ceiling from when the payment is made
+    if let (Some(deduction_type), Some(payment_date)) =
+        (payment.deduction_type, payment.payment_date)
+    {
+        let year = payment_date.with_timezone(&Stockholm).year();
+        if let Err(e) =
+            tax::record_deduction(&task.user_id, year, deduction_type, payment.root_deduction).await
+        {
+            log(format!(
+                "Could not record the deduction of payment {} due to {:?}",
+                payment.id, e
+            ));
+        }
+    }
+
+    let receipt_payment = payment.clone();
+    let accepted_with = task.payment_id.as_ref() == Some(&payment.id);
+
+    // We run the code necessary to send out a PN in a separate thread in order to quicker give a
+    // response. This code should not cause a failure anyway.
+    tokio::task::spawn(async move {
+        let (craftsman, task_owner) = tokio::join!(
+            async {
+                let (craftsman, _): (Craftsman, _) = get(
+                    CRAFTSMAN_COLLECTION,
+                    [&payment.office_id],
+                    &payment.craftsman_id,
+                )
+                .await?;
+                let (user, _): (User, _) =
+                    get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+                Result::<_, warp::Rejection>::Ok(user)
+            },
+            async {
+                let (user, _): (User, _) =
+                    get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+                Result::<_, warp::Rejection>::Ok(user)
+            }
+        );
+        let (craftsman_user, task_owner): (User, User) = match (craftsman, task_owner) {
+            (Ok(c), Ok(t)) => (c, t),
+            (Err(e), _) | (_, Err(e)) => {
+                log(format!(
+                    "Could not send PN after escrow payment since cosmos get failed with {:?}",
+                    e
+                ));
+                return;
+            }
+        };
+
+        // Send PN to the craftsman
+        if let Err(e) = send_custom_pn(
+            &craftsman_user,
+            &if accepted_with {
+                format!("Jaa! {} har accepterat ditt bud!", task_owner.name())
+            } else if payment.change_order_id.is_some() {
+                format!("{} har betalat för tilläggsarbetet!", task_owner.name())
+            } else {
+                format!(
+                    "{} har betalat in nästa delbetalning för jobbet!",
+                    task_owner.name()
+                )
+            },
+            None,
+            &NOTIFICATION_HUB_ACCOUNT,
+        )
+        .await
+        {
+            log(format!(
+                "Could not send PN after escrow payment due to {}",
+                e
+            ));
+        }
+        // Send PN to the payer
+        if let Err(e) = send_custom_pn(
+            &task_owner,
+            &format!(
+                "Snyggt! Din betalning till {} gick igenom!",
+                craftsman_user.name()
+            ),
+            None,
+            &NOTIFICATION_HUB_ACCOUNT,
+        )
+        .await
+        {
+            log(format!(
+                "Could not send PN after escrow payment due to {}",
+                e
+            ));
+        }
+    });
+
+    // Send the receipt to the task owner, like the PNs this should not fail the request
+    tokio::task::spawn(async move {
+        if let Err(e) = receipt::issue_receipt(&receipt_payment.office_id, &receipt_payment).await {
+            log(format!(
+                "Could not issue the receipt of payment {} due to {:?}",
+                receipt_payment.id, e
+            ));
+        }
+    });
+}
+
+// Asks the provider to pay the customer back. The payment is marked as refunded right away if the
+// provider is done at once, otherwise once the provider calls us back. A payment is only ever
+// refunded once, asking again does nothing.
+pub async fn refund(payment: &Payment, amount: Decimal) -> Result<(), warp::Rejection> {
+    let mut uuid_encode_buf = Uuid::encode_buffer();
+    let refund_id = Uuid::new_v4();
+    let refund_id = refund_id.to_simple().encode_upper(&mut uuid_encode_buf);
+
+    // NOTE: The refund is stored first so that a retry, or another instance, does not pay the
+    // customer back again while the provider is being asked or before it has called us back
+    let requested = maybe_modify(
+        PAYMENT_COLLECTION,
+        [&payment.office_id],
+        &payment.id,
+        |mut payment: Payment| {
+            if payment.refund_id.is_some() {
+                return Ok(ModifyReturn::DontReplace(payment));
+            }
+            payment.refund_id = Some(refund_id.to_string());
+            payment.modified = Utc::now();
+            Ok(ModifyReturn::Replace(payment))
+        },
+    )
+    .await?;
+    let payment = match requested {
+        ModifyReturn::Replace(payment) => payment,
+        ModifyReturn::DontReplace(payment) => {
+            log(format!(
+                "Payment {} has already been refunded with {:?}, not refunding it again",
+                payment.id, payment.refund_id
+            ));
+            return Ok(());
+        }
+    };
+
+    let outcome = match provider(&payment.payment_method)
+        .refund(&payment, refund_id, amount)
+        .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            // NOTE: The provider did not take the refund so it may be asked for again
+            let cleared = maybe_modify(
+                PAYMENT_COLLECTION,
+                [&payment.office_id],
+                &payment.id,
+                |mut payment: Payment| {
+                    if payment.refund_id.as_deref() != Some(refund_id) {
+                        return Ok(ModifyReturn::DontReplace(payment));
+                    }
+                    payment.refund_id = None;
+                    payment.modified = Utc::now();
+                    Ok(ModifyReturn::Replace(payment))
+                },
+            )
+            .await;
+            if let Err(e) = cleared {
+                log(format!(
+                    "Could not clear the failed refund {} of payment {} due to {}",
+                    refund_id, payment.id, e
+                ));
+            }
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = ledger::record_refund_initialized(&payment, refund_id, amount).await {
+        log(format!(
+            "Could not book refund of payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+
+    // NOTE: Some providers pay the customer back right away instead of calling us back later
+    if outcome == RefundOutcome::Completed {
+        refunded(&payment.office_id, &payment.id, refund_id, amount).await?;
+    }
+    Ok(())
+}
+
+// Marks the payment as refunded once the customer has been paid back. Returns the payment if it
+// was not already refunded.
+pub async fn refunded(
+    office_id: &str,
+    payment_id: &str,
+    refund_id: &str,
+    amount: Decimal,
+) -> Result<Option<Payment>, warp::Rejection> {
+    let payment = maybe_modify(
+        PAYMENT_COLLECTION,
+        [office_id],
+        payment_id,
+        |mut payment: Payment| {
+            // NOTE: Swish may call us more than once for the same refund
+            if let PaymentState::Refunded = payment.payment_state {
+                return Ok(ModifyReturn::DontReplace(payment));
+            }
+            // NOTE: The rest of a partially refunded payment has been released to the craftsman
+            if payment.is_partially_refunded() {
+                return Ok(ModifyReturn::DontReplace(payment));
+            }
+            payment.payment_state = PaymentState::Refunded;
+            payment.modified = Utc::now();
+            Ok(ModifyReturn::Replace(payment))
+        },
+    )
+    .await?;
+    let payment = match payment {
+        ModifyReturn::Replace(payment) => payment,
+        ModifyReturn::DontReplace(payment) => {
+            // NOTE: The deduction stays as it was, it is up to the craftsman to adjust what is
+            // requested from Skatteverket after a partial refund
+            if payment.is_partially_refunded() {
+                if let Err(e) =
+                    ledger::record_refund_finished(&payment, refund_id, amount, true).await
+                {
+                    log(format!(
+                        "Could not book partial refund of payment {} in the ledger due to {:?}",
+                        payment.id, e
+                    ));
+                }
+            }
+            return Ok(None);
+        }
+    };
+
+    if let Err(e) = ledger::record_refund_finished(&payment, refund_id, amount, true).await {
+        log(format!(
+            "Could not book refund of payment {} in the ledger due to {:?}",
+            payment.id, e
+        ));
+    }
+    // NOTE: Only a whole refund moves the referral credit back, the rest of a partially refunded
+    // payment was paid for
+    super::reverse_discount(&payment).await;
+
+    // The refunded deduction no longer counts towards the customer's yearly ceiling
+    if let (Some(deduction_type), Some(payment_date)) =
+        (payment.deduction_type, payment.payment_date)
+    {
+        let year = payment_date.with_timezone(&Stockholm).year();
+        let r = async {
+            let (task, _): (Task, _) = get(TASK_COLLECTION, [office_id], &payment.task_id).await?;
+            tax::record_deduction(&task.user_id, year, deduction_type, -payment.root_deduction)
+                .await
+        };
+        if let Err(e) = r.await {
+            log(format!(
+                "Could not remove the deduction of refunded payment {} due to {:?}",
+                payment.id, e
+            ));
+        }
+    }
+    Ok(Some(payment))
+}
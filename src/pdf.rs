@@ -0,0 +1,234 @@
+// A minimal PDF writer for the documents we generate ourselves, like receipts and statements. It
+// only supports text in the standard Helvetica fonts and straight lines on A4 pages, which is all
+// we need and saves us from pulling in a large dependency.
+
+// A4 in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource_name(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    content: Vec<u8>,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Page {
+            content: Vec::new(),
+        }
+    }
+
+    // Writes text with its baseline starting at x, y where 0, 0 is the bottom left corner
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.content.extend(
+            format!(
+                "BT /{} {:.1} Tf {:.2} {:.2} Td (",
+                font.resource_name(),
+                size,
+                x,
+                y
+            )
+            .as_bytes(),
+        );
+        self.content.extend(encode(text));
+        self.content.extend(b") Tj ET\n");
+    }
+
+    // Writes text that ends at x, used for columns of amounts
+    pub fn text_right(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.text(x - text_width(text, size, font), y, size, font, text);
+    }
+
+    // Writes text broken into lines no wider than width and returns the baseline of the last line
+    pub fn paragraph(
+        &mut self,
+        x: f32,
+        y: f32,
+        size: f32,
+        font: Font,
+        width: f32,
+        text: &str,
+    ) -> f32 {
+        let mut y = y;
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && text_width(&candidate, size, font) > width {
+                self.text(x, y, size, font, &line);
+                y -= size * 1.3;
+                line = word.to_string();
+            } else {
+                line = candidate;
+            }
+        }
+        self.text(x, y, size, font, &line);
+        y
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.content
+            .extend(format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", x1, y1, x2, y2).as_bytes());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    title: String,
+    pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new(title: &str) -> Self {
+        Document {
+            title: title.to_string(),
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn add_page(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    pub fn render(&self) -> Vec<u8> {
+        // NOTE: Objects 1 to 5 are the catalog, the page tree, the two fonts and the document
+        // information. Every page then gets a page object followed by its content stream.
+        let page_id = |i: usize| 6 + 2 * i;
+        let kids = (0..self.pages.len())
+            .map(|i| format!("{} 0 R", page_id(i)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids,
+                self.pages.len()
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        ];
+        let mut info = b"<< /Producer (Toolit) /Title (".to_vec();
+        info.extend(encode(&self.title));
+        info.extend(b") >>");
+        objects.push(info);
+
+        for (i, page) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    page_id(i) + 1
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+            stream.extend(&page.content);
+            stream.extend(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend(object);
+            out.extend(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            out.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+// Encodes text as WinAnsi and escapes it for use in a PDF string. Characters that can not be
+// encoded are replaced with a question mark.
+fn encode(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        let b = match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                c as u8
+            }
+            ' '..='~' => c as u8,
+            // NOTE: WinAnsi matches latin-1 in this range, which covers the swedish characters
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        };
+        out.push(b);
+    }
+    out
+}
+
+// Approximates the width of text in points using the widths of Helvetica. Bold text is slightly
+// wider which we account for with a flat factor since it is only used for alignment.
+pub fn text_width(text: &str, size: f32, font: Font) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' ' | '!' | ',' | '.' | '/' | ':' | ';' | 'I' | '[' | '\\' | ']' | 'f' | 't' => 278,
+            'i' | 'j' | 'l' => 222,
+            '\'' => 191,
+            '(' | ')' | '-' | '`' | 'r' => 333,
+            '*' => 389,
+            '"' => 355,
+            '^' => 469,
+            'J' | 'c' | 'k' | 's' | 'v' | 'x' | 'y' | 'z' => 500,
+            '+' | '<' | '=' | '>' | '~' => 584,
+            'F' | 'T' | 'Z' => 611,
+            'A' | 'B' | 'E' | 'K' | 'P' | 'S' | 'V' | 'X' | 'Y' | 'Å' | 'Ä' => 667,
+            'C' | 'D' | 'H' | 'N' | 'R' | 'U' | 'w' => 722,
+            'G' | 'O' | 'Q' | 'Ö' => 778,
+            'M' | 'm' => 833,
+            '%' => 889,
+            'W' => 944,
+            '@' => 1015,
+            _ => 556,
+        })
+        .sum();
+    let width = units as f32 * size / 1000.0;
+    match font {
+        Font::Regular => width,
+        Font::Bold => width * 1.05,
+    }
+}
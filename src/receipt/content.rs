@@ -0,0 +1,166 @@
+use crate::models::{
+    Bid, BidLine, ChangeOrder, Craftsman, DeductionType, Payment, PaymentMethod, Task, User,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::{Europe::Stockholm, Tz};
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+
+// Everything that goes on the receipt the customer gets once their payment has reached escrow
+#[derive(Debug, Clone)]
+pub struct Receipt {
+    pub number: String,
+    pub date: NaiveDate,
+    pub payment_method: PaymentMethod,
+
+    pub customer_name: String,
+    pub customer_address: String,
+    pub customer_email: String,
+    // Set when the customer is a business
+    pub customer_org_number: Option<String>,
+
+    pub task_title: String,
+    pub task_address: String,
+    // Describes what part of the task the payment covers, if it does not cover all of it
+    pub part: Option<String>,
+
+    pub company_name: String,
+    pub org_number: String,
+    pub company_address: String,
+    pub craftsman_name: String,
+    pub f_tax: bool,
+
+    // The lines of the bid, only when the payment pays for all of the bid
+    pub lines: Vec<BidLine>,
+    // Labour cost without the tax added
+    pub labour_cost: Decimal,
+    pub labour_hours: Option<Decimal>,
+    // Material cost without the tax added
+    pub material_cost: Decimal,
+    // The full vat of the labour and material
+    pub vat: Decimal,
+    // The price including vat before the deduction
+    pub total: Decimal,
+    pub deduction_type: Option<DeductionType>,
+    pub root_deduction: Decimal,
+    // Taken off the price by a campaign or referral credit
+    pub discount: Decimal,
+    // What the customer paid
+    pub amount: Decimal,
+    // The work was sold without vat and the customer reports the vat
+    pub reverse_charge: bool,
+}
+
+impl Receipt {
+    pub fn new(
+        task: &Task,
+        bid: &Bid,
+        change_order: Option<&ChangeOrder>,
+        payment: &Payment,
+        craftsman: &Craftsman,
+        craftsman_user: &User,
+        customer: &User,
+    ) -> Self {
+        let tz: Tz = Stockholm;
+        let date: DateTime<Utc> = payment.payment_date.unwrap_or(payment.modified);
+        let date = date.with_timezone(&tz).naive_local().date();
+
+        // NOTE: A milestone payment only shows its share of the bid and a change order payment
+        // only the change order
+        let costs = payment.costs(bid, change_order);
+        let labour_cost = costs.labour_cost;
+        let material_cost = costs.material_cost;
+        let root_deduction = costs.root_deduction;
+
+        // NOTE: The vat stored on the bid is reduced by the share of the labour that got the
+        // deduction, a receipt has to show the full vat of the purchase
+        let total = payment.gross_amount() + root_deduction;
+        let vat = (total - labour_cost - material_cost)
+            .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+
+        let company_address = match &craftsman.company_postal {
+            Some(postal) => format!("{}, {}", craftsman.company_address, postal),
+            None => craftsman.company_address.clone(),
+        };
+
+        Receipt {
+            number: payment.invoice_number(),
+            date,
+            payment_method: payment.payment_method.clone(),
+            customer_name: customer.name(),
+            customer_address: customer.address.clone(),
+            customer_email: customer.email.clone(),
+            customer_org_number: if task.customer_type.is_business() {
+                task.org_number.clone()
+            } else {
+                None
+            },
+            task_title: task.title.clone(),
+            task_address: format!("{}, {} {}", task.address, task.postcode, task.city),
+            part: match change_order {
+                Some(c) => Some(format!("ÄTA-arbete: {}", c.description)),
+                None => payment
+                    .milestone_id
+                    .as_ref()
+                    .and_then(|id| bid.milestone(id))
+                    .map(|m| format!("Delbetalning: {}", m.description)),
+            },
+            company_name: craftsman.company_name.clone(),
+            org_number: craftsman.org_number.clone(),
+            company_address,
+            craftsman_name: craftsman_user.name(),
+            f_tax: craftsman.f_tax,
+            lines: if change_order.is_none() && payment.milestone_id.is_none() {
+                bid.lines.clone()
+            } else {
+                Vec::new()
+            },
+            labour_cost,
+            labour_hours: payment.labour_hours(bid, change_order),
+            material_cost,
+            vat,
+            total,
+            deduction_type: if root_deduction.is_zero() {
+                None
+            } else {
+                Some(payment.deduction_type.unwrap_or(DeductionType::Rot))
+            },
+            root_deduction,
+            discount: payment.discount_amount(),
+            amount: payment.amount,
+            reverse_charge: costs.reverse_charge,
+        }
+    }
+
+    // The name of the pdf file
+    pub fn file_name(&self) -> String {
+        format!("kvitto-{}.pdf", self.number)
+    }
+}
+
+// The swedish vat registration number of a company, which is its organization number with SE in
+// front and 01 after
+pub fn vat_number(org_number: &str) -> String {
+    let digits: String = org_number.chars().filter(|c| c.is_ascii_digit()).collect();
+    format!("SE{}01", digits)
+}
+
+// Formats an amount the swedish way, e.g. 12 345,50 kr
+pub fn kronor(amount: Decimal) -> String {
+    let amount = amount.round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let negative = amount < Decimal::zero();
+    let s = format!("{:.2}", amount.abs());
+    let (whole, fraction) = s.split_at(s.len() - 3);
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(' ');
+        }
+        grouped.push(c);
+    }
+    format!(
+        "{}{},{} kr",
+        if negative { "-" } else { "" },
+        grouped,
+        &fraction[1..]
+    )
+}
@@ -0,0 +1,135 @@
+use super::content::{kronor, Receipt};
+use super::render::receipt_pdf;
+use crate::models::{Bid, ChangeOrder, Craftsman, Payment, Task, User};
+use crate::util::log;
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, CRAFTSMAN_COLLECTION, PAYMENT_COLLECTION,
+    RECEIPT_STORAGE_CONTAINER, SENDGRID_API_KEY, TASK_COLLECTION, USER_COLLECTION,
+};
+use chrono::Utc;
+use cosmos_utils::{get, modify, upload_bytes};
+use sendgrid::v3::*;
+
+// Builds the receipt of a payment that has reached escrow, stores it in the blob storage and
+// emails it to the customer. The receipt is stored under the payment id so issuing it again
+// replaces the old one.
+pub async fn issue_receipt(office_id: &str, payment: &Payment) -> Result<String, warp::Rejection> {
+    let (c, t) = tokio::join!(
+        async {
+            let (craftsman, _): (Craftsman, _) =
+                get(CRAFTSMAN_COLLECTION, [office_id], &payment.craftsman_id).await?;
+            let (craftsman_user, _): (User, _) =
+                get(USER_COLLECTION, [&craftsman.user_id], &craftsman.user_id).await?;
+            Result::<_, warp::Rejection>::Ok((craftsman, craftsman_user))
+        },
+        async {
+            let (task, _): (Task, _) = get(TASK_COLLECTION, [office_id], &payment.task_id).await?;
+            let (bid, _): (Bid, _) = get(BID_COLLECTION, [office_id], &payment.bid_id).await?;
+            let change_order: Option<ChangeOrder> = match &payment.change_order_id {
+                Some(id) => Some(get(CHANGE_ORDER_COLLECTION, [office_id], id).await?.0),
+                None => None,
+            };
+            let (customer, _): (User, _) =
+                get(USER_COLLECTION, [&task.user_id], &task.user_id).await?;
+            Result::<_, warp::Rejection>::Ok((task, bid, change_order, customer))
+        }
+    );
+    let (craftsman, craftsman_user): (Craftsman, User) = c?;
+    let (task, bid, change_order, customer) = t?;
+
+    let receipt = Receipt::new(
+        &task,
+        &bid,
+        change_order.as_ref(),
+        payment,
+        &craftsman,
+        &craftsman_user,
+        &customer,
+    );
+    let pdf = receipt_pdf(&receipt);
+
+    let receipt_id = upload_bytes(
+        pdf.clone(),
+        "application/pdf",
+        &*RECEIPT_STORAGE_CONTAINER,
+        &format!("{}.pdf", payment.id),
+    )
+    .await?;
+    modify(
+        PAYMENT_COLLECTION,
+        [office_id],
+        &payment.id,
+        |mut payment: Payment| {
+            payment.receipt_id = Some(receipt_id.clone());
+            payment.modified = Utc::now();
+            Ok(payment)
+        },
+    )
+    .await?;
+
+    let mut map = SGMap::new();
+    map.insert(String::from("userFirstName"), customer.first_name.clone());
+    map.insert(String::from("userLastName"), customer.last_name.clone());
+    map.insert(
+        String::from("craftmanCompanyName"),
+        craftsman.company_name.clone(),
+    );
+    map.insert(
+        String::from("craftmanCompanyNid"),
+        craftsman.org_number.clone(),
+    );
+    map.insert(
+        String::from("craftsmanFirstName"),
+        craftsman_user.first_name.clone(),
+    );
+    map.insert(
+        String::from("craftsmanLastName"),
+        craftsman_user.last_name.clone(),
+    );
+    map.insert(String::from("jobTitle"), task.title.clone());
+    map.insert(
+        String::from("jobTransactionDate"),
+        receipt.date.format("%Y-%m-%d").to_string(),
+    );
+    map.insert(
+        String::from("jobCraftmanPrice"),
+        kronor(receipt.labour_cost),
+    );
+    map.insert(
+        String::from("craftmanMaterialCost"),
+        kronor(receipt.material_cost),
+    );
+    map.insert(
+        String::from("rotavdrag"),
+        if receipt.deduction_type.is_none() {
+            String::from("Ej applicerbart")
+        } else {
+            kronor(receipt.root_deduction)
+        },
+    );
+    map.insert(String::from("vat"), kronor(receipt.vat));
+    map.insert(String::from("jobSum"), kronor(receipt.amount));
+
+    let p = Personalization::new(Email::new("support@toolitapp.com"))
+        .add_to(Email::new(&customer.email))
+        .add_dynamic_template_data(map);
+
+    let attachment = Attachment::new()
+        .set_content(&pdf)
+        .set_filename(receipt.file_name())
+        .set_mime_type("application/pdf");
+
+    let m = Message::new(Email::new("support@toolitapp.com"))
+        .set_template_id("d-e734aff83ce247a3b6ce0b30306b17c9")
+        .add_personalization(p)
+        .add_attachment(attachment);
+    let sender = Sender::new(SENDGRID_API_KEY.to_string());
+    if let Err(e) = sender.send(&m).await {
+        log(format!(
+            "Could not send the receipt of payment {} due to {:?}",
+            payment.id, e
+        ));
+    }
+
+    Ok(receipt_id)
+}
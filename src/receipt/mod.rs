@@ -0,0 +1,10 @@
+// The receipts customers get when their payment has reached escrow. A receipt is rendered as a
+// pdf, kept in the blob storage and emailed to the customer.
+
+mod content;
+pub use content::kronor;
+
+mod render;
+
+mod issue;
+pub use issue::issue_receipt;
@@ -0,0 +1,222 @@
+use super::content::{kronor, vat_number, Receipt};
+use crate::models::{BidLineKind, DeductionType, PaymentMethod};
+use crate::pdf::{Document, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+
+const MARGIN: f32 = 56.0;
+
+// MAGIC NUMBER: The lines of a bid that fit on the page together with the rest of the receipt
+const MAX_LINES: usize = 15;
+
+// Renders the receipt as a single A4 pdf page
+pub fn receipt_pdf(receipt: &Receipt) -> Vec<u8> {
+    let mut page = Page::new();
+    let right = PAGE_WIDTH - MARGIN;
+    let mut y = PAGE_HEIGHT - MARGIN - 16.0;
+
+    page.text(MARGIN, y, 22.0, Font::Bold, "Kvitto");
+    page.text_right(right, y, 10.0, Font::Regular, "Toolit");
+    y -= 28.0;
+    page.text(
+        MARGIN,
+        y,
+        10.0,
+        Font::Regular,
+        &format!("Kvittonummer: {}", receipt.number),
+    );
+    page.text_right(
+        right,
+        y,
+        10.0,
+        Font::Regular,
+        &format!("Betalningsdatum: {}", receipt.date.format("%Y-%m-%d")),
+    );
+    y -= 34.0;
+
+    // The customer to the left and the craftsman's company to the right
+    let column = PAGE_WIDTH / 2.0;
+    page.text(MARGIN, y, 11.0, Font::Bold, "Kund");
+    page.text(column, y, 11.0, Font::Bold, "Utförare");
+    y -= 16.0;
+    let customer_org_number = receipt
+        .customer_org_number
+        .as_ref()
+        .map(|org_number| format!("Org.nr: {}", org_number));
+    let mut customer = vec![
+        receipt.customer_name.as_str(),
+        receipt.customer_address.as_str(),
+        receipt.customer_email.as_str(),
+    ];
+    if let Some(org_number) = &customer_org_number {
+        customer.push(org_number.as_str());
+    }
+    let org_number = format!("Org.nr: {}", receipt.org_number);
+    let f_tax = format!(
+        "Godkänd för F-skatt: {}",
+        if receipt.f_tax { "Ja" } else { "Nej" }
+    );
+    let contact = format!("Kontaktperson: {}", receipt.craftsman_name);
+    let company = [
+        receipt.company_name.as_str(),
+        receipt.company_address.as_str(),
+        org_number.as_str(),
+        f_tax.as_str(),
+        contact.as_str(),
+    ];
+    for (i, line) in company.iter().enumerate() {
+        if let Some(customer_line) = customer.get(i) {
+            page.text(MARGIN, y, 10.0, Font::Regular, customer_line);
+        }
+        page.text(column, y, 10.0, Font::Regular, line);
+        y -= 14.0;
+    }
+    y -= 20.0;
+
+    page.text(MARGIN, y, 11.0, Font::Bold, "Uppdrag");
+    y -= 16.0;
+    y = page.paragraph(
+        MARGIN,
+        y,
+        10.0,
+        Font::Regular,
+        right - MARGIN,
+        &receipt.task_title,
+    );
+    y -= 14.0;
+    page.text(MARGIN, y, 10.0, Font::Regular, &receipt.task_address);
+    if let Some(part) = &receipt.part {
+        y -= 14.0;
+        y = page.paragraph(MARGIN, y, 10.0, Font::Regular, right - MARGIN, part);
+    }
+    y -= 30.0;
+
+    // Price breakdown
+    page.text(MARGIN, y, 11.0, Font::Bold, "Specifikation");
+    page.text_right(right, y, 11.0, Font::Bold, "Belopp");
+    y -= 8.0;
+    page.line(MARGIN, y, right, y);
+    y -= 16.0;
+
+    // NOTE: The lines are what the craftsman charged for, the rows below sum them up
+    if !receipt.lines.is_empty() {
+        for line in receipt.lines.iter().take(MAX_LINES) {
+            let kind = match line.kind {
+                BidLineKind::Labour => "arbete",
+                BidLineKind::Material => "material",
+            };
+            page.text(
+                MARGIN,
+                y,
+                9.0,
+                Font::Regular,
+                &format!(
+                    "{} ({}), {} {} à {}",
+                    line.description,
+                    kind,
+                    line.quantity.normalize(),
+                    line.unit,
+                    kronor(line.unit_price)
+                ),
+            );
+            page.text_right(right, y, 9.0, Font::Regular, &kronor(line.amount()));
+            y -= 14.0;
+        }
+        if receipt.lines.len() > MAX_LINES {
+            page.text(
+                MARGIN,
+                y,
+                9.0,
+                Font::Regular,
+                &format!("... och {} rader till", receipt.lines.len() - MAX_LINES),
+            );
+            y -= 14.0;
+        }
+        y += 6.0;
+        page.line(MARGIN, y, right, y);
+        y -= 16.0;
+    }
+
+    let labour = match receipt.labour_hours {
+        Some(hours) => format!("Arbetskostnad exkl. moms ({} timmar)", hours.normalize()),
+        None => String::from("Arbetskostnad exkl. moms"),
+    };
+    let mut rows = vec![
+        (labour, kronor(receipt.labour_cost)),
+        (
+            String::from("Materialkostnad exkl. moms"),
+            kronor(receipt.material_cost),
+        ),
+    ];
+    if receipt.reverse_charge {
+        rows.push((String::from("Summa exkl. moms"), kronor(receipt.total)));
+    } else {
+        rows.push((String::from("Moms"), kronor(receipt.vat)));
+        rows.push((String::from("Summa inkl. moms"), kronor(receipt.total)));
+    }
+    if let Some(deduction_type) = receipt.deduction_type {
+        let name = match deduction_type {
+            DeductionType::Rot => "ROT-avdrag",
+            DeductionType::Rut => "RUT-avdrag",
+        };
+        rows.push((String::from(name), kronor(-receipt.root_deduction)));
+    }
+    if !receipt.discount.is_zero() {
+        rows.push((String::from("Rabatt"), kronor(-receipt.discount)));
+    }
+    for (name, amount) in rows.iter() {
+        page.text(MARGIN, y, 10.0, Font::Regular, name);
+        page.text_right(right, y, 10.0, Font::Regular, amount);
+        y -= 16.0;
+    }
+    y += 8.0;
+    page.line(MARGIN, y, right, y);
+    y -= 18.0;
+    page.text(MARGIN, y, 12.0, Font::Bold, "Betalt belopp");
+    page.text_right(right, y, 12.0, Font::Bold, &kronor(receipt.amount));
+    y -= 16.0;
+    let method = match receipt.payment_method {
+        PaymentMethod::Swish => "Swish",
+        PaymentMethod::Invoice => "Faktura",
+    };
+    page.text(
+        MARGIN,
+        y,
+        10.0,
+        Font::Regular,
+        &format!("Betalningssätt: {}", method),
+    );
+    y -= 30.0;
+
+    // NOTE: The payment is held by Toolit until the customer has approved the work, which the
+    // customer should be made aware of
+    let mut notes = vec![String::from(
+        "Betalningen hålls av Toolit tills du har godkänt det utförda arbetet, därefter betalas den ut till utföraren.",
+    )];
+    if receipt.deduction_type.is_some() {
+        notes.push(String::from(
+            "Avdraget är preliminärt. Utföraren begär utbetalning av avdraget från Skatteverket efter att arbetet är betalt. Om Skatteverket inte beviljar avdraget kan du bli skyldig att betala mellanskillnaden.",
+        ));
+    }
+    // NOTE: An invoice under reverse charge has to say so and carry the vat numbers of both the
+    // seller and the buyer
+    if receipt.reverse_charge {
+        let mut note = format!(
+            "Omvänd betalningsskyldighet för byggtjänster. Moms debiteras inte, köparen redovisar momsen. Säljarens momsreg.nr: {}.",
+            vat_number(&receipt.org_number)
+        );
+        if let Some(org_number) = &receipt.customer_org_number {
+            note.push_str(&format!(
+                " Köparens momsreg.nr: {}.",
+                vat_number(org_number)
+            ));
+        }
+        notes.push(note);
+    }
+    for note in notes.iter() {
+        y = page.paragraph(MARGIN, y, 9.0, Font::Regular, right - MARGIN, note);
+        y -= 16.0;
+    }
+
+    let mut document = Document::new(&format!("Kvitto {}", receipt.number));
+    document.add_page(page);
+    document.render()
+}
@@ -0,0 +1,274 @@
+use crate::models::{Bid, ChangeOrder, CraftType, Payment, Task, User};
+use crate::tax::rules_at;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::{Europe::Stockholm, Tz};
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+
+// MAGIC NUMBER: Skatteverket does not accept more than 100 cases in a single request
+const MAX_CASES: usize = 100;
+
+// The kind of work as categorized by Skatteverket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkKind {
+    Construction,
+    Electricity,
+    Plumbing,
+    Painting,
+}
+
+impl WorkKind {
+    pub fn from_craft(craft: &CraftType) -> Self {
+        match craft {
+            CraftType::Plumber => WorkKind::Plumbing,
+            CraftType::Electrician => WorkKind::Electricity,
+            CraftType::Painter => WorkKind::Painting,
+            CraftType::Carpenter | CraftType::FloorLayer | CraftType::Tiler => {
+                WorkKind::Construction
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Property {
+    Designation(String),
+    Apartment {
+        number: String,
+        union_org_number: String,
+    },
+}
+
+// A single case ("ärende") in a request for payment. All amounts are in whole kronor as required
+// by Skatteverket.
+#[derive(Debug, Clone)]
+pub struct RotCase {
+    pub task_title: String,
+    pub buyer_nid: String,
+    pub payment_date: NaiveDate,
+    // The labour cost including vat
+    pub labour_price: Decimal,
+    // What the customer paid for the labour
+    pub paid_amount: Decimal,
+    // What the craftsman requests from Skatteverket
+    pub requested_amount: Decimal,
+    // The most that can be requested given the rules at the time of payment
+    pub max_requested_amount: Decimal,
+    pub invoice_number: String,
+    pub property: Option<Property>,
+    pub work_kind: Option<WorkKind>,
+    // The hours of the labour that got the deduction
+    pub hours: Option<Decimal>,
+    // The material cost and the labour that did not get the deduction, including vat
+    pub material_cost: Decimal,
+}
+
+impl RotCase {
+    pub fn new(
+        task: &Task,
+        bid: &Bid,
+        change_order: Option<&ChangeOrder>,
+        payment: &Payment,
+        customer: &User,
+    ) -> Self {
+        let tz: Tz = Stockholm;
+        let payment_date: DateTime<Utc> = payment.payment_date.unwrap_or(payment.modified);
+        let payment_date = payment_date.with_timezone(&tz).naive_local().date();
+        let rules = rules_at(payment_date);
+        let vat_percentage = rules.vat_percentage;
+
+        // NOTE: Every milestone and change order payment is its own case
+        let costs = payment.costs(bid, change_order);
+        // NOTE: Only the labour that got the deduction is the labour of the case, the rest of the
+        // labour is reported with the material so that the case adds up to the whole job
+        let labour_cost = costs.deductible_labour_cost;
+        let material_cost = costs.material_cost + costs.labour_cost - costs.deductible_labour_cost;
+        let hours = payment.labour_hours(bid, change_order).map(|hours| {
+            if costs.labour_cost.is_zero() {
+                hours
+            } else {
+                hours * costs.deductible_labour_cost / costs.labour_cost
+            }
+        });
+
+        let labour_price = (labour_cost + labour_cost * vat_percentage)
+            .round_dp_with_strategy(0, RoundingStrategy::BankersRounding);
+        // NOTE: We never request more than the deduction that was given on the bid, so the
+        // deduction is rounded down to whole kronor
+        let requested_amount = costs.root_deduction.trunc();
+        let material_cost = (material_cost + material_cost * vat_percentage)
+            .round_dp_with_strategy(0, RoundingStrategy::BankersRounding);
+
+        let property = match (
+            &task.property_designation,
+            &task.apartment_number,
+            &task.realestate_union,
+        ) {
+            (Some(designation), _, _) => Some(Property::Designation(designation.clone())),
+            (None, Some(number), Some(union_org_number)) => Some(Property::Apartment {
+                number: number.clone(),
+                union_org_number: union_org_number.clone(),
+            }),
+            _ => None,
+        };
+
+        RotCase {
+            task_title: task.title.clone(),
+            buyer_nid: customer.nid.clone(),
+            payment_date,
+            labour_price,
+            paid_amount: labour_price - requested_amount,
+            requested_amount,
+            max_requested_amount: (labour_price * rules.rot_percentage).trunc(),
+            invoice_number: payment.invoice_number(),
+            property,
+            work_kind: task.crafts.get(0).map(WorkKind::from_craft),
+            hours,
+            material_cost,
+        }
+    }
+
+    // Validates the case against the rules of Skatteverket's schema, returning a description of
+    // every broken rule
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut error = |msg: String| errors.push(format!("{}: {}", self.task_title, msg));
+
+        if !is_valid_nid(&self.buyer_nid) {
+            error(format!(
+                "the customer does not have a valid personal identity number"
+            ));
+        }
+        if self.requested_amount < Decimal::new(1, 0) {
+            error(format!("the requested amount has to be at least 1 kr"));
+        }
+        if self.requested_amount > self.max_requested_amount {
+            error(format!(
+                "the requested amount {} is more than the {} allowed for the labour price {}",
+                self.requested_amount, self.max_requested_amount, self.labour_price
+            ));
+        }
+        if self.paid_amount < Decimal::zero() {
+            error(format!("the paid amount can not be negative"));
+        }
+        match &self.property {
+            Some(Property::Designation(designation)) => {
+                if designation.trim().is_empty() || designation.chars().count() > 40 {
+                    error(format!(
+                        "the property designation has to be between 1 and 40 characters"
+                    ));
+                }
+            }
+            Some(Property::Apartment {
+                number,
+                union_org_number,
+            }) => {
+                if number.len() != 4 || !number.chars().all(|c| c.is_ascii_digit()) {
+                    error(format!("the apartment number has to be four digits"));
+                }
+                if !is_valid_org_number(union_org_number) {
+                    error(format!(
+                        "the housing association does not have a valid organization number"
+                    ));
+                }
+            }
+            None => {
+                error(format!(
+                    "either a property designation or an apartment number and housing association is required"
+                ));
+            }
+        }
+        if self.work_kind.is_none() {
+            error(format!("the task does not have any craft"));
+        }
+        match self.hours {
+            Some(hours) if hours > Decimal::zero() => (),
+            _ => error(format!("the bid does not state the hours of labour")),
+        }
+        if self.invoice_number.is_empty() || self.invoice_number.chars().count() > 20 {
+            error(format!(
+                "the invoice number has to be between 1 and 20 characters"
+            ));
+        }
+        errors
+    }
+}
+
+// Validates the rules that concern the request as a whole, like the number of cases and the
+// yearly ceiling for every customer
+pub fn validate_request(cases: &[RotCase], year: i32, today: NaiveDate) -> Vec<String> {
+    let mut errors = Vec::new();
+    if cases.is_empty() {
+        errors.push(format!("There are no paid ROT tasks for {}", year));
+    }
+    if cases.len() > MAX_CASES {
+        errors.push(format!(
+            "A request can contain at most {} cases but there are {}",
+            MAX_CASES,
+            cases.len()
+        ));
+    }
+    // NOTE: The request has to reach Skatteverket before the end of January the year after the
+    // payment was made
+    if today > NaiveDate::from_ymd(year + 1, 1, 31) {
+        errors.push(format!(
+            "Requests for payments made in {} had to be sent before {}-01-31",
+            year,
+            year + 1
+        ));
+    }
+    for case in cases {
+        if case.payment_date.year() != year {
+            errors.push(format!("{}: was not paid during {}", case.task_title, year));
+        }
+        let total = cases
+            .iter()
+            .filter(|c| c.buyer_nid == case.buyer_nid)
+            .fold(Decimal::zero(), |sum, c| sum + c.requested_amount);
+        let cap = rules_at(case.payment_date).rot_yearly_cap;
+        if total > cap {
+            errors.push(format!(
+                "{}: the customer's requested deductions sum to {} kr which is more than {} kr",
+                case.task_title, total, cap
+            ));
+        }
+    }
+    errors
+}
+
+// A personal identity number in the form YYYYMMDDNNNN where the last digit is a luhn checksum of
+// the ten last digits
+pub fn is_valid_nid(nid: &str) -> bool {
+    nid.len() == 12 && nid.chars().all(|c| c.is_ascii_digit()) && luhn(&nid[2..])
+}
+
+// An organization number in the form NNNNNNNNNN, optionally with a hyphen after the sixth digit,
+// where the third digit is at least 2
+pub fn is_valid_org_number(org_number: &str) -> bool {
+    let digits: String = org_number.chars().filter(|c| *c != '-').collect();
+    digits.len() == 10
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && digits.as_bytes()[2] >= b'2'
+        && luhn(&digits)
+}
+
+fn luhn(digits: &str) -> bool {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| {
+            if i % 2 == 1 {
+                let d = d * 2;
+                if d > 9 {
+                    d - 9
+                } else {
+                    d
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
@@ -0,0 +1,8 @@
+// Generation of the requests for payment ("begäran om utbetalning") that craftsmen send to
+// Skatteverket to get paid the ROT deductions they have given their customers.
+
+mod claim;
+pub use claim::{is_valid_org_number, validate_request, RotCase};
+
+mod xml;
+pub use xml::rot_request_xml;
@@ -0,0 +1,102 @@
+use super::claim::{Property, RotCase, WorkKind};
+use rust_decimal::Decimal;
+
+// NOTE: Version 6 of Skatteverket's schema for requests for payment of ROT and RUT deductions
+const NAMESPACE: &str = "http://xmls.skatteverket.se/se/skatteverket/ht/begaran/6.0";
+const COMPONENT_NAMESPACE: &str =
+    "http://xmls.skatteverket.se/se/skatteverket/ht/komponent/begaran/6.0";
+
+// Renders a request for payment ("begäran om utbetalning") of ROT deductions. The cases are
+// expected to already have been validated.
+pub fn rot_request_xml(name: &str, cases: &[RotCase]) -> String {
+    let mut out = String::new();
+    out.push_str(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#);
+    out.push('\n');
+    out.push_str(&format!(
+        r#"<p:Begaran xmlns:p="{}" xmlns:i="{}">"#,
+        NAMESPACE, COMPONENT_NAMESPACE
+    ));
+    out.push('\n');
+    out.push_str(&element(1, "i:NamnPaBegaran", name));
+    out.push_str("  <i:RotBegaran>\n");
+    for case in cases {
+        out.push_str("    <i:Arenden>\n");
+        out.push_str(&element(3, "i:Kopare", &case.buyer_nid));
+        out.push_str(&element(
+            3,
+            "i:BetalningsDatum",
+            &case.payment_date.format("%Y-%m-%d").to_string(),
+        ));
+        out.push_str(&element(3, "i:PrisForArbete", &kronor(case.labour_price)));
+        out.push_str(&element(3, "i:BetaltBelopp", &kronor(case.paid_amount)));
+        out.push_str(&element(
+            3,
+            "i:BegartBelopp",
+            &kronor(case.requested_amount),
+        ));
+        out.push_str(&element(3, "i:FakturaNr", &case.invoice_number));
+        out.push_str(&element(3, "i:Ovrigkostnad", "0"));
+        match &case.property {
+            Some(Property::Designation(designation)) => {
+                out.push_str(&element(3, "i:Fastighetsbeteckning", designation));
+            }
+            Some(Property::Apartment {
+                number,
+                union_org_number,
+            }) => {
+                out.push_str(&element(3, "i:LagenhetsNr", number));
+                out.push_str(&element(
+                    3,
+                    "i:BrfOrgNr",
+                    &union_org_number.replace('-', ""),
+                ));
+            }
+            None => (),
+        }
+        if let (Some(work_kind), Some(hours)) = (case.work_kind, case.hours) {
+            let tag = match work_kind {
+                WorkKind::Construction => "i:Bygg",
+                WorkKind::Electricity => "i:El",
+                WorkKind::Plumbing => "i:Vvs",
+                WorkKind::Painting => "i:MalningTapetsering",
+            };
+            out.push_str("      <i:UtfortArbete>\n");
+            out.push_str(&format!("        <{}>\n", tag));
+            out.push_str(&element(5, "i:AntalTimmar", &kronor(hours)));
+            out.push_str(&element(
+                5,
+                "i:Materialkostnad",
+                &kronor(case.material_cost),
+            ));
+            out.push_str(&format!("        </{}>\n", tag));
+            out.push_str("      </i:UtfortArbete>\n");
+        }
+        out.push_str("    </i:Arenden>\n");
+    }
+    out.push_str("  </i:RotBegaran>\n");
+    out.push_str("</p:Begaran>\n");
+    out
+}
+
+fn element(depth: usize, tag: &str, value: &str) -> String {
+    format!(
+        "{}<{}>{}</{}>\n",
+        "  ".repeat(depth),
+        tag,
+        escape(value),
+        tag
+    )
+}
+
+// Skatteverket only accepts whole numbers
+fn kronor(d: Decimal) -> String {
+    d.round().to_string()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,239 @@
+use crate::ledger;
+use crate::models::{
+    Bid, ChangeOrder, Craftsman, DeductionType, Office, Payment, PaymentState, Task,
+};
+use crate::util::is_none;
+use crate::{
+    BID_COLLECTION, CHANGE_ORDER_COLLECTION, CRAFTSMAN_COLLECTION, OFFICE_COLLECTION,
+    PAYMENT_COLLECTION, TASK_COLLECTION,
+};
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::{Europe::Stockholm, Tz};
+use cosmos_utils::{get, query};
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// One payment the craftsman got paid for during the year
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementLine {
+    pub payment_id: String,
+    pub task_id: String,
+    pub task_title: String,
+    pub date: NaiveDate,
+    // Describes what part of the task the payment covers, if it does not cover all of it
+    #[serde(skip_serializing_if = "is_none")]
+    pub part: Option<String>,
+    // The price of the work including vat before the deduction, without what was refunded
+    pub gross_amount: Decimal,
+    // The full vat of the labour and material
+    pub vat: Decimal,
+    #[serde(skip_serializing_if = "is_none")]
+    pub deduction_type: Option<DeductionType>,
+    pub root_deduction: Decimal,
+    // The brokerage withheld by Toolit including its vat
+    pub brokerage: Decimal,
+    pub brokerage_vat: Decimal,
+    // What the craftsman gets, which is what the customer paid less the refunds and the brokerage
+    pub payout: Decimal,
+    // The payout has left Toolit, otherwise it is still owed to the craftsman
+    pub paid_out: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    pub year: i32,
+    pub craftsman_id: String,
+    pub company_name: String,
+    pub org_number: String,
+    pub craftsman_name: String,
+
+    pub completed_tasks: usize,
+    pub gross_amount: Decimal,
+    pub vat: Decimal,
+    pub rot_deduction: Decimal,
+    pub rut_deduction: Decimal,
+    pub brokerage: Decimal,
+    pub brokerage_vat: Decimal,
+    pub payouts_received: Decimal,
+    pub payouts_pending: Decimal,
+
+    pub lines: Vec<StatementLine>,
+}
+
+impl Statement {
+    fn new(craftsman: &Craftsman, year: i32, lines: Vec<StatementLine>, tasks: &[Task]) -> Self {
+        let mut statement = Statement {
+            year,
+            craftsman_id: craftsman.id.clone(),
+            company_name: craftsman.company_name.clone(),
+            org_number: craftsman.org_number.clone(),
+            craftsman_name: craftsman.craftsman_name.clone(),
+            completed_tasks: tasks
+                .iter()
+                .filter(|t| t.finished && lines.iter().any(|l| l.task_id == t.id))
+                .count(),
+            gross_amount: Decimal::zero(),
+            vat: Decimal::zero(),
+            rot_deduction: Decimal::zero(),
+            rut_deduction: Decimal::zero(),
+            brokerage: Decimal::zero(),
+            brokerage_vat: Decimal::zero(),
+            payouts_received: Decimal::zero(),
+            payouts_pending: Decimal::zero(),
+            lines: Vec::new(),
+        };
+        for line in lines.iter() {
+            statement.gross_amount += line.gross_amount;
+            statement.vat += line.vat;
+            match line.deduction_type {
+                Some(DeductionType::Rot) => statement.rot_deduction += line.root_deduction,
+                Some(DeductionType::Rut) => statement.rut_deduction += line.root_deduction,
+                None => {}
+            }
+            statement.brokerage += line.brokerage;
+            statement.brokerage_vat += line.brokerage_vat;
+            if line.paid_out {
+                statement.payouts_received += line.payout;
+            } else {
+                statement.payouts_pending += line.payout;
+            }
+        }
+        statement.lines = lines;
+        statement
+    }
+}
+
+// Fetches everything with the given ids in one query
+async fn by_ids<T: serde::de::DeserializeOwned>(
+    collection: &str,
+    office_id: &str,
+    ids: &[&String],
+) -> Result<Vec<T>, warp::Rejection> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut ids: Vec<String> = ids.iter().map(|id| format!(r#""{}""#, id)).collect();
+    ids.sort();
+    ids.dedup();
+    let q = format!(
+        r#"SELECT * FROM {} c WHERE ARRAY_CONTAINS([{}], c.id)"#,
+        collection,
+        ids.join(",")
+    );
+    Ok(query(collection, [office_id], q, -1).await?)
+}
+
+// Builds the statement of a craftsman from the payments that were released to them during the
+// year. A payment belongs to the year it was paid by the customer, the same as for the ROT claim.
+pub async fn craftsman_statement(
+    office_id: &str,
+    craftsman_id: &str,
+    year: i32,
+) -> Result<Statement, warp::Rejection> {
+    let q = format!(
+        r#"SELECT * FROM {} p WHERE p.craftsmanId = "{}" AND (p.paymentState = "finalized" OR p.paymentState = "paidToCraftsman") AND p.paymentDate >= "{}" AND p.paymentDate < "{}""#,
+        PAYMENT_COLLECTION,
+        craftsman_id,
+        year - 1,
+        year + 2
+    );
+    let (c, o, p) = tokio::join!(
+        get(CRAFTSMAN_COLLECTION, [office_id], craftsman_id),
+        get(OFFICE_COLLECTION, [office_id], office_id),
+        query(PAYMENT_COLLECTION, [office_id], q, -1)
+    );
+    let (craftsman, _): (Craftsman, _) = c?;
+    let (office, _): (Office, _) = o?;
+    let payments: Vec<Payment> = p?;
+
+    let tz: Tz = Stockholm;
+    let payments: Vec<(NaiveDate, Payment)> = payments
+        .into_iter()
+        .filter(|p| !p.deleted)
+        .filter_map(|p| {
+            let date = p.payment_date?.with_timezone(&tz).naive_local().date();
+            if date.year() == year {
+                Some((date, p))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let task_ids: Vec<&String> = payments.iter().map(|(_, p)| &p.task_id).collect();
+    let bid_ids: Vec<&String> = payments.iter().map(|(_, p)| &p.bid_id).collect();
+    let change_order_ids: Vec<&String> = payments
+        .iter()
+        .filter_map(|(_, p)| p.change_order_id.as_ref())
+        .collect();
+    let (t, b, co) = tokio::join!(
+        by_ids(TASK_COLLECTION, office_id, &task_ids),
+        by_ids(BID_COLLECTION, office_id, &bid_ids),
+        by_ids(CHANGE_ORDER_COLLECTION, office_id, &change_order_ids)
+    );
+    let tasks: Vec<Task> = t?;
+    let bids: HashMap<String, Bid> = b?.into_iter().map(|b: Bid| (b.id.clone(), b)).collect();
+    let change_orders: HashMap<String, ChangeOrder> = co?
+        .into_iter()
+        .map(|c: ChangeOrder| (c.id.clone(), c))
+        .collect();
+
+    let mut lines = Vec::new();
+    for (date, payment) in payments.iter() {
+        let (task, bid) = match (
+            tasks.iter().find(|t| t.id == payment.task_id),
+            bids.get(&payment.bid_id),
+        ) {
+            (Some(task), Some(bid)) => (task, bid),
+            _ => continue,
+        };
+        let change_order = payment
+            .change_order_id
+            .as_ref()
+            .and_then(|id| change_orders.get(id));
+
+        // NOTE: What was refunded on a partial refund never reached the craftsman
+        let kept = payment.gross_amount() - payment.refunded_amount;
+        let costs = payment.costs(bid, change_order).part(kept);
+        let (fee_ex_vat, fee_vat) = ledger::brokerage(
+            &costs,
+            payment.brokerage_percentage(&office),
+            payment.tax_date(),
+        );
+        let gross_amount = costs.final_amount + costs.root_deduction;
+
+        lines.push(StatementLine {
+            payment_id: payment.id.clone(),
+            task_id: task.id.clone(),
+            task_title: task.title.clone(),
+            date: *date,
+            part: match change_order {
+                Some(c) => Some(format!("ÄTA-arbete: {}", c.description)),
+                None => payment
+                    .milestone_id
+                    .as_ref()
+                    .and_then(|id| bid.milestone(id))
+                    .map(|m| format!("Delbetalning: {}", m.description)),
+            },
+            gross_amount,
+            vat: (gross_amount - costs.labour_cost - costs.material_cost)
+                .round_dp_with_strategy(2, RoundingStrategy::BankersRounding),
+            deduction_type: if costs.root_deduction.is_zero() {
+                None
+            } else {
+                Some(payment.deduction_type.unwrap_or(DeductionType::Rot))
+            },
+            root_deduction: costs.root_deduction,
+            brokerage: fee_ex_vat + fee_vat,
+            brokerage_vat: fee_vat,
+            payout: kept - fee_ex_vat - fee_vat,
+            paid_out: matches!(payment.payment_state, PaymentState::PaidToCraftsman),
+        });
+    }
+    lines.sort_by(|a, b| a.date.cmp(&b.date));
+
+    Ok(Statement::new(&craftsman, year, lines, &tasks))
+}
@@ -0,0 +1,9 @@
+// The yearly statements craftsmen get of what went through Toolit: the work they were paid for,
+// the vat and deductions on it, the brokerage we withheld and what we paid out. A statement can be
+// read as json or exported as a pdf or a csv file.
+
+mod content;
+pub use content::craftsman_statement;
+
+mod render;
+pub use render::{statement_csv, statement_pdf};
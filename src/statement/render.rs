@@ -0,0 +1,210 @@
+use super::content::Statement;
+use crate::pdf::{text_width, Document, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::receipt::kronor;
+use rust_decimal::prelude::{Decimal, RoundingStrategy};
+
+const MARGIN: f32 = 56.0;
+// MAGIC NUMBER: The distance between the right edges of the amount columns of the table
+const AMOUNT_COLUMN: f32 = 62.0;
+
+// Cuts text so it fits in the given width, marking that it was cut
+fn fit(text: &str, size: f32, font: Font, width: f32) -> String {
+    if text_width(text, size, font) <= width {
+        return text.to_string();
+    }
+    let mut fitted = String::new();
+    for c in text.chars() {
+        if text_width(&format!("{}{}...", fitted, c), size, font) > width {
+            break;
+        }
+        fitted.push(c);
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+// Renders the statement as a pdf with a summary of the year followed by every payment
+pub fn statement_pdf(statement: &Statement) -> Vec<u8> {
+    let mut document = Document::new(&format!(
+        "Årsbesked {} {}",
+        statement.year, statement.company_name
+    ));
+    let mut page = Page::new();
+    let right = PAGE_WIDTH - MARGIN;
+    let mut y = PAGE_HEIGHT - MARGIN - 16.0;
+
+    page.text(
+        MARGIN,
+        y,
+        22.0,
+        Font::Bold,
+        &format!("Årsbesked {}", statement.year),
+    );
+    page.text_right(right, y, 10.0, Font::Regular, "Toolit");
+    y -= 28.0;
+    for line in [
+        statement.company_name.clone(),
+        format!("Org.nr: {}", statement.org_number),
+        format!("Kontaktperson: {}", statement.craftsman_name),
+    ]
+    .iter()
+    {
+        page.text(MARGIN, y, 10.0, Font::Regular, line);
+        y -= 14.0;
+    }
+    y -= 20.0;
+
+    page.text(MARGIN, y, 11.0, Font::Bold, "Sammanställning");
+    y -= 8.0;
+    page.line(MARGIN, y, right, y);
+    y -= 16.0;
+    let rows = [
+        ("Avslutade uppdrag", statement.completed_tasks.to_string()),
+        ("Belopp inkl. moms", kronor(statement.gross_amount)),
+        ("Varav moms", kronor(statement.vat)),
+        ("ROT-avdrag", kronor(statement.rot_deduction)),
+        ("RUT-avdrag", kronor(statement.rut_deduction)),
+        ("Förmedlingsavgift inkl. moms", kronor(statement.brokerage)),
+        (
+            "Varav moms på förmedlingsavgift",
+            kronor(statement.brokerage_vat),
+        ),
+        ("Utbetalt", kronor(statement.payouts_received)),
+        ("Ej utbetalt", kronor(statement.payouts_pending)),
+    ];
+    for (name, value) in rows.iter() {
+        page.text(MARGIN, y, 10.0, Font::Regular, name);
+        page.text_right(right, y, 10.0, Font::Regular, value);
+        y -= 16.0;
+    }
+    y -= 20.0;
+
+    // The table of payments, which continues on new pages as long as it needs to
+    let headers = ["Belopp", "Moms", "Avdrag", "Avgift", "Utbetalning"];
+    let title_width = right - AMOUNT_COLUMN * headers.len() as f32 - MARGIN - 60.0;
+    let table_header = |page: &mut Page, y: f32| {
+        page.text(MARGIN, y, 9.0, Font::Bold, "Datum");
+        page.text(MARGIN + 56.0, y, 9.0, Font::Bold, "Uppdrag");
+        for (i, header) in headers.iter().enumerate() {
+            let x = right - AMOUNT_COLUMN * (headers.len() - 1 - i) as f32;
+            page.text_right(x, y, 9.0, Font::Bold, header);
+        }
+        page.line(MARGIN, y - 6.0, right, y - 6.0);
+        y - 20.0
+    };
+    page.text(MARGIN, y, 11.0, Font::Bold, "Betalningar");
+    y -= 20.0;
+    y = table_header(&mut page, y);
+    for line in statement.lines.iter() {
+        if y < MARGIN {
+            document.add_page(page);
+            page = Page::new();
+            y = table_header(&mut page, PAGE_HEIGHT - MARGIN);
+        }
+        let title = match &line.part {
+            Some(part) => format!("{} ({})", line.task_title, part),
+            None => line.task_title.clone(),
+        };
+        page.text(
+            MARGIN,
+            y,
+            8.0,
+            Font::Regular,
+            &line.date.format("%Y-%m-%d").to_string(),
+        );
+        page.text(
+            MARGIN + 56.0,
+            y,
+            8.0,
+            Font::Regular,
+            &fit(&title, 8.0, Font::Regular, title_width),
+        );
+        let amounts = [
+            line.gross_amount,
+            line.vat,
+            line.root_deduction,
+            line.brokerage,
+            line.payout,
+        ];
+        for (i, amount) in amounts.iter().enumerate() {
+            let x = right - AMOUNT_COLUMN * (amounts.len() - 1 - i) as f32;
+            page.text_right(x, y, 8.0, Font::Regular, &kronor(*amount));
+        }
+        y -= 14.0;
+    }
+    if statement.lines.is_empty() {
+        page.text(
+            MARGIN,
+            y,
+            9.0,
+            Font::Regular,
+            "Inga betalningar under året.",
+        );
+    }
+
+    document.add_page(page);
+    document.render()
+}
+
+// An amount with a decimal comma and without the currency, which is what spreadsheets in Sweden
+// expect
+fn csv_amount(amount: Decimal) -> String {
+    format!(
+        "{:.2}",
+        amount.round_dp_with_strategy(2, RoundingStrategy::BankersRounding)
+    )
+    .replace('.', ",")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ';' || c == '"' || c == '\n') {
+        format!(r#""{}""#, field.replace('"', r#""""#))
+    } else {
+        field.to_string()
+    }
+}
+
+// Renders the statement as a semicolon separated csv file with one row per payment
+pub fn statement_csv(statement: &Statement) -> String {
+    let mut rows = vec![[
+        "Datum",
+        "Betalning",
+        "Uppdrag",
+        "Del",
+        "Belopp inkl. moms",
+        "Moms",
+        "Avdragstyp",
+        "Avdrag",
+        "Förmedlingsavgift inkl. moms",
+        "Moms på förmedlingsavgift",
+        "Utbetalning",
+        "Utbetald",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect::<Vec<_>>()];
+    for line in statement.lines.iter() {
+        rows.push(vec![
+            line.date.format("%Y-%m-%d").to_string(),
+            line.payment_id.clone(),
+            csv_field(&line.task_title),
+            csv_field(line.part.as_deref().unwrap_or("")),
+            csv_amount(line.gross_amount),
+            csv_amount(line.vat),
+            match line.deduction_type {
+                Some(deduction_type) => format!("{:?}", deduction_type).to_uppercase(),
+                None => String::new(),
+            },
+            csv_amount(line.root_deduction),
+            csv_amount(line.brokerage),
+            csv_amount(line.brokerage_vat),
+            csv_amount(line.payout),
+            String::from(if line.paid_out { "Ja" } else { "Nej" }),
+        ]);
+    }
+    let mut file = String::new();
+    for row in rows.iter() {
+        file.push_str(&row.join(";"));
+        file.push_str("\r\n");
+    }
+    file
+}
@@ -0,0 +1,95 @@
+use super::TaxRules;
+use crate::models::{DeductionType, Task, TaxDeductionUsage};
+use crate::TAX_DEDUCTION_COLLECTION;
+use chrono::{Datelike, NaiveDate, Utc};
+use cosmos_utils::{get, insert, modify, CosmosErrorKind};
+use rust_decimal::Decimal;
+use warp::reject;
+
+// Returns what the customer has used so far during the year, a customer without any deductions
+// does not have a document yet
+pub async fn deduction_usage(
+    user_id: &str,
+    year: i32,
+) -> Result<TaxDeductionUsage, warp::Rejection> {
+    match get(
+        TAX_DEDUCTION_COLLECTION,
+        [user_id],
+        TaxDeductionUsage::id(user_id, year),
+    )
+    .await
+    {
+        Ok((usage, _)) => Ok(usage),
+        Err(e) => match e.kind {
+            CosmosErrorKind::NotFound => Ok(TaxDeductionUsage::new(user_id, year)),
+            _ => Err(reject::custom(e)),
+        },
+    }
+}
+
+// Adds to (or with a negative amount removes from) the deduction the customer has used during the
+// year
+pub async fn record_deduction(
+    user_id: &str,
+    year: i32,
+    deduction_type: DeductionType,
+    amount: Decimal,
+) -> Result<(), warp::Rejection> {
+    let id = TaxDeductionUsage::id(user_id, year);
+    // NOTE: We try twice in case someone else creates the document between our modify and insert
+    for _ in 0..2usize {
+        let e = match modify(
+            TAX_DEDUCTION_COLLECTION,
+            [user_id],
+            &id,
+            |mut usage: TaxDeductionUsage| {
+                usage.add(deduction_type, amount);
+                usage.modified = Utc::now();
+                Ok(usage)
+            },
+        )
+        .await
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        match e.kind {
+            CosmosErrorKind::NotFound => {
+                let mut usage = TaxDeductionUsage::new(user_id, year);
+                usage.add(deduction_type, amount);
+                match insert(TAX_DEDUCTION_COLLECTION, [user_id], &usage, None).await {
+                    Ok(_) => return Ok(()),
+                    Err(e) => match e.kind {
+                        CosmosErrorKind::Conflict => continue,
+                        _ => return Err(reject::custom(e)),
+                    },
+                }
+            }
+            _ => return Err(reject::custom(e)),
+        }
+    }
+    Err(reject::custom(crate::fault::Fault::IllegalState(format!(
+        "Could not record the deduction for user {} in {}",
+        user_id, year
+    ))))
+}
+
+// The deduction a task is eligible for together with how much of it the task owner has left in the
+// year of the given date, which is the date the payment is made on since that is the year the
+// deduction counts towards
+pub async fn task_deduction(
+    rules: &TaxRules,
+    task: &Task,
+    date: NaiveDate,
+) -> Result<(Option<DeductionType>, Option<Decimal>), warp::Rejection> {
+    match rules.task_deduction(task) {
+        Some(deduction_type) => {
+            let usage = deduction_usage(&task.user_id, date.year()).await?;
+            Ok((
+                Some(deduction_type),
+                Some(rules.remaining_allowance(&usage, deduction_type)),
+            ))
+        }
+        None => Ok((None, None)),
+    }
+}
@@ -0,0 +1,209 @@
+use super::TaxRules;
+use crate::models::DeductionType;
+use rust_decimal::prelude::{Decimal, RoundingStrategy, Zero};
+use std::cmp::min;
+use std::fmt;
+
+// The price components of a bid or anything else that is paid through escrow
+#[derive(Debug, Clone)]
+pub struct Costs {
+    // Labour cost without the tax added
+    pub labour_cost: Decimal,
+    // The part of the labour cost that gets the deduction, usually all of it
+    pub deductible_labour_cost: Decimal,
+    // Material cost without the tax added
+    pub material_cost: Decimal,
+    // Total added tax
+    pub vat: Decimal,
+    // The total root deduction
+    pub root_deduction: Decimal,
+    // The total price including the tax and removing the root deduction
+    pub final_amount: Decimal,
+    // Sold without vat to a business that reports the vat itself
+    pub reverse_charge: bool,
+}
+
+impl Costs {
+    // The costs of a part of the final amount, e.g. what is left of a payment after a partial
+    // refund
+    pub fn part(&self, amount: Decimal) -> Costs {
+        if self.final_amount.is_zero() {
+            return self.clone();
+        }
+        let share = |cost: Decimal| {
+            (cost * amount / self.final_amount)
+                .round_dp_with_strategy(2, RoundingStrategy::BankersRounding)
+        };
+        Costs {
+            labour_cost: share(self.labour_cost),
+            deductible_labour_cost: share(self.deductible_labour_cost),
+            material_cost: share(self.material_cost),
+            vat: share(self.vat),
+            root_deduction: share(self.root_deduction),
+            final_amount: amount,
+            reverse_charge: self.reverse_charge,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostComponent {
+    FinalAmount,
+    Vat,
+    RootDeduction,
+}
+
+#[derive(Debug, Clone)]
+pub struct CostError {
+    pub component: CostComponent,
+    pub expected: Decimal,
+    pub actual: Decimal,
+}
+
+impl fmt::Display for CostError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let component = match self.component {
+            CostComponent::FinalAmount => "final price",
+            CostComponent::Vat => "vat",
+            CostComponent::RootDeduction => "ROT/RUT deduction",
+        };
+        write!(
+            f,
+            "The {} should have been {} but it was {}",
+            component, self.expected, self.actual
+        )
+    }
+}
+
+// Calculates what the vat, deduction and final amount should be for the given labour and material
+// costs, where only the deductible part of the labour gets the deduction. The deduction never
+// exceeds the customer's remaining yearly allowance when one is given. Under reverse charge there
+// is neither vat nor deduction. We round to 2 decimals at every step.
+pub fn expected_costs(
+    labour_cost: Decimal,
+    deductible_labour_cost: Decimal,
+    material_cost: Decimal,
+    rules: &TaxRules,
+    deduction: Option<DeductionType>,
+    allowance: Option<Decimal>,
+    reverse_charge: bool,
+) -> Costs {
+    let labour_cost = labour_cost.round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let deductible_labour_cost = min(
+        deductible_labour_cost.round_dp_with_strategy(2, RoundingStrategy::BankersRounding),
+        labour_cost,
+    );
+    let material_cost = material_cost.round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+
+    if reverse_charge {
+        return Costs {
+            labour_cost,
+            deductible_labour_cost,
+            material_cost,
+            vat: Decimal::zero(),
+            root_deduction: Decimal::zero(),
+            final_amount: labour_cost + material_cost,
+            reverse_charge,
+        };
+    }
+
+    let labour_cost_vat = (labour_cost * rules.vat_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let labour_cost_inc_vat = labour_cost + labour_cost_vat;
+    let deductible_vat = (deductible_labour_cost * rules.vat_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let deductible_inc_vat = deductible_labour_cost + deductible_vat;
+
+    let material_cost_vat = (material_cost * rules.vat_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let material_cost_inc_vat = material_cost + material_cost_vat;
+
+    let root_percentage = match deduction {
+        Some(d) => rules.deduction_percentage(d),
+        None => Decimal::zero(),
+    };
+    let full_deduction = (deductible_inc_vat * root_percentage)
+        .round_dp_with_strategy(2, RoundingStrategy::BankersRounding);
+    let (root_deduction, vat) = match allowance {
+        Some(allowance) if allowance < full_deduction => {
+            // NOTE: When the customer has reached the yearly ceiling only part of the labour gets
+            // the deduction, the vat is reduced by the same share
+            let root_deduction = min(
+                allowance.round_dp_with_strategy(2, RoundingStrategy::RoundDown),
+                full_deduction,
+            );
+            let share = if deductible_inc_vat.is_zero() {
+                Decimal::zero()
+            } else {
+                root_deduction / deductible_inc_vat
+            };
+            let vat = (labour_cost_vat - (deductible_vat * share))
+                .round_dp_with_strategy(2, RoundingStrategy::BankersRounding)
+                + material_cost_vat;
+            (root_deduction, vat)
+        }
+        _ => (
+            full_deduction,
+            (labour_cost_vat - (deductible_vat * root_percentage)) + material_cost_vat,
+        ),
+    };
+    let final_amount = labour_cost_inc_vat + material_cost_inc_vat - root_deduction;
+
+    Costs {
+        labour_cost,
+        deductible_labour_cost,
+        material_cost,
+        vat,
+        root_deduction,
+        final_amount,
+        reverse_charge,
+    }
+}
+
+// The hours of labour are reported to the tax agency with a ROT or RUT claim, so they have to be
+// given whenever the customer gets a deduction
+pub fn has_labour_hours(deduction: Option<DeductionType>, labour_hours: Option<Decimal>) -> bool {
+    deduction.is_none() || labour_hours.is_some_and(|hours| hours > Decimal::zero())
+}
+
+// Makes sure the final amount, vat and deduction match the labour and material costs, reporting
+// the first component that is wrong
+pub fn verify_cost(
+    costs: &Costs,
+    rules: &TaxRules,
+    deduction: Option<DeductionType>,
+    allowance: Option<Decimal>,
+) -> Result<(), CostError> {
+    let expected = expected_costs(
+        costs.labour_cost,
+        costs.deductible_labour_cost,
+        costs.material_cost,
+        rules,
+        deduction,
+        allowance,
+        costs.reverse_charge,
+    );
+    let checks = [
+        (
+            CostComponent::RootDeduction,
+            expected.root_deduction,
+            costs.root_deduction,
+        ),
+        (CostComponent::Vat, expected.vat, costs.vat),
+        (
+            CostComponent::FinalAmount,
+            expected.final_amount,
+            costs.final_amount,
+        ),
+    ];
+    for (component, expected, actual) in checks.iter() {
+        if expected != actual {
+            return Err(CostError {
+                component: *component,
+                expected: *expected,
+                actual: *actual,
+            });
+        }
+    }
+    Ok(())
+}
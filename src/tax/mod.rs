@@ -0,0 +1,26 @@
+// Swedish tax rules used when pricing work: vat and the ROT and RUT deductions on labour together
+// with the yearly ceilings for how much deduction a person can get.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::{Europe::Stockholm, Tz};
+
+mod rules;
+pub use rules::{rules_at, TaxRules};
+
+mod cost;
+pub use cost::{has_labour_hours, verify_cost, CostError, Costs};
+
+mod allowance;
+pub use allowance::{deduction_usage, record_deduction, task_deduction};
+
+// Today's date in Sweden, which is what decides the rules and the year of a deduction
+pub fn today() -> NaiveDate {
+    let tz: Tz = Stockholm;
+    chrono::Utc::now().with_timezone(&tz).naive_local().date()
+}
+
+// The date in Sweden at the given moment
+pub fn date_of(at: DateTime<Utc>) -> NaiveDate {
+    let tz: Tz = Stockholm;
+    at.with_timezone(&tz).naive_local().date()
+}
@@ -0,0 +1,136 @@
+use crate::models::{CraftType, DeductionType, Task, TaxDeductionUsage};
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use rust_decimal::{prelude::Zero, Decimal};
+use std::cmp::min;
+
+// A set of tax rules that applies from a given date until the next set takes over
+#[derive(Debug, Clone)]
+pub struct TaxRules {
+    pub effective_from: NaiveDate,
+
+    pub vat_percentage: Decimal,
+
+    // Share of the labour cost including vat that can be deducted
+    pub rot_percentage: Decimal,
+    pub rut_percentage: Decimal,
+
+    // The most ROT deduction a person can get in a year
+    pub rot_yearly_cap: Decimal,
+    // The most ROT and RUT deduction combined a person can get in a year
+    pub total_yearly_cap: Decimal,
+}
+
+lazy_static! {
+    // NOTE: Keep these sorted by the date they take effect. Add a new set when the rules change
+    // instead of editing an old one, bids and payments from before the change are still validated
+    // against the rules that applied then.
+    static ref RULE_SETS: Vec<TaxRules> = vec![
+        TaxRules {
+            effective_from: NaiveDate::from_ymd(2016, 1, 1),
+            vat_percentage: Decimal::new(25, 2),
+            rot_percentage: Decimal::new(30, 2),
+            rut_percentage: Decimal::new(50, 2),
+            rot_yearly_cap: Decimal::new(50000, 0),
+            total_yearly_cap: Decimal::new(50000, 0),
+        },
+        TaxRules {
+            effective_from: NaiveDate::from_ymd(2021, 1, 1),
+            vat_percentage: Decimal::new(25, 2),
+            rot_percentage: Decimal::new(30, 2),
+            rut_percentage: Decimal::new(50, 2),
+            rot_yearly_cap: Decimal::new(50000, 0),
+            total_yearly_cap: Decimal::new(75000, 0),
+        },
+        // NOTE: ROT was raised to 50% for work paid from the 12th of May 2025 until the end of
+        // the year
+        TaxRules {
+            effective_from: NaiveDate::from_ymd(2025, 5, 12),
+            vat_percentage: Decimal::new(25, 2),
+            rot_percentage: Decimal::new(50, 2),
+            rut_percentage: Decimal::new(50, 2),
+            rot_yearly_cap: Decimal::new(50000, 0),
+            total_yearly_cap: Decimal::new(75000, 0),
+        },
+        TaxRules {
+            effective_from: NaiveDate::from_ymd(2026, 1, 1),
+            vat_percentage: Decimal::new(25, 2),
+            rot_percentage: Decimal::new(30, 2),
+            rut_percentage: Decimal::new(50, 2),
+            rot_yearly_cap: Decimal::new(50000, 0),
+            total_yearly_cap: Decimal::new(75000, 0),
+        },
+    ];
+}
+
+// Returns the rule set in effect on the given date
+pub fn rules_at(date: NaiveDate) -> &'static TaxRules {
+    RULE_SETS
+        .iter()
+        .rev()
+        .find(|r| r.effective_from <= date)
+        .unwrap_or(&RULE_SETS[0])
+}
+
+// The deduction the work of a craft falls under, which does not change with the rules
+pub fn deduction_type(craft: &CraftType) -> DeductionType {
+    match craft {
+        CraftType::Plumber
+        | CraftType::Carpenter
+        | CraftType::Electrician
+        | CraftType::Painter
+        | CraftType::FloorLayer
+        | CraftType::Tiler => DeductionType::Rot,
+    }
+}
+
+impl TaxRules {
+    pub fn deduction_percentage(&self, deduction_type: DeductionType) -> Decimal {
+        match deduction_type {
+            DeductionType::Rot => self.rot_percentage,
+            DeductionType::Rut => self.rut_percentage,
+        }
+    }
+
+    // The deduction that applies to a task, if any. A task that mixes crafts is treated as ROT
+    // since that is the deduction with the lower percentage.
+    pub fn task_deduction(&self, task: &Task) -> Option<DeductionType> {
+        if !task.use_rot_rut || task.crafts.is_empty() || self.reverse_charge(task) {
+            return None;
+        }
+        if task
+            .crafts
+            .iter()
+            .any(|c| deduction_type(c) == DeductionType::Rot)
+        {
+            Some(DeductionType::Rot)
+        } else {
+            Some(DeductionType::Rut)
+        }
+    }
+
+    // Construction services sold to a business are charged without vat, the buyer reports the vat
+    // instead ("omvänd betalningsskyldighet"). NOTE: Every craft on offer is a construction
+    // service, a craft that is not has to be left out here when it is added.
+    pub fn reverse_charge(&self, task: &Task) -> bool {
+        task.customer_type.is_business() && !task.crafts.is_empty()
+    }
+
+    // How much more deduction of the given type the customer can get this year
+    pub fn remaining_allowance(
+        &self,
+        usage: &TaxDeductionUsage,
+        deduction_type: DeductionType,
+    ) -> Decimal {
+        let total_left = self.total_yearly_cap - usage.rot - usage.rut;
+        let left = match deduction_type {
+            DeductionType::Rot => min(self.rot_yearly_cap - usage.rot, total_left),
+            DeductionType::Rut => total_left,
+        };
+        if left < Decimal::zero() {
+            Decimal::zero()
+        } else {
+            left
+        }
+    }
+}